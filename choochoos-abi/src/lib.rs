@@ -15,23 +15,79 @@
 
 use serde::{Deserialize, Serialize};
 
-/// A task identifier.
+/// Defines a FFI-safe newtype around `usize`, which encodes both the index of
+/// an object's slot in one of the kernel's tables (the low 16 bits), and the
+/// slot's _generation_ (the next 15 bits).
 ///
-/// This is a FFI-safe newtype around `usize`.
-#[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash, Deserialize, Serialize)]
-#[repr(transparent)]
-pub struct Tid(usize);
-
-impl Tid {
-    /// Create a new Tid from a raw value.
-    pub fn from(val: usize) -> Tid {
-        Tid(val)
-    }
+/// The top bit is always clear, so a raw identifier is never mistaken for a
+/// (negative) syscall error code.
+macro_rules! generational_id {
+    ($(#[$meta:meta])* pub struct $name:ident;) => {
+        $(#[$meta])*
+        #[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+        #[repr(transparent)]
+        pub struct $name(usize);
 
-    /// Return the Tid's raw value.
-    pub fn into(self) -> usize {
-        self.0
-    }
+        impl $name {
+            const SLOT_BITS: usize = 16;
+            const SLOT_MASK: usize = (1 << Self::SLOT_BITS) - 1;
+
+            /// The largest possible generation number. Generations wrap around
+            /// to zero once this value is exceeded.
+            pub const MAX_GENERATION: usize = (1 << 15) - 1;
+
+            #[doc = concat!("Create a new ", stringify!($name), " from a raw value.")]
+            pub fn from(val: usize) -> $name {
+                $name(val)
+            }
+
+            #[doc = concat!("Return the ", stringify!($name), "'s raw value.")]
+            pub fn into(self) -> usize {
+                self.0
+            }
+
+            #[doc = concat!("Create a new ", stringify!($name), " from a slot index and generation.")]
+            pub fn new(slot: usize, generation: usize) -> $name {
+                debug_assert!(slot <= Self::SLOT_MASK);
+                debug_assert!(generation <= Self::MAX_GENERATION);
+                $name((generation << Self::SLOT_BITS) | slot)
+            }
+
+            /// Return the index of the slot in the kernel's table.
+            pub fn slot(self) -> usize {
+                self.0 & Self::SLOT_MASK
+            }
+
+            /// Return the generation of the slot.
+            pub fn generation(self) -> usize {
+                (self.0 >> Self::SLOT_BITS) & Self::MAX_GENERATION
+            }
+        }
+    };
+}
+
+generational_id! {
+    /// A task identifier.
+    ///
+    /// This is a FFI-safe newtype around `usize`, which encodes both the index
+    /// of the task's slot in the kernel's task table (the low 16 bits), and the
+    /// slot's _generation_ (the next 15 bits). A slot's generation is
+    /// incremented whenever its task exits, which allows the kernel to reject
+    /// stale Tids instead of silently referring to whichever task reused the
+    /// slot.
+    ///
+    /// The top bit is always clear, so a raw Tid is never mistaken for a
+    /// (negative) syscall error code.
+    pub struct Tid;
+}
+
+generational_id! {
+    /// A mailbox identifier (as returned by the `MailboxCreate` syscall).
+    ///
+    /// Like [`Tid`], this encodes both the index of the mailbox's slot in the
+    /// kernel's mailbox table, and the slot's generation, so that the kernel
+    /// can reject stale identifiers of destroyed mailboxes.
+    pub struct MailboxId;
 }
 
 /// Tid of the kernel-spawned name server.
@@ -44,16 +100,109 @@ impl Tid {
 /// as the specific name server message protocol is not stable, and may change
 /// at any time. As such, tasks should only use the provided name server API,
 /// which is guaranteed to remain stable.
-pub const NAMESERVER_TID: Tid = Tid(1);
+pub const NAMESERVER_TID: Tid = Tid(1); // slot 1, generation 0
+
+/// Number of distinct task priorities.
+///
+/// Valid priorities range from `0` (lowest) to `NUM_PRIORITIES - 1` (highest).
+pub const NUM_PRIORITIES: usize = 32;
+
+/// Version of the [`PerfData`] and [`TaskPerfData`] structures.
+///
+/// Bumped whenever the layout of either structure changes. The kernel writes
+/// this value into each structure's `version` field, which should be checked
+/// before interpreting any of the other fields.
+pub const PERF_VERSION: u32 = 4;
 
 /// Container for various bits of kernel performance data returned as part of
 /// the `Perf` syscall.
-// TODO: improve `struct PerfData`.
 #[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash)]
 #[repr(C)]
 pub struct PerfData {
-    /// A number from 0 - 100
+    /// Always set to [`PERF_VERSION`].
+    pub version: u32,
+    /// Percentage of time (0 - 100) the kernel has spent idle since boot.
     pub idle_time_pct: u32,
+    /// Percentage of time (0 - 100) the kernel has spent idle over the last
+    /// second (or so).
+    pub idle_time_pct_window: u32,
+    /// Number of interrupts whose volatile data was dropped since boot, due
+    /// to no task calling `AwaitEvent` in time.
+    pub dropped_events: u32,
+    /// Total time spent handling interrupts since boot (in microseconds).
+    pub irq_time_us: u64,
+}
+
+/// Container for per-task performance data returned as part of the `PerfTask`
+/// syscall.
+///
+/// All times are in microseconds, and are measured since the task was created.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[repr(C)]
+pub struct TaskPerfData {
+    /// Always set to [`PERF_VERSION`].
+    pub version: u32,
+    /// Number of times the task has been scheduled.
+    pub context_switches: u32,
+    /// Number of valid entries in `syscalls` (i.e: the kernel's
+    /// [`NUM_SYSCALLS`](syscall::NUM_SYSCALLS)).
+    pub num_syscalls: u32,
+    /// Number of syscalls issued by the task, indexed by
+    /// [`SyscallNo`](syscall::SyscallNo). Entries past `num_syscalls` are
+    /// always zero.
+    pub syscalls: [u32; syscall::MAX_SYSCALLS],
+    /// Time spent running (including time spent in the kernel servicing the
+    /// task's syscalls, but excluding time spent handling interrupts).
+    pub cpu_time_us: u64,
+    /// Time spent ready, but waiting to be scheduled.
+    pub ready_time_us: u64,
+    /// Time spent blocked in `Send`, waiting for the receiver to `Receive`.
+    pub send_wait_time_us: u64,
+    /// Time spent blocked in `Receive`, waiting for a sender.
+    pub recv_wait_time_us: u64,
+    /// Time spent blocked in `Send`, waiting for the receiver to `Reply`.
+    pub reply_wait_time_us: u64,
+    /// Time spent blocked in `AwaitEvent`.
+    pub event_wait_time_us: u64,
+    /// Time spent blocked in `Delay` / `DelayUntil`.
+    pub delay_wait_time_us: u64,
+    /// Time spent blocked in `MailboxReceive`, waiting for a message.
+    pub mailbox_wait_time_us: u64,
+}
+
+/// Kernel log verbosity levels (as used by the `SetLogLevel` syscall), ordered
+/// from least to most verbose.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash)]
+#[repr(usize)]
+pub enum LogLevel {
+    /// Disable logging.
+    Off   = 0,
+    /// Only log errors.
+    Error = 1,
+    /// Log warnings and errors.
+    Warn  = 2,
+    /// Log informational messages, warnings and errors.
+    Info  = 3,
+    /// Log debugging messages, and everything above.
+    Debug = 4,
+    /// Log everything (including very verbose tracing messages).
+    Trace = 5,
+}
+
+impl LogLevel {
+    /// Return enum corresponding to raw log level (if one exists).
+    pub fn from_usize(level: usize) -> Option<LogLevel> {
+        let level = match level {
+            0 => LogLevel::Off,
+            1 => LogLevel::Error,
+            2 => LogLevel::Warn,
+            3 => LogLevel::Info,
+            4 => LogLevel::Debug,
+            5 => LogLevel::Trace,
+            _ => return None,
+        };
+        Some(level)
+    }
 }
 
 /// Kernel syscall interface (i.e: syscall numbers, signatures, error codes)
@@ -63,23 +212,51 @@ pub mod syscall {
     #[repr(u8)]
     #[allow(missing_docs)]
     pub enum SyscallNo {
-        Yield       = 0,
-        Exit        = 1,
-        MyParentTid = 2,
-        MyTid       = 3,
-        Create      = 4,
-        Send        = 5,
-        Receive     = 6,
-        Reply       = 7,
-        AwaitEvent  = 8,
-        Perf        = 9,
-        Shutdown    = 10,
+        Yield             = 0,
+        Exit              = 1,
+        MyParentTid       = 2,
+        MyTid             = 3,
+        Create            = 4,
+        Send              = 5,
+        Receive           = 6,
+        Reply             = 7,
+        AwaitEvent        = 8,
+        Perf              = 9,
+        Shutdown          = 10,
+        PerfTask          = 11,
+        Destroy           = 12,
+        StackUsage        = 13,
+        Abort             = 14,
+        ReadLog           = 15,
+        SetLogLevel       = 16,
+        ReadTrace         = 17,
+        DetectDeadlocks   = 18,
+        TrySend           = 19,
+        SendTimeout       = 20,
+        ReceiveTimeout    = 21,
+        Time              = 22,
+        Delay             = 23,
+        DelayUntil        = 24,
+        MailboxCreate     = 25,
+        MailboxPost       = 26,
+        MailboxReceive    = 27,
+        MailboxTryReceive = 28,
+        MailboxDestroy    = 29,
     }
 
+    /// Total number of syscalls.
+    pub const NUM_SYSCALLS: usize = 30;
+
+    /// Maximum number of syscalls which can be added without changing the
+    /// layout of [`TaskPerfData`](crate::TaskPerfData).
+    pub const MAX_SYSCALLS: usize = 64;
+
+    const _: () = assert!(NUM_SYSCALLS <= MAX_SYSCALLS);
+
     impl SyscallNo {
         /// Return enum corresponding to raw syscall number (if one exists).
         pub fn from_u8(no: u8) -> Option<SyscallNo> {
-            if no > 10 {
+            if no as usize >= NUM_SYSCALLS {
                 None
             } else {
                 // SAFETY: SyscallNo is repr(u8), and was checked to be in bounds
@@ -94,7 +271,7 @@ pub mod syscall {
     pub mod signature {
         #![allow(missing_docs)]
 
-        use crate::{PerfData, Tid};
+        use crate::{MailboxId, PerfData, TaskPerfData, Tid};
 
         pub type Yield = unsafe extern "C" fn();
         pub type Exit = unsafe extern "C" fn() -> !;
@@ -116,8 +293,79 @@ pub mod syscall {
 
         /// Custom - Query kernel-specific [`PerfData`]
         pub type Perf = unsafe extern "C" fn(perf: *mut PerfData);
+        /// Custom - Query per-task [`TaskPerfData`]
+        pub type PerfTask = unsafe extern "C" fn(tid: Tid, perf: *mut TaskPerfData) -> isize;
+        /// Custom - Forcefully terminate a task.
+        pub type Destroy = unsafe extern "C" fn(tid: Tid) -> isize;
+        /// Custom - Query a task's stack high-water mark (in bytes).
+        pub type StackUsage = unsafe extern "C" fn(tid: Tid) -> isize;
+        /// Custom - Drain output from the kernel log.
+        pub type ReadLog = unsafe extern "C" fn(buf: *mut u8, buflen: usize) -> isize;
+        /// Custom - Set the kernel log level of a module (and its children),
+        /// where `level` is a raw [`LogLevel`](crate::LogLevel).
+        pub type SetLogLevel =
+            unsafe extern "C" fn(module: *const u8, module_len: usize, level: usize) -> isize;
+        /// Custom - Drain records from the kernel trace buffer.
+        pub type ReadTrace =
+            unsafe extern "C" fn(buf: *mut crate::trace::Record, buflen: usize) -> isize;
+        /// Custom - Report any Send-Receive-Reply deadlocks to the kernel log,
+        /// and return the Tids of the deadlocked tasks.
+        pub type DetectDeadlocks = unsafe extern "C" fn(tids: *mut Tid, len: usize) -> isize;
+        /// Custom - `Send`, failing immediately if the receiver isn't
+        /// receive-blocked.
+        pub type TrySend = unsafe extern "C" fn(
+            tid: Tid,
+            msg: *const u8,
+            msglen: usize,
+            reply: *mut u8,
+            rplen: usize,
+        ) -> isize;
+        /// Custom - `Send`, failing if the message isn't received within
+        /// `timeout` ticks.
+        pub type SendTimeout = unsafe extern "C" fn(
+            tid: Tid,
+            msg: *const u8,
+            msglen: usize,
+            reply: *mut u8,
+            rplen: usize,
+            timeout: usize,
+        ) -> isize;
+        /// Custom - `Receive`, failing if no message is received within
+        /// `timeout` ticks.
+        pub type ReceiveTimeout = unsafe extern "C" fn(
+            tid: *mut Tid,
+            msg: *mut u8,
+            msglen: usize,
+            timeout: usize,
+        ) -> isize;
+        /// Custom - Query the number of kernel clock ticks since boot.
+        pub type Time = unsafe extern "C" fn() -> isize;
+        /// Custom - Block for `ticks` kernel clock ticks, returning the
+        /// current tick.
+        pub type Delay = unsafe extern "C" fn(ticks: isize) -> isize;
+        /// Custom - Block until the given kernel clock tick, returning the
+        /// current tick.
+        pub type DelayUntil = unsafe extern "C" fn(tick: usize) -> isize;
+        /// Custom - Create a mailbox holding up to `count` messages of up to
+        /// `slot_size` bytes each, owned by the calling task.
+        pub type MailboxCreate = unsafe extern "C" fn(slot_size: usize, count: usize) -> isize;
+        /// Custom - Post a message to a mailbox (without blocking).
+        pub type MailboxPost =
+            unsafe extern "C" fn(mailbox: MailboxId, msg: *const u8, msglen: usize) -> isize;
+        /// Custom - Receive the oldest message in a mailbox, blocking until
+        /// one is posted if the mailbox is empty.
+        pub type MailboxReceive =
+            unsafe extern "C" fn(mailbox: MailboxId, msg: *mut u8, msglen: usize) -> isize;
+        /// Custom - Receive the oldest message in a mailbox, failing if the
+        /// mailbox is empty.
+        pub type MailboxTryReceive =
+            unsafe extern "C" fn(mailbox: MailboxId, msg: *mut u8, msglen: usize) -> isize;
+        /// Custom - Destroy a mailbox owned by the calling task.
+        pub type MailboxDestroy = unsafe extern "C" fn(mailbox: MailboxId) -> isize;
         /// Custom - Terminate the kernel.
         pub type Shutdown = unsafe extern "C" fn() -> !;
+        /// Custom - Terminate the kernel, printing a crash dump.
+        pub type Abort = unsafe extern "C" fn() -> !;
     }
 
     /// Errors returned by various syscalls.
@@ -127,7 +375,9 @@ pub mod syscall {
         #[repr(isize)]
         pub enum MyParentTid {
             /// Task does not have a parent.
-            NoParent = -1,
+            NoParent        = -1,
+            /// The task's parent has exited.
+            TidDoesNotExist = -2,
         }
 
         /// Errors returned by the `Create` syscall
@@ -148,10 +398,29 @@ pub mod syscall {
         #[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash)]
         #[repr(isize)]
         pub enum Send {
-            /// `tid` is not the task id of an existing task.
-            TidDoesNotExist = -1,
+            /// `tid` is not the task id of an existing task (e.g: the task
+            /// has exited).
+            TidDoesNotExist    = -1,
             /// The send-receive-reply transaction could not be completed.
-            CouldNotSSR     = -2,
+            CouldNotSSR        = -2,
+            /// The receiver exited (or was destroyed) after receiving the
+            /// message, but before replying to it.
+            ReceiverTerminated = -3,
+            /// The receiver didn't receive the message in time (`TrySend` and
+            /// `SendTimeout` only).
+            Timeout            = -4,
+        }
+
+        /// Errors returned by the `ReceiveTimeout` syscall.
+        ///
+        /// Note that message Truncation does _not_ correspond to an error code,
+        /// and must be inferred by comparing the length of received message
+        /// with the expected length.
+        #[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash)]
+        #[repr(isize)]
+        pub enum Receive {
+            /// No message was sent to the task in time.
+            Timeout = -1,
         }
 
         /// Errors returned by the `Reply` syscall.
@@ -162,7 +431,8 @@ pub mod syscall {
         #[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash)]
         #[repr(isize)]
         pub enum Reply {
-            /// `tid` is not the task id of an existing task.
+            /// `tid` is not the task id of an existing task (e.g: the task
+            /// has exited).
             TidDoesNotExist      = -1,
             /// `tid` is not the task id of a reply-blocked task.
             TidIsNotReplyBlocked = -2,
@@ -174,8 +444,280 @@ pub mod syscall {
         pub enum AwaitEvent {
             /// Invalid event id.
             InvalidEventId        = -1,
-            /// Corrupted volatile data.
+            /// Volatile data from one or more occurrences of the event was
+            /// dropped (i.e: the event occurred too many times without being
+            /// waited on).
             CorruptedVolatileData = -2,
+            /// Too many tasks are already waiting on the event.
+            TooManyWaiters        = -3,
+        }
+
+        /// Errors returned by the `PerfTask` syscall.
+        #[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash)]
+        #[repr(isize)]
+        pub enum PerfTask {
+            /// `tid` is not the task id of an existing task.
+            TidDoesNotExist = -1,
+        }
+
+        /// Errors returned by the `Destroy` syscall.
+        #[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash)]
+        #[repr(isize)]
+        pub enum Destroy {
+            /// `tid` is not the task id of an existing task.
+            TidDoesNotExist  = -1,
+            /// `tid` is the calling task's task id (use `Exit` instead).
+            TidIsCurrentTask = -2,
+        }
+
+        /// Errors returned by the `StackUsage` syscall.
+        #[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash)]
+        #[repr(isize)]
+        pub enum StackUsage {
+            /// `tid` is not the task id of an existing task.
+            TidDoesNotExist = -1,
+            /// Stack usage isn't tracked on this platform.
+            Unsupported     = -2,
+        }
+
+        /// Errors returned by the `SetLogLevel` syscall.
+        #[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash)]
+        #[repr(isize)]
+        pub enum SetLogLevel {
+            /// `level` is not a valid [`LogLevel`](crate::LogLevel).
+            InvalidLevel   = -1,
+            /// The kernel has run out of space for per-module filters.
+            TooManyFilters = -2,
+            /// The module path is too long.
+            ModuleTooLong  = -3,
+            /// The module path is not valid UTF-8.
+            InvalidModule  = -4,
+        }
+
+        /// Errors returned by the `MailboxCreate` syscall.
+        #[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash)]
+        #[repr(isize)]
+        pub enum MailboxCreate {
+            /// `slot_size` or `count` is zero.
+            InvalidSize    = -1,
+            /// The kernel has run out of mailboxes.
+            OutOfMailboxes = -2,
+            /// The kernel's mailbox pool doesn't have enough free space.
+            OutOfMemory    = -3,
+        }
+
+        /// Errors returned by the `MailboxPost` syscall.
+        #[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash)]
+        #[repr(isize)]
+        pub enum MailboxPost {
+            /// `mailbox` does not refer to an existing mailbox.
+            MailboxDoesNotExist = -1,
+            /// The message is larger than the mailbox's slot size.
+            MessageTooLarge     = -2,
+            /// The mailbox is full.
+            Full                = -3,
+        }
+
+        /// Errors returned by the `MailboxReceive` and `MailboxTryReceive`
+        /// syscalls.
+        ///
+        /// Note that message Truncation does _not_ correspond to an error code,
+        /// and must be inferred by comparing the length of received message
+        /// with the expected length.
+        #[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash)]
+        #[repr(isize)]
+        pub enum MailboxReceive {
+            /// `mailbox` does not refer to an existing mailbox.
+            MailboxDoesNotExist = -1,
+            /// The mailbox is empty (`MailboxTryReceive` only).
+            Empty               = -2,
+            /// The mailbox was destroyed while waiting for a message.
+            MailboxDestroyed    = -3,
+        }
+
+        /// Errors returned by the `MailboxDestroy` syscall.
+        #[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash)]
+        #[repr(isize)]
+        pub enum MailboxDestroy {
+            /// `mailbox` does not refer to an existing mailbox.
+            MailboxDoesNotExist = -1,
+            /// The mailbox isn't owned by the calling task.
+            NotOwner            = -2,
+        }
+
+        /// Errors returned by the `Delay` and `DelayUntil` syscalls.
+        #[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash)]
+        #[repr(isize)]
+        pub enum Delay {
+            /// The kernel wasn't built with the `kernel-clock` feature.
+            ClockDisabled = -1,
+            /// `Delay` was called with a negative number of ticks.
+            NegativeDelay = -2,
+        }
+    }
+}
+
+/// Kernel trace records (as returned by the `ReadTrace` syscall).
+///
+/// When built with the `trace` feature, the kernel records a fixed-size
+/// [`Record`] for each scheduling, syscall, and interrupt event into a ring
+/// buffer, which can be drained using the `ReadTrace` syscall. Any undrained
+/// records are dumped (as hex, one record per line) when the kernel shuts
+/// down.
+pub mod trace {
+    /// Raw Tid used in records which aren't associated with a task (e.g: an
+    /// interrupt which occurred while the kernel was idle).
+    pub const NO_TID: u32 = u32::MAX;
+
+    /// Line printed before the records in a kernel trace dump.
+    pub const DUMP_BEGIN: &str = "[trace] begin";
+    /// Line printed after the records in a kernel trace dump.
+    pub const DUMP_END: &str = "[trace] end";
+
+    /// Kinds of trace events.
+    #[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash)]
+    #[repr(u8)]
+    pub enum EventKind {
+        /// The task was activated.
+        SwitchIn      = 0,
+        /// The task stopped running. `data[0]` is the task's new [`State`], and
+        /// `data[1]` is the raw Tid of the task it's blocked on (if any).
+        SwitchOut     = 1,
+        /// The task invoked the syscall `syscall_no`. `data` contains the
+        /// syscall's first four arguments.
+        Syscall       = 2,
+        /// The task's syscall `syscall_no` returned. `data[0]` is the return
+        /// value.
+        ///
+        /// This is recorded when the task resumes (i.e: right before the
+        /// following `SwitchIn`), so syscalls which never return (e.g: `Exit`)
+        /// don't have a corresponding `SyscallReturn`.
+        SyscallReturn = 3,
+        /// An interrupt occurred while the task was running (or while the
+        /// kernel was idle, in which case the Tid is [`NO_TID`]). `data[0]` is
+        /// the event id, and `data[1]` is the volatile data.
+        Irq           = 4,
+        /// The task was created. `data[0]` is the raw Tid of its parent (or
+        /// [`NO_TID`]), and `data[1]` is its priority.
+        TaskCreate    = 5,
+        /// The task exited (or was destroyed / killed).
+        TaskExit      = 6,
+    }
+
+    impl EventKind {
+        /// Return enum corresponding to raw event kind (if one exists).
+        pub fn from_u8(kind: u8) -> Option<EventKind> {
+            let kind = match kind {
+                0 => EventKind::SwitchIn,
+                1 => EventKind::SwitchOut,
+                2 => EventKind::Syscall,
+                3 => EventKind::SyscallReturn,
+                4 => EventKind::Irq,
+                5 => EventKind::TaskCreate,
+                6 => EventKind::TaskExit,
+                _ => return None,
+            };
+            Some(kind)
+        }
+    }
+
+    /// A task's state after being switched out.
+    #[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash)]
+    #[repr(u32)]
+    #[allow(missing_docs)]
+    pub enum State {
+        Ready       = 0,
+        SendWait    = 1,
+        RecvWait    = 2,
+        ReplyWait   = 3,
+        EventWait   = 4,
+        Exited      = 5,
+        DelayWait   = 6,
+        MailboxWait = 7,
+    }
+
+    impl State {
+        /// Return enum corresponding to raw state (if one exists).
+        pub fn from_u32(state: u32) -> Option<State> {
+            let state = match state {
+                0 => State::Ready,
+                1 => State::SendWait,
+                2 => State::RecvWait,
+                3 => State::ReplyWait,
+                4 => State::EventWait,
+                5 => State::Exited,
+                6 => State::DelayWait,
+                7 => State::MailboxWait,
+                _ => return None,
+            };
+            Some(state)
+        }
+    }
+
+    /// A single trace record.
+    #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+    #[repr(C)]
+    pub struct Record {
+        /// Time since boot (in microseconds).
+        pub timestamp_us: u64,
+        /// Raw Tid of the task the event relates to (or [`NO_TID`]).
+        pub tid: u32,
+        /// Raw [`EventKind`].
+        pub kind: u8,
+        /// Raw [`SyscallNo`](crate::syscall::SyscallNo) (only meaningful for
+        /// syscall events).
+        pub syscall_no: u8,
+        /// Always zero.
+        pub reserved: u16,
+        /// Event-specific data (see [`EventKind`]).
+        pub data: [u32; 4],
+    }
+
+    impl Record {
+        /// Size of a serialized record (in bytes).
+        pub const SIZE: usize = 32;
+
+        /// An all-zero record.
+        pub const EMPTY: Record = Record {
+            timestamp_us: 0,
+            tid: 0,
+            kind: 0,
+            syscall_no: 0,
+            reserved: 0,
+            data: [0; 4],
+        };
+
+        /// Serialize the record (in little-endian byte order, matching its
+        /// in-memory layout on the TS-7200).
+        pub fn to_bytes(&self) -> [u8; Record::SIZE] {
+            let mut buf = [0; Record::SIZE];
+            buf[0..8].copy_from_slice(&self.timestamp_us.to_le_bytes());
+            buf[8..12].copy_from_slice(&self.tid.to_le_bytes());
+            buf[12] = self.kind;
+            buf[13] = self.syscall_no;
+            buf[14..16].copy_from_slice(&self.reserved.to_le_bytes());
+            for (i, d) in self.data.iter().enumerate() {
+                buf[16 + i * 4..20 + i * 4].copy_from_slice(&d.to_le_bytes());
+            }
+            buf
+        }
+
+        /// Deserialize a record serialized using [`Record::to_bytes`].
+        pub fn from_bytes(buf: &[u8; Record::SIZE]) -> Record {
+            let u32_at =
+                |i: usize| u32::from_le_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]]);
+            let mut data = [0; 4];
+            for (i, d) in data.iter_mut().enumerate() {
+                *d = u32_at(16 + i * 4);
+            }
+            Record {
+                timestamp_us: u64::from(u32_at(0)) | u64::from(u32_at(4)) << 32,
+                tid: u32_at(8),
+                kind: buf[12],
+                syscall_no: buf[13],
+                reserved: u16::from_le_bytes([buf[14], buf[15]]),
+                data,
+            }
         }
     }
 }
@@ -0,0 +1,86 @@
+//! Runs userspace distros on the kernel's `platform-host` backend.
+//!
+//! Each distro is built using the top-level Makefile (with `PLATFORM=host`),
+//! and then run as a regular process, with its output captured for inspection
+//! by the tests in `tests/`.
+
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a distro may run for before it's considered to be hung.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Every distro is built into (and run from) the same `bin/` directory, so
+/// only one distro can be built and run at a time.
+static BIN_LOCK: Mutex<()> = Mutex::new(());
+
+/// The outcome of running a distro.
+#[derive(Debug)]
+pub struct Run {
+    /// The kernel's exit status.
+    pub status: ExitStatus,
+    /// Everything written to stdout (by both the kernel, and userspace).
+    pub stdout: String,
+}
+
+/// Build the given distro for the host, and run it to completion (with stdin
+/// closed).
+///
+/// # Panics
+///
+/// Panics if the distro fails to build, or doesn't exit within [`TIMEOUT`].
+pub fn run_distro(distro: &str) -> Run {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    let _guard = BIN_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let status = Command::new("make")
+        .current_dir(root)
+        .arg("PLATFORM=host")
+        .arg(format!("DISTRO={}", distro))
+        .arg("DEBUG=1")
+        .status()
+        .expect("failed to run make");
+    assert!(status.success(), "failed to build {}", distro);
+
+    let mut kernel = Command::new(root.join("bin/choochoos-kernel"))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to run the kernel");
+
+    // drain stdout concurrently, so the kernel never blocks on a full pipe
+    let mut pipe = kernel.stdout.take().unwrap();
+    let stdout = std::thread::spawn(move || {
+        let mut stdout = String::new();
+        pipe.read_to_string(&mut stdout).map(|_| stdout)
+    });
+
+    let deadline = Instant::now() + TIMEOUT;
+    let status = loop {
+        if let Some(status) = kernel.try_wait().expect("failed to wait on the kernel") {
+            break status;
+        }
+        if Instant::now() > deadline {
+            let _ = kernel.kill();
+            let _ = kernel.wait();
+            panic!(
+                "{} didn't exit within {:?}. stdout:\n{}",
+                distro,
+                TIMEOUT,
+                stdout.join().unwrap().unwrap_or_default()
+            );
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+
+    Run {
+        status,
+        stdout: stdout
+            .join()
+            .unwrap()
+            .expect("kernel's stdout wasn't UTF-8"),
+    }
+}
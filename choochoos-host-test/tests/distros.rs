@@ -0,0 +1,53 @@
+use choochoos_host_test::run_distro;
+
+#[test]
+fn k1() {
+    let run = run_distro("k1");
+    assert!(run.status.success(), "{:?}\n{}", run.status, run.stdout);
+
+    // The "true" FirstUserTask (priority 4) creates two lower priority tasks,
+    // which only run once it exits, and two higher priority tasks, which run
+    // (and yield, and exit) as soon as they're created.
+    let events: Vec<&str> = run
+        .stdout
+        .lines()
+        .filter_map(|line| match line {
+            "FirstUserTask: exiting" => Some(line),
+            _ if line.starts_with("Created: ") => Some("Created"),
+            _ if line.starts_with("MyTid=") => Some("MyTid"),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        events,
+        [
+            "Created",
+            "Created",
+            "MyTid",
+            "MyTid",
+            "Created",
+            "MyTid",
+            "MyTid",
+            "Created",
+            "FirstUserTask: exiting",
+            "MyTid",
+            "MyTid",
+            "MyTid",
+            "MyTid",
+        ],
+        "\n{}",
+        run.stdout
+    );
+}
+
+#[test]
+fn ns_test() {
+    let run = run_distro("ns_test");
+    assert!(run.status.success(), "{:?}\n{}", run.status, run.stdout);
+    assert!(
+        run.stdout.lines().any(|line| line == "OK"),
+        "{}",
+        run.stdout
+    );
+}
@@ -1,3 +1,91 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Parse a (possibly `0x` prefixed) numeric kernel config value from the
+/// environment, falling back to `default` if it isn't set.
+fn config_var(name: &str, default: usize) -> usize {
+    println!("cargo:rerun-if-env-changed={}", name);
+
+    let val = match env::var(name) {
+        Ok(val) if !val.trim().is_empty() => val,
+        _ => return default,
+    };
+
+    let val = val.trim();
+    let parsed = match val.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => val.parse(),
+    };
+
+    match parsed {
+        Ok(n) => n,
+        Err(e) => panic!("invalid value for {}: {:?} ({})", name, val, e),
+    }
+}
+
+/// Maximum number of per-module kernel log filters (see `kernel_log.rs`).
+const MAX_LOG_FILTERS: usize = 16;
+/// Maximum length of a module path in a kernel log filter.
+const MAX_LOG_MODULE_LEN: usize = 64;
+
+/// Parse a log level name into the corresponding `log::LevelFilter` variant.
+fn parse_log_level(level: &str) -> Option<&'static str> {
+    let level = match level.trim().to_ascii_lowercase().as_str() {
+        "off" => "Off",
+        "error" => "Error",
+        "warn" => "Warn",
+        "info" => "Info",
+        "debug" => "Debug",
+        "trace" => "Trace",
+        _ => return None,
+    };
+    Some(level)
+}
+
+/// Parse the kernel's compile-time log filters from `CHOOCHOOS_LOG`, which
+/// uses a (simplified) `env_logger` syntax: a comma-separated list of
+/// `module=level` entries, and an optional bare `level` which applies to every
+/// other module (e.g: `warn,kernel::syscalls::send=trace`).
+///
+/// Module paths are relative to the kernel crate's root.
+fn log_filters(default: &'static str) -> (&'static str, Vec<(String, &'static str)>) {
+    println!("cargo:rerun-if-env-changed=CHOOCHOOS_LOG");
+
+    let spec = env::var("CHOOCHOOS_LOG").unwrap_or_default();
+
+    let mut default = default;
+    let mut modules: Vec<(String, &'static str)> = Vec::new();
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let invalid = || panic!("invalid CHOOCHOOS_LOG entry: {:?}", entry);
+        match entry.find('=') {
+            None => default = parse_log_level(entry).unwrap_or_else(invalid),
+            Some(i) => {
+                let module = entry[..i].trim().to_string();
+                let level = parse_log_level(&entry[i + 1..]).unwrap_or_else(invalid);
+                if module.is_empty() || module.len() > MAX_LOG_MODULE_LEN {
+                    panic!(
+                        "CHOOCHOOS_LOG module paths must be between 1 and {} bytes long (got {:?})",
+                        MAX_LOG_MODULE_LEN, module
+                    );
+                }
+                modules.retain(|(m, _)| *m != module);
+                modules.push((module, level));
+            }
+        }
+    }
+
+    if modules.len() > MAX_LOG_FILTERS {
+        panic!(
+            "CHOOCHOOS_LOG can contain at most {} per-module filters (got {})",
+            MAX_LOG_FILTERS,
+            modules.len()
+        );
+    }
+
+    (default, modules)
+}
+
 fn main() {
     #[allow(clippy::single_match)]
     match std::env::var("DISTRO") {
@@ -12,4 +100,180 @@ fn main() {
             // `clippy`, so we just do nothing and let the linker error occur.
         }
     }
+
+    // Kernel limits are set by the distro at build time (see the Makefile).
+    let max_tasks = config_var("CHOOCHOOS_MAX_TASKS", 16);
+    let max_events = config_var("CHOOCHOOS_MAX_EVENTS", 16);
+    let max_event_waiters = config_var("CHOOCHOOS_MAX_EVENT_WAITERS", 4);
+    let max_pending_event_data = config_var("CHOOCHOOS_MAX_PENDING_EVENT_DATA", 8);
+    let user_stack_size = config_var("CHOOCHOOS_USER_STACK_SIZE", 0x40000);
+    let stack_paint_size = config_var("CHOOCHOOS_STACK_PAINT_SIZE", 0x4000);
+    let kernel_log_size = config_var("CHOOCHOOS_KERNEL_LOG_SIZE", 0x2000);
+    let trace_records = config_var("CHOOCHOOS_TRACE_RECORDS", 1024);
+    let max_mailboxes = config_var("CHOOCHOOS_MAX_MAILBOXES", 8);
+    let mailbox_pool_size = config_var("CHOOCHOOS_MAILBOX_POOL_SIZE", 0x4000);
+
+    // the `kdebug` feature enables debug logs by default
+    let default_log_level = match env::var_os("CARGO_FEATURE_KDEBUG") {
+        Some(_) => "Debug",
+        None => "Info",
+    };
+    let (log_level, log_module_levels) = log_filters(default_log_level);
+
+    // heapless (v0.5) sizes its containers using `typenum` constants, which
+    // are only defined up to 1024.
+    for &(name, val) in &[
+        ("CHOOCHOOS_MAX_TASKS", max_tasks),
+        ("CHOOCHOOS_MAX_EVENTS", max_events),
+        ("CHOOCHOOS_MAX_EVENT_WAITERS", max_event_waiters),
+        ("CHOOCHOOS_MAX_PENDING_EVENT_DATA", max_pending_event_data),
+    ] {
+        if !(1..=1024).contains(&val) {
+            panic!("{} must be between 1 and 1024 (got {})", name, val);
+        }
+    }
+    if user_stack_size == 0 || !user_stack_size.is_multiple_of(8) {
+        panic!(
+            "CHOOCHOOS_USER_STACK_SIZE must be a non-zero multiple of 8 (got {:#x})",
+            user_stack_size
+        );
+    }
+
+    if !stack_paint_size.is_multiple_of(4) {
+        panic!(
+            "CHOOCHOOS_STACK_PAINT_SIZE must be a multiple of 4 (got {:#x})",
+            stack_paint_size
+        );
+    }
+
+    if !kernel_log_size.is_power_of_two() {
+        panic!(
+            "CHOOCHOOS_KERNEL_LOG_SIZE must be a power of two (got {:#x})",
+            kernel_log_size
+        );
+    }
+
+    if !trace_records.is_power_of_two() {
+        panic!(
+            "CHOOCHOOS_TRACE_RECORDS must be a power of two (got {})",
+            trace_records
+        );
+    }
+
+    // mailbox ids reserve 16 bits for the mailbox's slot
+    if !(1..=0x10000).contains(&max_mailboxes) {
+        panic!(
+            "CHOOCHOOS_MAX_MAILBOXES must be between 1 and 65536 (got {})",
+            max_mailboxes
+        );
+    }
+
+    // the `mmu` feature maps user stacks using 4K pages
+    if env::var_os("CARGO_FEATURE_MMU").is_some() && !user_stack_size.is_multiple_of(0x1000) {
+        panic!(
+            "CHOOCHOOS_USER_STACK_SIZE must be a multiple of 4K when using the `mmu` feature (got {:#x})",
+            user_stack_size
+        );
+    }
+
+    let config = format!(
+        r#"// generated by build.rs - do not edit!
+
+/// Maximum number of concurrently running tasks.
+pub const MAX_TASKS: usize = {max_tasks};
+
+/// Maximum number of distinct events with pending volatile data / blocked tasks.
+#[allow(dead_code)]
+pub const MAX_EVENTS: usize = {max_events};
+#[allow(non_camel_case_types)]
+pub type MAX_EVENTS = heapless::consts::U{max_events};
+
+/// Maximum number of tasks which can wait on a single event at once.
+#[allow(non_camel_case_types)]
+pub type MAX_EVENT_WAITERS = heapless::consts::U{max_event_waiters};
+
+/// Maximum number of undelivered volatile data values queued per event.
+pub const MAX_PENDING_EVENT_DATA: usize = {max_pending_event_data};
+
+/// Size of each task's stack (in bytes).
+#[allow(dead_code)]
+pub const USER_STACK_SIZE: usize = {user_stack_size:#x};
+
+/// Number of bytes at the top of each task's stack which are painted in order
+/// to track stack usage.
+#[allow(dead_code)]
+pub const STACK_PAINT_SIZE: usize = {stack_paint_size:#x};
+
+/// Size of the kernel log ring buffer (in bytes).
+pub const KERNEL_LOG_SIZE: usize = {kernel_log_size:#x};
+
+/// Number of records in the kernel trace buffer.
+#[allow(dead_code)]
+pub const TRACE_RECORDS: usize = {trace_records};
+
+/// Maximum number of concurrently existing mailboxes.
+pub const MAX_MAILBOXES: usize = {max_mailboxes};
+
+/// Size of the pool from which mailbox storage is allocated (in bytes).
+pub const MAILBOX_POOL_SIZE: usize = {mailbox_pool_size:#x};
+
+/// Maximum number of per-module kernel log filters.
+#[allow(non_camel_case_types)]
+pub type MAX_LOG_FILTERS = heapless::consts::U{max_log_filters};
+
+/// Maximum length of a module path in a kernel log filter.
+#[allow(non_camel_case_types)]
+pub type MAX_LOG_MODULE_LEN = heapless::consts::U{max_log_module_len};
+
+/// Initial log level of modules without a more specific filter.
+pub const LOG_LEVEL: log::LevelFilter = log::LevelFilter::{log_level};
+
+/// Initial per-module log levels.
+pub const LOG_MODULE_LEVELS: &[(&str, log::LevelFilter)] = &[{log_module_levels}];
+"#,
+        max_tasks = max_tasks,
+        max_events = max_events,
+        max_event_waiters = max_event_waiters,
+        max_pending_event_data = max_pending_event_data,
+        user_stack_size = user_stack_size,
+        stack_paint_size = stack_paint_size,
+        kernel_log_size = kernel_log_size,
+        trace_records = trace_records,
+        max_mailboxes = max_mailboxes,
+        mailbox_pool_size = mailbox_pool_size,
+        max_log_filters = MAX_LOG_FILTERS,
+        max_log_module_len = MAX_LOG_MODULE_LEN,
+        log_level = log_level,
+        log_module_levels = log_module_levels
+            .iter()
+            .map(|(module, level)| format!("({:?}, log::LevelFilter::{})", module, level))
+            .collect::<Vec<_>>()
+            .join(", "),
+    );
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("config.rs"), config).unwrap();
+
+    if env::var_os("CARGO_FEATURE_PLATFORM_TS7200").is_some() {
+        // `_start` lives in the kernel library, which nothing in the executable
+        // itself refers to
+        println!("cargo:rustc-link-arg-bins=--undefined=_start");
+
+        // the MMU can only protect section-aligned regions, so `mmu` builds
+        // place userspace in its own sections (NOTE: the linker runs from the
+        // workspace root)
+        let linker_script = if env::var_os("CARGO_FEATURE_MMU").is_some() {
+            "ts7200_redboot_mmu.ld"
+        } else {
+            "ts7200_redboot.ld"
+        };
+        println!("cargo:rustc-link-arg-bins=-T{}", linker_script);
+
+        // the linker script uses these to size the user stack region
+        println!("cargo:rustc-link-arg=--defsym=__MAX_TASKS__={}", max_tasks);
+        println!(
+            "cargo:rustc-link-arg=--defsym=__USER_STACK_SIZE__={:#x}",
+            user_stack_size
+        );
+    }
 }
@@ -0,0 +1,85 @@
+//! Frame-pointer based backtraces (used in crash dumps).
+//!
+//! Relies on code being compiled with frame pointers (see the
+//! `eliminate-frame-pointer` key in the target spec). In ARM mode, LLVM's frame
+//! records store the caller's frame pointer at `[fp]`, and the return address
+//! at `[fp, #4]`.
+
+use core::ops::Range;
+use core::ptr;
+
+use crate::{bwkprint, bwkprintln};
+
+use super::userstack::UserStack;
+
+/// Maximum number of frames to walk, in case the frame chain is corrupted.
+const MAX_FRAMES: usize = 32;
+
+/// Walk the chain of frame records starting at `fp`, calling `f` with each
+/// frame's return address. Only frames which lie within `stack` are followed.
+fn walk_frames(mut fp: usize, stack: Range<usize>, mut f: impl FnMut(usize)) {
+    for _ in 0..MAX_FRAMES {
+        if fp % 4 != 0 || fp < stack.start || fp + 8 > stack.end {
+            return;
+        }
+
+        let (prev_fp, ret_addr) = unsafe {
+            (
+                ptr::read_volatile(fp as *const usize),
+                ptr::read_volatile((fp + 4) as *const usize),
+            )
+        };
+        f(ret_addr);
+
+        // stacks grow down, so callers' frames live at higher addresses
+        if prev_fp <= fp {
+            return;
+        }
+        fp = prev_fp;
+    }
+}
+
+/// Print a backtrace of the kernel, starting from the caller.
+#[inline(never)]
+pub fn print_kernel_backtrace() {
+    // The kernel runs on the stack RedBoot hands it, which lives somewhere
+    // near the end of RAM.
+    let fp: usize;
+    unsafe { asm!("mov {}, r11", out(reg) fp) };
+    let ram_end = crate::platform::ram_end();
+
+    walk_frames(fp, fp..ram_end, |ret_addr| {
+        bwkprintln!("  {:#010x}", ret_addr)
+    });
+}
+
+/// Print a suspended task's saved registers, along with a backtrace (which is
+/// constrained to the task's `stack`).
+///
+/// NOTE: when the task is suspended in a syscall, the function which invoked
+/// the syscall only shows up as `lr`, as the syscall stubs don't set up a frame
+/// record.
+pub fn print_user_state(saved: &UserStack, stack: Range<usize>) {
+    let sp = saved as *const _ as usize + core::mem::size_of::<UserStack>();
+
+    bwkprintln!(
+        "  pc={:#010x} lr={:#010x} sp={:#010x} spsr={:#010x}",
+        saved.pc as usize,
+        saved.lr,
+        sp,
+        saved.spsr
+    );
+    for (i, regs) in saved.regs.chunks(4).enumerate() {
+        bwkprint!("  ");
+        for (j, reg) in regs.iter().enumerate() {
+            bwkprint!("r{:<2}={:#010x} ", i * 4 + j, reg);
+        }
+        bwkprintln!();
+    }
+
+    bwkprintln!("  backtrace:");
+    bwkprintln!("    {:#010x}", saved.pc as usize);
+    walk_frames(saved.regs[11], stack, |ret_addr| {
+        bwkprintln!("    {:#010x}", ret_addr)
+    });
+}
@@ -29,3 +29,8 @@ pub unsafe fn fresh_stack(
 
     ptr::NonNull::new_unchecked(sp)
 }
+
+/// Release the stack of a task which will never be activated again.
+///
+/// Task stacks are just memory, so there's nothing to do.
+pub unsafe fn free_stack(_sp: ptr::NonNull<UserStack>) {}
@@ -144,3 +144,133 @@ pub unsafe extern "C" fn _irq_handler() {
         handle_irq = sym super::irq_handler::handle_irq
     }
 }
+
+#[naked]
+pub unsafe extern "C" fn _undefined_instruction_handler() {
+    asm! {
+        // Faults in the kernel are unrecoverable. The undefined mode SP isn't
+        // used for anything else, so use it as a scratch register to check if
+        // the fault came from user mode.
+        "mrs     sp,spsr",
+        "and     sp,sp,#0x1f",
+        "cmp     sp,#0x10",
+
+        // r0 = handle_kernel_fault 1st param = fault kind
+        // r1 = handle_kernel_fault 2nd param = address of the faulting instr
+        "movne   r0,#0",
+        "subne   r1,lr,#4",
+        // Switch to supervisor mode (IRQs disabled), and panic on the kernel's stack
+        "msrne   cpsr_c, #0xd3",
+        "bne     {handle_kernel_fault}",
+
+        // Switch to system mode (IRQs disabled)
+        // This banks in the user's LR and SP
+        "msr     cpsr_c, #0xdf",
+
+        // Stack user registers on user stack
+        "stmfd   sp!,{{r0-r12,lr}}",
+        "mov     r4,sp", // hold on to user sp
+
+        // Switch to undefined mode (IRQs disabled)
+        // This banks in the undefined mode's SP and LR.
+        "msr     cpsr_c, #0xdb",
+
+        // store user mode spsr and the address of the faulting instruction
+        // (compensating with -4)
+        "mrs     r0,spsr",
+        "sub     lr,lr,#4",
+        "stmfd   r4!,{{r0, lr}}",
+
+        // Switch to supervisor mode (IRQs disabled)
+        "msr     cpsr_c, #0xd3",
+
+        // r0 = handle_fault 1st param = fault kind
+        // r1 = handle_fault 2nd param = user's stack pointer (with saved state)
+        "mov     r0,#0",
+        "mov     r1,r4",
+        "bl      {handle_fault}",
+
+        // Return the final user SP via r0
+        "mov     r0, r4",
+
+        // Restore the kernel's context, and return to the caller of _activate_task
+        "ldmfd   sp!,{{r4-r12,pc}}",
+
+        handle_fault = sym super::fault_handler::handle_fault,
+        handle_kernel_fault = sym super::fault_handler::handle_kernel_fault,
+    }
+}
+
+#[naked]
+pub unsafe extern "C" fn _prefetch_abort_handler() {
+    asm! {
+        // See `_undefined_instruction_handler`
+        "mrs     sp,spsr",
+        "and     sp,sp,#0x1f",
+        "cmp     sp,#0x10",
+
+        "movne   r0,#1",
+        "subne   r1,lr,#4",
+        "msrne   cpsr_c, #0xd3",
+        "bne     {handle_kernel_fault}",
+
+        "msr     cpsr_c, #0xdf",
+        "stmfd   sp!,{{r0-r12,lr}}",
+        "mov     r4,sp",
+
+        // store user mode spsr and the address of the aborted instruction
+        // (compensating with -4)
+        "msr     cpsr_c, #0xd7",
+        "mrs     r0,spsr",
+        "sub     lr,lr,#4",
+        "stmfd   r4!,{{r0, lr}}",
+
+        "msr     cpsr_c, #0xd3",
+        "mov     r0,#1",
+        "mov     r1,r4",
+        "bl      {handle_fault}",
+
+        "mov     r0, r4",
+        "ldmfd   sp!,{{r4-r12,pc}}",
+
+        handle_fault = sym super::fault_handler::handle_fault,
+        handle_kernel_fault = sym super::fault_handler::handle_kernel_fault,
+    }
+}
+
+#[naked]
+pub unsafe extern "C" fn _data_abort_handler() {
+    asm! {
+        // See `_undefined_instruction_handler`
+        "mrs     sp,spsr",
+        "and     sp,sp,#0x1f",
+        "cmp     sp,#0x10",
+
+        "movne   r0,#2",
+        "subne   r1,lr,#8",
+        "msrne   cpsr_c, #0xd3",
+        "bne     {handle_kernel_fault}",
+
+        "msr     cpsr_c, #0xdf",
+        "stmfd   sp!,{{r0-r12,lr}}",
+        "mov     r4,sp",
+
+        // store user mode spsr and the address of the aborted instruction
+        // (compensating with -8)
+        "msr     cpsr_c, #0xd7",
+        "mrs     r0,spsr",
+        "sub     lr,lr,#8",
+        "stmfd   r4!,{{r0, lr}}",
+
+        "msr     cpsr_c, #0xd3",
+        "mov     r0,#2",
+        "mov     r1,r4",
+        "bl      {handle_fault}",
+
+        "mov     r0, r4",
+        "ldmfd   sp!,{{r4-r12,pc}}",
+
+        handle_fault = sym super::fault_handler::handle_fault,
+        handle_kernel_fault = sym super::fault_handler::handle_kernel_fault,
+    }
+}
@@ -0,0 +1,115 @@
+//! Handling faults (i.e: undefined instructions, and prefetch / data aborts).
+//!
+//! Faults in the kernel always panic. Faulting tasks are killed (and reported),
+//! unless the `panic-on-user-fault` feature is enabled, in which case the
+//! kernel panics (printing a crash dump of the faulting task).
+
+use super::userstack::UserStack;
+
+/// The kind of fault which occurred.
+///
+/// Passed as the first argument to the fault handlers by the fault assembly
+/// routines in [`ctx_switch`](super::ctx_switch).
+#[derive(Debug, Copy, Clone)]
+#[repr(u32)]
+pub enum Fault {
+    UndefinedInstruction = 0,
+    PrefetchAbort        = 1,
+    DataAbort            = 2,
+}
+
+/// Formats a description of a fault, including the relevant fault status /
+/// address registers.
+struct FaultReport {
+    fault: Fault,
+    pc: usize,
+}
+
+impl core::fmt::Display for FaultReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.fault {
+            Fault::UndefinedInstruction => {
+                write!(f, "undefined instruction at pc={:#010x}", self.pc)
+            }
+            Fault::PrefetchAbort => write!(
+                f,
+                "prefetch abort at pc={:#010x} (fsr={:#x})",
+                self.pc,
+                prefetch_fault_status()
+            ),
+            Fault::DataAbort => write!(
+                f,
+                "data abort at pc={:#010x} accessing {:#010x} (fsr={:#x})",
+                self.pc,
+                fault_address(),
+                data_fault_status()
+            ),
+        }
+    }
+}
+
+/// Called by the [`_undefined_instruction_handler`],
+/// [`_prefetch_abort_handler`] and [`_data_abort_handler`] assembly routines
+/// when a task faults.
+///
+/// [`_undefined_instruction_handler`]: super::ctx_switch::_undefined_instruction_handler
+/// [`_prefetch_abort_handler`]: super::ctx_switch::_prefetch_abort_handler
+/// [`_data_abort_handler`]: super::ctx_switch::_data_abort_handler
+pub unsafe extern "C" fn handle_fault(fault: Fault, stack: *mut UserStack) {
+    let kernel = match &mut crate::KERNEL {
+        Some(kernel) => kernel,
+        None => core::hint::unreachable_unchecked(),
+    };
+
+    let report = FaultReport {
+        fault,
+        pc: (*stack).pc as usize,
+    };
+
+    // breakpoints set by GDB stop the task instead
+    #[cfg(feature = "gdb")]
+    {
+        if matches!(fault, Fault::UndefinedInstruction) && kernel.gdb_breakpoint_hit(&*stack) {
+            return;
+        }
+    }
+
+    #[cfg(feature = "panic-on-user-fault")]
+    {
+        kernel.trap_frame = core::ptr::NonNull::new(stack);
+        panic!("{:?} faulted ({})", kernel.current_tid, report);
+    }
+
+    #[cfg(not(feature = "panic-on-user-fault"))]
+    kernel.kill_current_task(format_args!("faulted ({})", report));
+}
+
+/// Called by the fault assembly routines when the kernel itself faults, which
+/// is unrecoverable.
+pub unsafe extern "C" fn handle_kernel_fault(fault: Fault, pc: usize) -> ! {
+    panic!("{} in kernel", FaultReport { fault, pc });
+}
+
+/// Returns the Fault Status Register, which describes the most recent data
+/// abort.
+fn data_fault_status() -> u32 {
+    let fsr: u32;
+    unsafe { asm!("mrc p15, 0, {}, c5, c0, 0", out(reg) fsr) };
+    fsr
+}
+
+/// Returns the Instruction Fault Status Register, which describes the most
+/// recent prefetch abort.
+fn prefetch_fault_status() -> u32 {
+    let ifsr: u32;
+    unsafe { asm!("mrc p15, 0, {}, c5, c0, 1", out(reg) ifsr) };
+    ifsr
+}
+
+/// Returns the Fault Address Register, i.e: the address which caused the most
+/// recent data abort.
+fn fault_address() -> u32 {
+    let far: u32;
+    unsafe { asm!("mrc p15, 0, {}, c6, c0, 0", out(reg) far) };
+    far
+}
@@ -0,0 +1,227 @@
+//! ARM specific support for the GDB stub (see `kernel::gdb`).
+//!
+//! The ARM920T doesn't support hardware single-stepping, so single-steps are
+//! implemented by decoding the instruction at the task's `pc`, and placing a
+//! temporary breakpoint on whichever instruction it will execute next.
+
+use gdbstub_arch::arm::reg::ArmCoreRegs;
+
+use super::userstack::UserStack;
+
+/// The instruction used for software breakpoints (which is permanently
+/// undefined, and therefore traps into the undefined instruction handler).
+pub const BREAKPOINT: u32 = 0xe7ff_defe;
+
+/// The CPSR bits which may be modified by GDB (i.e: the condition flags).
+const CPSR_FLAGS_MASK: u32 = 0xf000_0000;
+
+/// Returns a suspended task's stack pointer, which points just past its saved
+/// state.
+fn user_sp(saved: &UserStack) -> u32 {
+    (saved as *const _ as usize + core::mem::size_of::<UserStack>()) as u32
+}
+
+/// Read a suspended task's registers.
+pub fn read_registers(saved: &UserStack, regs: &mut ArmCoreRegs) {
+    for (reg, &saved) in regs.r.iter_mut().zip(saved.regs.iter()) {
+        *reg = saved as u32;
+    }
+    regs.sp = user_sp(saved);
+    regs.lr = saved.lr as u32;
+    regs.pc = saved.pc as usize as u32;
+    regs.cpsr = saved.spsr as u32;
+}
+
+/// Update a suspended task's registers.
+///
+/// The task's saved state lives at the top of its stack, so `sp` can't be
+/// modified, and only the condition flags of the `cpsr` may be modified.
+pub fn write_registers(saved: &mut UserStack, regs: &ArmCoreRegs) -> Result<(), ()> {
+    if regs.sp != user_sp(saved) || regs.pc == 0 {
+        return Err(());
+    }
+
+    for (saved, &reg) in saved.regs.iter_mut().zip(regs.r.iter()) {
+        *saved = reg as usize;
+    }
+    saved.lr = regs.lr as usize;
+    // SAFETY: `pc` isn't null (which is all that fn pointers require)
+    saved.pc = unsafe { core::mem::transmute::<usize, unsafe extern "C" fn()>(regs.pc as usize) };
+    saved.spsr = (saved.spsr & !CPSR_FLAGS_MASK as usize) | (regs.cpsr & CPSR_FLAGS_MASK) as usize;
+
+    Ok(())
+}
+
+/// Returns the value of register `n`, as read by the instruction at the task's
+/// `pc` (i.e: `pc` reads as the address of the instruction plus 8).
+fn reg(saved: &UserStack, n: u32) -> u32 {
+    match n {
+        0..=12 => saved.regs[n as usize] as u32,
+        13 => user_sp(saved),
+        14 => saved.lr as u32,
+        _ => (saved.pc as usize as u32).wrapping_add(8),
+    }
+}
+
+/// Check if an instruction with the given condition field will execute.
+fn condition_passed(cond: u32, cpsr: u32) -> bool {
+    let n = cpsr & (1 << 31) != 0;
+    let z = cpsr & (1 << 30) != 0;
+    let c = cpsr & (1 << 29) != 0;
+    let v = cpsr & (1 << 28) != 0;
+
+    match cond {
+        0x0 => z,
+        0x1 => !z,
+        0x2 => c,
+        0x3 => !c,
+        0x4 => n,
+        0x5 => !n,
+        0x6 => v,
+        0x7 => !v,
+        0x8 => c && !z,
+        0x9 => !c || z,
+        0xa => n == v,
+        0xb => n != v,
+        0xc => !z && n == v,
+        0xd => z || n != v,
+        _ => true,
+    }
+}
+
+/// Apply a barrel shifter operation. `imm` is set when the shift amount is
+/// encoded as an immediate (in which case an amount of 0 has special meanings).
+fn shift(val: u32, kind: u32, amount: u32, imm: bool, carry: bool) -> u32 {
+    match kind {
+        // LSL
+        0 => val.checked_shl(amount).unwrap_or(0),
+        // LSR
+        1 => {
+            let amount = if imm && amount == 0 { 32 } else { amount };
+            val.checked_shr(amount).unwrap_or(0)
+        }
+        // ASR
+        2 => {
+            let amount = if imm && amount == 0 { 32 } else { amount };
+            ((val as i32) >> amount.min(31)) as u32
+        }
+        // RRX
+        _ if imm && amount == 0 => (carry as u32) << 31 | val >> 1,
+        // ROR
+        _ => val.rotate_right(amount),
+    }
+}
+
+/// Returns the address of the instruction which the task will execute after
+/// `instr` (the instruction at its `pc`).
+///
+/// `read_word` is used to read any values loaded into `pc`.
+pub fn next_pc(saved: &UserStack, instr: u32, read_word: impl Fn(u32) -> u32) -> u32 {
+    let pc = saved.pc as usize as u32;
+    let cpsr = saved.spsr as u32;
+    let carry = cpsr & (1 << 29) != 0;
+    let bit = |n: u32| instr & (1 << n) != 0;
+    let field = |lo: u32, len: u32| (instr >> lo) & ((1 << len) - 1);
+
+    if !condition_passed(field(28, 4), cpsr) {
+        return pc.wrapping_add(4);
+    }
+
+    // B / BL
+    if field(25, 3) == 0b101 {
+        let offset = ((instr << 8) as i32 >> 6) as u32;
+        return reg(saved, 15).wrapping_add(offset);
+    }
+
+    // BX
+    if instr & 0x0fff_fff0 == 0x012f_ff10 {
+        return reg(saved, field(0, 4)) & !1;
+    }
+
+    // data processing, with pc as the destination (excluding multiplies and
+    // halfword transfers, as well as the comparison opcodes, which don't write
+    // a result)
+    let is_extension = !bit(25) && bit(7) && bit(4);
+    let opcode = field(21, 4);
+    if field(26, 2) == 0b00 && field(12, 4) == 15 && !is_extension && !(8..=11).contains(&opcode) {
+        let rn = reg(saved, field(16, 4));
+        let op2 = if bit(25) {
+            field(0, 8).rotate_right(field(8, 4) * 2)
+        } else if bit(4) {
+            let amount = reg(saved, field(8, 4)) & 0xff;
+            shift(reg(saved, field(0, 4)), field(5, 2), amount, false, carry)
+        } else {
+            shift(
+                reg(saved, field(0, 4)),
+                field(5, 2),
+                field(7, 5),
+                true,
+                carry,
+            )
+        };
+
+        return match opcode {
+            0x0 => rn & op2,
+            0x1 => rn ^ op2,
+            0x2 => rn.wrapping_sub(op2),
+            0x3 => op2.wrapping_sub(rn),
+            0x4 => rn.wrapping_add(op2),
+            0x5 => rn.wrapping_add(op2).wrapping_add(carry as u32),
+            0x6 => rn.wrapping_sub(op2).wrapping_sub(!carry as u32),
+            0x7 => op2.wrapping_sub(rn).wrapping_sub(!carry as u32),
+            0xc => rn | op2,
+            0xd => op2,
+            0xe => rn & !op2,
+            _ => !op2,
+        };
+    }
+
+    // LDR into pc
+    if field(26, 2) == 0b01 && bit(20) && field(12, 4) == 15 {
+        let base = reg(saved, field(16, 4));
+        let offset = if bit(25) {
+            shift(
+                reg(saved, field(0, 4)),
+                field(5, 2),
+                field(7, 5),
+                true,
+                carry,
+            )
+        } else {
+            field(0, 12)
+        };
+        let addr = match (bit(24), bit(23)) {
+            // post-indexed
+            (false, _) => base,
+            (true, true) => base.wrapping_add(offset),
+            (true, false) => base.wrapping_sub(offset),
+        };
+        return read_word(addr);
+    }
+
+    // LDM including pc (which is always loaded from the highest address)
+    if field(25, 3) == 0b100 && bit(20) && bit(15) {
+        let base = reg(saved, field(16, 4));
+        let count = field(0, 16).count_ones();
+        let addr = match (bit(24), bit(23)) {
+            // increment after / before
+            (false, true) => base.wrapping_add(4 * (count - 1)),
+            (true, true) => base.wrapping_add(4 * count),
+            // decrement after / before
+            (false, false) => base,
+            (true, false) => base.wrapping_sub(4),
+        };
+        return read_word(addr);
+    }
+
+    pc.wrapping_add(4)
+}
+
+/// Make a modified instruction at `addr` visible to the instruction stream,
+/// by writing back the D-cache line containing it, and invalidating the
+/// I-cache.
+pub unsafe fn sync_instruction(addr: usize) {
+    asm!("mcr p15, 0, {}, c7, c10, 1", in(reg) addr);
+    asm!("mcr p15, 0, {}, c7, c10, 4", in(reg) 0);
+    asm!("mcr p15, 0, {}, c7, c5, 0", in(reg) 0);
+}
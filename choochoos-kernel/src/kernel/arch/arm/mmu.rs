@@ -0,0 +1,297 @@
+//! User memory protection using the ARM920T's MMU.
+//!
+//! RedBoot leaves the MMU enabled with a section-mapped translation table,
+//! which (among other things) maps the TS-7200's discontiguous SDRAM banks into
+//! a single contiguous virtual address range. Instead of building a translation
+//! table from scratch, the kernel copies RedBoot's table, and adjusts the
+//! access permissions of each mapping:
+//!
+//! - RAM is only accessible from privileged modes, with the exception of the
+//!   userspace image (see `ts7200_redboot_mmu.ld`), and the stack of the
+//!   currently running task. Userspace's code (and read-only data) is mapped
+//!   read-only.
+//! - Everything else (i.e: memory-mapped IO) remains accessible from user mode,
+//!   as userspace still performs busy-wait IO.
+//!
+//! The user stack region is mapped using 4K small pages (via coarse page
+//! tables), and the permissions of the outgoing / incoming task's stack are
+//! swapped whenever a different task is activated.
+
+use core::ops::Range;
+use core::ptr;
+
+use crate::kernel::{stack, MAX_TASKS, USER_STACK_SIZE};
+
+const SECTION_SIZE: usize = 1 << 20;
+const PAGE_SIZE: usize = 1 << 12;
+const PAGES_PER_SECTION: usize = SECTION_SIZE / PAGE_SIZE;
+const NUM_SECTIONS: usize = 4096;
+
+/// Size of a single ARM920T D-cache line.
+const CACHE_LINE_SIZE: usize = 32;
+
+/// Upper bound on the number of sections spanned by the user stack region
+/// (which isn't necessarily section-aligned).
+const STACK_SECTIONS: usize = (MAX_TASKS * USER_STACK_SIZE + SECTION_SIZE - 1) / SECTION_SIZE + 1;
+
+// first-level descriptor fields
+const L1_TYPE_MASK: u32 = 0b11;
+const L1_TYPE_COARSE: u32 = 0b01;
+const L1_TYPE_SECTION: u32 = 0b10;
+/// Should be set in all ARM920T first-level descriptors.
+const L1_BIT4: u32 = 1 << 4;
+const L1_DOMAIN_MASK: u32 = 0b1111 << 5;
+const SECTION_CB_MASK: u32 = 0b11 << 2;
+const SECTION_AP_SHIFT: u32 = 10;
+const SECTION_BASE_MASK: u32 = 0xfff0_0000;
+
+// second-level (coarse page table) descriptor fields
+const L2_TYPE_SMALL_PAGE: u32 = 0b10;
+/// Small pages have four 2-bit AP fields (one per 1K subpage).
+const SMALL_PAGE_AP_SHIFT: u32 = 4;
+const SMALL_PAGE_AP_MASK: u32 = 0xff << SMALL_PAGE_AP_SHIFT;
+
+/// Access permission (AP) bits, assuming the S and R bits in the CP15 control
+/// register are clear (which RedBoot leaves them as).
+#[derive(Debug, Copy, Clone)]
+#[repr(u32)]
+enum Access {
+    /// Read/write from privileged modes, no access from user mode.
+    Privileged   = 0b01,
+    /// Read/write from privileged modes, read-only from user mode.
+    UserReadOnly = 0b10,
+    /// Read/write from any mode.
+    User         = 0b11,
+}
+
+#[repr(C, align(16384))]
+struct L1Table([u32; NUM_SECTIONS]);
+
+#[repr(C, align(1024))]
+#[derive(Copy, Clone)]
+struct CoarseTable([u32; PAGES_PER_SECTION]);
+
+static mut L1_TABLE: L1Table = L1Table([0; NUM_SECTIONS]);
+static mut STACK_TABLES: [CoarseTable; STACK_SECTIONS] =
+    [CoarseTable([0; PAGES_PER_SECTION]); STACK_SECTIONS];
+
+/// The task slot whose stack is currently accessible from user mode.
+static mut ACTIVE_STACK: Option<usize> = None;
+
+/// Returns the region of memory containing userspace's code (and read-only
+/// data).
+fn user_text_region() -> Range<usize> {
+    // provided by the linker
+    extern "C" {
+        static __USER_TEXT_START__: core::ffi::c_void;
+        static __USER_TEXT_END__: core::ffi::c_void;
+    }
+
+    unsafe {
+        (&__USER_TEXT_START__ as *const _ as usize)..(&__USER_TEXT_END__ as *const _ as usize)
+    }
+}
+
+/// Returns the region of memory containing userspace's (writable) data.
+fn user_data_region() -> Range<usize> {
+    // provided by the linker
+    extern "C" {
+        static __USER_DATA_START__: core::ffi::c_void;
+        static __USER_DATA_END__: core::ffi::c_void;
+    }
+
+    unsafe {
+        (&__USER_DATA_START__ as *const _ as usize)..(&__USER_DATA_END__ as *const _ as usize)
+    }
+}
+
+/// Returns the region of memory containing every task's stack.
+fn user_stacks_region() -> Range<usize> {
+    let start = crate::platform::user_stacks_start();
+    start..start + MAX_TASKS * USER_STACK_SIZE
+}
+
+/// Translate a (section mapped) virtual address to a physical address using
+/// the given first-level table.
+unsafe fn virt_to_phys(table: *const u32, addr: usize) -> u32 {
+    let desc = ptr::read_volatile(table.add(addr / SECTION_SIZE));
+    assert_eq!(
+        desc & L1_TYPE_MASK,
+        L1_TYPE_SECTION,
+        "{:#010x} isn't section mapped",
+        addr
+    );
+    (desc & SECTION_BASE_MASK) | (addr % SECTION_SIZE) as u32
+}
+
+fn with_section_access(desc: u32, access: Access) -> u32 {
+    (desc & !(0b11 << SECTION_AP_SHIFT)) | (access as u32) << SECTION_AP_SHIFT
+}
+
+fn with_small_page_access(desc: u32, access: Access) -> u32 {
+    let ap = access as u32;
+    let ap = ap | ap << 2 | ap << 4 | ap << 6;
+    (desc & !SMALL_PAGE_AP_MASK) | ap << SMALL_PAGE_AP_SHIFT
+}
+
+/// Set up the kernel's translation table, and start using it.
+///
+/// # Safety
+///
+/// Must only be called once, before any tasks are activated.
+pub unsafe fn init() {
+    // NOTE: this assumes RedBoot's table lives in identity-mapped memory
+    let redboot_table = (read_ttb() & !0x3fff) as *const u32;
+
+    let user_text = user_text_region();
+    let user_data = user_data_region();
+    let stacks = user_stacks_region();
+    let ram_end = crate::platform::ram_end();
+
+    for region in &[&user_text, &user_data] {
+        assert!(
+            region.start % SECTION_SIZE == 0 && region.end % SECTION_SIZE == 0,
+            "user region {:#x?} isn't section aligned",
+            region
+        );
+    }
+    assert_eq!(
+        stacks.start % PAGE_SIZE,
+        0,
+        "user stacks aren't page aligned"
+    );
+
+    let mut next_stack_table = 0;
+    for (i, entry) in L1_TABLE.0.iter_mut().enumerate() {
+        let desc = ptr::read_volatile(redboot_table.add(i));
+        let start = i * SECTION_SIZE;
+        // NOTE: the last section ends at 4G, which doesn't fit in a usize
+        let last = start + (SECTION_SIZE - 1);
+
+        *entry = if desc & L1_TYPE_MASK != L1_TYPE_SECTION {
+            // leave unmapped (and finer-grained) regions as-is
+            desc
+        } else if start < stacks.end && last >= stacks.start {
+            // split the section into small pages, which are only accessible from
+            // user mode while the corresponding task is running.
+            let table = &mut STACK_TABLES[next_stack_table];
+            next_stack_table += 1;
+
+            for (j, page) in table.0.iter_mut().enumerate() {
+                let phys = (desc & SECTION_BASE_MASK) + (j * PAGE_SIZE) as u32;
+                *page = with_small_page_access(
+                    phys | (desc & SECTION_CB_MASK) | L2_TYPE_SMALL_PAGE,
+                    Access::Privileged,
+                );
+            }
+
+            let table_phys = virt_to_phys(redboot_table, table as *const _ as usize);
+            table_phys | (desc & L1_DOMAIN_MASK) | L1_BIT4 | L1_TYPE_COARSE
+        } else if user_text.start <= start && last < user_text.end {
+            with_section_access(desc, Access::UserReadOnly)
+        } else if user_data.start <= start && last < user_data.end {
+            with_section_access(desc, Access::User)
+        } else if start < ram_end {
+            with_section_access(desc, Access::Privileged)
+        } else {
+            with_section_access(desc, Access::User)
+        };
+    }
+
+    // the MMU's table walk bypasses the D-cache
+    clean_dcache_range(
+        L1_TABLE.0.as_ptr() as usize,
+        core::mem::size_of::<L1Table>(),
+    );
+    clean_dcache_range(
+        STACK_TABLES.as_ptr() as usize,
+        core::mem::size_of::<[CoarseTable; STACK_SECTIONS]>(),
+    );
+
+    // all domains are "clients", i.e: accesses are checked against AP bits
+    write_dacr(0x5555_5555);
+    write_ttb(virt_to_phys(redboot_table, L1_TABLE.0.as_ptr() as usize));
+    invalidate_tlb();
+}
+
+/// Returns a pointer to the second-level descriptor for the given user stack
+/// page.
+unsafe fn stack_page_desc(addr: usize) -> *mut u32 {
+    let first_section = user_stacks_region().start / SECTION_SIZE;
+    let table = &mut STACK_TABLES[addr / SECTION_SIZE - first_section];
+    &mut table.0[(addr % SECTION_SIZE) / PAGE_SIZE]
+}
+
+unsafe fn set_stack_access(slot: usize, access: Access) {
+    for page in (stack::base(slot)..stack::top(slot)).step_by(PAGE_SIZE) {
+        let desc = stack_page_desc(page);
+        ptr::write_volatile(
+            desc,
+            with_small_page_access(ptr::read_volatile(desc), access),
+        );
+        clean_dcache_range(desc as usize, core::mem::size_of::<u32>());
+    }
+}
+
+/// Make the stack of the task in the given slot (and no other task's stack)
+/// accessible from user mode.
+///
+/// # Safety
+///
+/// [`init`] must have been called.
+pub unsafe fn activate_task_memory(slot: usize) {
+    if ACTIVE_STACK == Some(slot) {
+        return;
+    }
+
+    if let Some(prev_slot) = ACTIVE_STACK {
+        set_stack_access(prev_slot, Access::Privileged);
+    }
+    set_stack_access(slot, Access::User);
+    ACTIVE_STACK = Some(slot);
+
+    invalidate_tlb();
+}
+
+/// Check if the `len` bytes starting at `addr` are readable (or if `write` is
+/// set, writable) from user mode while the task in the given slot is running.
+pub fn user_accessible(slot: usize, addr: usize, len: usize, write: bool) -> bool {
+    let end = match addr.checked_add(len) {
+        Some(end) => end,
+        None => return false,
+    };
+    let within = |region: Range<usize>| region.start <= addr && end <= region.end;
+
+    (!write && within(user_text_region()))
+        || within(user_data_region())
+        || within(stack::base(slot)..stack::top(slot))
+}
+
+unsafe fn read_ttb() -> u32 {
+    let ttb: u32;
+    asm!("mrc p15, 0, {}, c2, c0, 0", out(reg) ttb);
+    ttb
+}
+
+unsafe fn write_ttb(ttb: u32) {
+    asm!("mcr p15, 0, {}, c2, c0, 0", in(reg) ttb);
+}
+
+unsafe fn write_dacr(dacr: u32) {
+    asm!("mcr p15, 0, {}, c3, c0, 0", in(reg) dacr);
+}
+
+unsafe fn invalidate_tlb() {
+    asm!("mcr p15, 0, {}, c8, c7, 0", in(reg) 0);
+}
+
+/// Write back any dirty D-cache lines covering the given range, and drain the
+/// write buffer.
+unsafe fn clean_dcache_range(start: usize, len: usize) {
+    let mut line = start & !(CACHE_LINE_SIZE - 1);
+    while line < start + len {
+        asm!("mcr p15, 0, {}, c7, c10, 1", in(reg) line);
+        line += CACHE_LINE_SIZE;
+    }
+    asm!("mcr p15, 0, {}, c7, c10, 4", in(reg) 0);
+}
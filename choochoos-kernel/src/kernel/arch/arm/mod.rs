@@ -1,19 +1,44 @@
 //! Architecture specific code for 32-bit ARM
 
+mod backtrace;
 mod create_task;
 mod ctx_switch;
+mod fault_handler;
+#[cfg(feature = "gdb")]
+mod gdb;
 mod irq_handler;
+#[cfg(feature = "mmu")]
+mod mmu;
 mod swi_handler;
 mod userstack;
 
-pub use create_task::fresh_stack;
+pub use backtrace::{print_kernel_backtrace, print_user_state};
+pub use create_task::{free_stack, fresh_stack};
 pub use ctx_switch::_activate_task;
+#[cfg(feature = "gdb")]
+pub use gdb::{next_pc, read_registers, sync_instruction, write_registers, BREAKPOINT};
+#[cfg(feature = "mmu")]
+pub use mmu::{activate_task_memory, user_accessible};
 pub use userstack::UserStack;
 
 pub unsafe fn init() {
-    use ctx_switch::{_irq_handler, _swi_handler};
+    use ctx_switch::{
+        _data_abort_handler, _irq_handler, _prefetch_abort_handler, _swi_handler,
+        _undefined_instruction_handler,
+    };
 
     // Register exception handlers
+    core::ptr::write_volatile(
+        0x24 as *mut unsafe extern "C" fn(),
+        _undefined_instruction_handler,
+    );
     core::ptr::write_volatile(0x28 as *mut unsafe extern "C" fn(), _swi_handler);
+    core::ptr::write_volatile(0x2c as *mut unsafe extern "C" fn(), _prefetch_abort_handler);
+    core::ptr::write_volatile(0x30 as *mut unsafe extern "C" fn(), _data_abort_handler);
     core::ptr::write_volatile(0x38 as *mut unsafe extern "C" fn(), _irq_handler);
+
+    // the vector table lives in (soon to be privileged-only) RAM, so the
+    // handlers must be registered before the MMU is reconfigured.
+    #[cfg(feature = "mmu")]
+    mmu::init();
 }
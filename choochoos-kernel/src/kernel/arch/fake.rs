@@ -0,0 +1,95 @@
+//! A stand-in "architecture" for the kernel's unit tests.
+//!
+//! Tasks never actually run. Instead, tests activate tasks using a scripted
+//! [`Activate`](crate::kernel::Activate) implementation, which fills in a
+//! task's [`UserStack`] as though it had just made a syscall, and dispatches
+//! the syscall itself.
+
+use core::ops::Range;
+use core::ptr;
+
+/// Number of syscall argument / return value "registers".
+const NUM_REGS: usize = 6;
+
+/// A suspended task's (fake) saved state.
+#[repr(C)]
+#[derive(Debug)]
+pub struct UserStack {
+    /// Syscall number of the most recent syscall.
+    pub syscall_no: u8,
+    /// Syscall parameters. `regs[0]` doubles as the syscall return value.
+    pub regs: [usize; NUM_REGS],
+}
+
+impl UserStack {
+    /// Inject a return value into the saved user stack.
+    ///
+    /// # Panics
+    ///
+    /// Currently only supports values with size equal to
+    /// `mem::size_of::<usize>()`
+    pub fn inject_return_value<T: Copy>(&mut self, val: T) {
+        assert_eq!(core::mem::size_of::<T>(), core::mem::size_of::<usize>());
+        self.regs[0] = unsafe { *(&val as *const _ as *const usize) };
+    }
+
+    /// Extract arguments for a saved user stack.
+    pub fn args(&mut self) -> UserStackArgs<'_> {
+        UserStackArgs {
+            stack: self,
+            idx: 0,
+        }
+    }
+}
+
+/// Helper to extract arguments from a user's stack.
+pub struct UserStackArgs<'a> {
+    stack: &'a UserStack,
+    idx: usize,
+}
+
+impl<'a> UserStackArgs<'a> {
+    /// Obtain a reference to the next argument in the user's stack.
+    pub unsafe fn extract_ref<T: Copy>(&mut self) -> &'a T {
+        assert_eq!(core::mem::size_of::<T>(), core::mem::size_of::<usize>());
+        let ret = &self.stack.regs[self.idx];
+        self.idx += 1;
+        &*(ret as *const usize as *const T)
+    }
+
+    /// Obtain a copy of the next argument in the user's stack.
+    pub unsafe fn extract<T: 'a + Copy>(&mut self) -> T {
+        *self.extract_ref()
+    }
+}
+
+pub unsafe fn fresh_stack(
+    start_addr: usize,
+    _function: unsafe extern "C" fn(),
+) -> ptr::NonNull<UserStack> {
+    let sp = ptr::NonNull::new_unchecked(
+        (start_addr - core::mem::size_of::<UserStack>()) as *mut UserStack,
+    );
+    ptr::write(
+        sp.as_ptr(),
+        UserStack {
+            syscall_no: 0,
+            regs: [0; NUM_REGS],
+        },
+    );
+    sp
+}
+
+pub unsafe fn free_stack(_sp: ptr::NonNull<UserStack>) {}
+
+pub unsafe fn _activate_task(_sp: ptr::NonNull<UserStack>) -> ptr::NonNull<UserStack> {
+    panic!("tasks can only be activated using a scripted `Activate` in tests")
+}
+
+pub unsafe fn init() {}
+
+pub fn print_kernel_backtrace() {}
+
+pub fn print_user_state(saved: &UserStack, _stack: Range<usize>) {
+    crate::bwkprintln!("  syscall_no={} regs={:x?}", saved.syscall_no, saved.regs);
+}
@@ -0,0 +1,19 @@
+//! Crash dump support.
+
+use core::ops::Range;
+
+use crate::bwkprintln;
+
+use super::userstack::UserStack;
+
+/// Backtraces are left to the host's panic handler.
+pub fn print_kernel_backtrace() {
+    bwkprintln!("  (set RUST_BACKTRACE=1 for a backtrace)");
+}
+
+/// Print a suspended task's saved syscall arguments.
+///
+/// Tasks are backed by host threads, so there's no machine state to inspect.
+pub fn print_user_state(saved: &UserStack, _stack: Range<usize>) {
+    bwkprintln!("  syscall_no={} regs={:x?}", saved.syscall_no, saved.regs);
+}
@@ -0,0 +1,72 @@
+use core::ffi::c_void;
+use core::ptr;
+use std::sync::mpsc;
+
+use super::ctx_switch;
+use super::pthread;
+use super::userstack::UserStack;
+
+/// Everything a freshly spawned task thread needs to start running its task.
+struct TaskStart {
+    sp: ptr::NonNull<UserStack>,
+    resume: mpsc::Receiver<()>,
+    function: unsafe extern "C" fn(),
+}
+
+/// Entry point of each task's backing thread.
+extern "C" fn task_thread(arg: *mut c_void) -> *mut c_void {
+    // SAFETY: `arg` was leaked from a `Box<TaskStart>` by `fresh_stack`
+    let TaskStart {
+        sp,
+        resume,
+        function,
+    } = *unsafe { Box::from_raw(arg as *mut TaskStart) };
+
+    if ctx_switch::enter_task(sp, resume) {
+        unsafe { function() };
+        panic!("task returned without calling Exit");
+    }
+
+    ptr::null_mut()
+}
+
+pub unsafe fn fresh_stack(
+    start_addr: usize,
+    function: unsafe extern "C" fn(),
+) -> ptr::NonNull<UserStack> {
+    let sp = ptr::NonNull::new_unchecked(
+        (start_addr - core::mem::size_of::<UserStack>()) as *mut UserStack,
+    );
+
+    let (resume_tx, resume_rx) = mpsc::sync_channel(1);
+    let start = Box::new(TaskStart {
+        sp,
+        resume: resume_rx,
+        function,
+    });
+
+    let mut thread = 0;
+    let err = pthread::pthread_create(
+        &mut thread,
+        ptr::null(),
+        task_thread,
+        Box::into_raw(start) as *mut c_void,
+    );
+    assert_eq!(err, 0, "failed to spawn task thread");
+    pthread::pthread_detach(thread);
+
+    // the task's thread won't touch its `UserStack` until it's activated
+    ptr::write(sp.as_ptr(), UserStack::new(resume_tx));
+
+    sp
+}
+
+/// Release the stack of a task which will never be activated again.
+///
+/// Hanging up the task's resume channel parks its backing thread for good (see
+/// `ctx_switch::__choochoos_syscall`), after which the thread no longer touches
+/// the task's `UserStack`. Parked threads are only reclaimed when the process
+/// exits, which is fine for a test platform.
+pub unsafe fn free_stack(sp: ptr::NonNull<UserStack>) {
+    ptr::drop_in_place(sp.as_ptr());
+}
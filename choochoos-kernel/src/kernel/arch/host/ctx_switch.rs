@@ -0,0 +1,105 @@
+//! Routines for handing control between the Kernel and task threads.
+
+use core::cell::RefCell;
+use core::ptr;
+use std::sync::mpsc;
+
+use super::userstack::UserStack;
+
+/// Wrapper around a task's stack pointer that can be sent between threads.
+pub(super) struct SendSp(pub ptr::NonNull<UserStack>);
+
+// SAFETY: While tasks are backed by OS threads, only a single thread (either
+// the kernel, or a single task) is ever running at any given time.
+unsafe impl Send for SendSp {}
+
+/// Channel over which task threads hand control back to the kernel.
+static mut TRAPS: Option<(mpsc::SyncSender<SendSp>, mpsc::Receiver<SendSp>)> = None;
+
+thread_local! {
+    /// The stack pointer of the task running on this thread, along with the
+    /// channel used to resume it.
+    static TASK: RefCell<Option<(ptr::NonNull<UserStack>, mpsc::Receiver<()>)>> =
+        const { RefCell::new(None) };
+}
+
+unsafe fn traps() -> &'static (mpsc::SyncSender<SendSp>, mpsc::Receiver<SendSp>) {
+    match &TRAPS {
+        Some(traps) => traps,
+        None => panic!("called into the kernel before `arch::init`"),
+    }
+}
+
+pub(super) unsafe fn init() {
+    TRAPS = Some(mpsc::sync_channel(1));
+}
+
+/// Called from a freshly spawned task thread. Blocks until the task is
+/// scheduled for the first time.
+///
+/// Returns `false` if the task will never be scheduled.
+pub(super) fn enter_task(sp: ptr::NonNull<UserStack>, resume: mpsc::Receiver<()>) -> bool {
+    if resume.recv().is_err() {
+        return false;
+    }
+
+    TASK.with(|task| *task.borrow_mut() = Some((sp, resume)));
+    true
+}
+
+/// Resume execution of the task with the given stack pointer, blocking until
+/// the task traps back into the kernel.
+pub unsafe fn _activate_task(next_sp: ptr::NonNull<UserStack>) -> ptr::NonNull<UserStack> {
+    // Real hardware would immediately take any pending IRQ upon returning to
+    // user mode, before the task could make any progress.
+    if crate::platform::interrupts::irq_pending() {
+        super::irq_handler::handle_irq();
+        return next_sp;
+    }
+
+    (next_sp.as_ref().resume)
+        .send(())
+        .expect("task thread unexpectedly hung up");
+
+    let SendSp(sp) = traps().1.recv().expect("task thread unexpectedly hung up");
+    super::swi_handler::handle_syscall(sp.as_ref().syscall_no, sp.as_ptr());
+
+    sp
+}
+
+/// Userspace syscall entry point (i.e: the host's equivalent of `swi #x`).
+///
+/// Invoked from the `syscall` crate when compiled for non-ARM targets.
+#[no_mangle]
+pub unsafe extern "C" fn __choochoos_syscall(no: u8, args: *const usize, nargs: usize) -> usize {
+    let (mut sp, resume) = TASK.with(|task| match task.borrow().as_ref() {
+        Some((sp, resume)) => (*sp, resume as *const mpsc::Receiver<()>),
+        None => panic!("syscall invoked from outside of a task"),
+    });
+
+    {
+        let stack = sp.as_mut();
+        stack.syscall_no = no;
+        stack.regs[..nargs].copy_from_slice(core::slice::from_raw_parts(args, nargs));
+    }
+
+    traps()
+        .0
+        .send(SendSp(sp))
+        .expect("kernel unexpectedly hung up");
+
+    // The kernel hangs up once the task has exited / been destroyed (see
+    // `create_task::free_stack`), at which point the task is never returned
+    // to. As on real hardware, none of the task's destructors are run: the
+    // thread is parked until the process exits, as exiting the thread (e.g:
+    // using `pthread_exit`) would unwind through the task's frames.
+    //
+    // SAFETY: the receiver lives in `TASK`, which outlives the task's frames.
+    if (*resume).recv().is_err() {
+        loop {
+            std::thread::park();
+        }
+    }
+
+    sp.as_ref().regs[0]
+}
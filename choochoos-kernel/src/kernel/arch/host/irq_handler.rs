@@ -0,0 +1,10 @@
+/// Called by [`_activate_task`](super::ctx_switch::_activate_task) whenever a
+/// simulated interrupt is pending.
+pub unsafe fn handle_irq() {
+    let kernel = match &mut crate::KERNEL {
+        Some(kernel) => kernel,
+        None => core::hint::unreachable_unchecked(),
+    };
+
+    kernel.handle_irq();
+}
@@ -0,0 +1,25 @@
+//! Architecture specific code for running the kernel as a regular (hosted)
+//! process.
+//!
+//! Each task is backed by a dedicated OS thread, but only a single thread is
+//! ever allowed to run at any given time: control is explicitly handed back
+//! and forth between the kernel and the currently active task, mirroring the
+//! context switch routines used on real hardware. In other words, tasks are
+//! "green threads" which just so happen to be scheduled by the host OS.
+
+mod backtrace;
+mod create_task;
+mod ctx_switch;
+mod irq_handler;
+mod pthread;
+mod swi_handler;
+mod userstack;
+
+pub use backtrace::{print_kernel_backtrace, print_user_state};
+pub use create_task::{free_stack, fresh_stack};
+pub use ctx_switch::_activate_task;
+pub use userstack::UserStack;
+
+pub unsafe fn init() {
+    ctx_switch::init();
+}
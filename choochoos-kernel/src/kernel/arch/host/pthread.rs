@@ -0,0 +1,23 @@
+//! Minimal bindings to the host's POSIX threads API.
+//!
+//! Task threads are spawned using `pthread_create` directly (instead of via
+//! `std::thread`), so that a panic on a task thread can't unwind out of its
+//! `extern "C"` entry point, and aborts the process. Under `std::thread`, the
+//! panic would only end the task's thread, leaving the kernel waiting for the
+//! task to trap back into it forever.
+
+use core::ffi::c_void;
+
+/// Opaque handle to a thread (which is pointer-sized on all supported hosts).
+#[allow(non_camel_case_types)]
+pub type pthread_t = usize;
+
+extern "C" {
+    pub fn pthread_create(
+        thread: *mut pthread_t,
+        attr: *const c_void,
+        start_routine: extern "C" fn(*mut c_void) -> *mut c_void,
+        arg: *mut c_void,
+    ) -> i32;
+    pub fn pthread_detach(thread: pthread_t) -> i32;
+}
@@ -0,0 +1,22 @@
+use core::ptr;
+
+use abi::syscall::SyscallNo;
+
+use super::userstack::UserStack;
+
+/// Called by [`_activate_task`](super::ctx_switch::_activate_task) whenever the
+/// active task makes a syscall.
+pub unsafe fn handle_syscall(no: u8, sp: *mut UserStack) {
+    let mut sp = ptr::NonNull::new(sp).expect("passed null sp to handle_syscall");
+    let stack = sp.as_mut();
+
+    let syscall_no = SyscallNo::from_u8(no).expect("invalid syscall");
+    log::trace!("Called {:?}", syscall_no);
+
+    let kernel = match &mut crate::KERNEL {
+        Some(kernel) => kernel,
+        None => core::hint::unreachable_unchecked(),
+    };
+
+    crate::kernel::dispatch::dispatch_syscall(kernel, syscall_no, stack);
+}
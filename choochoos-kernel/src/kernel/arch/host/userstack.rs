@@ -0,0 +1,89 @@
+use std::sync::mpsc;
+
+/// Number of syscall argument / return value "registers".
+const NUM_REGS: usize = 6;
+
+/// Provides a structured view into a suspended task, as returned by the
+/// context switch routines in the [`ctx_switch`](super::ctx_switch) module.
+///
+/// Unlike it's ARM counterpart, this struct doesn't contain any real machine
+/// state, and instead serves as the "mailbox" between a task's backing thread
+/// and the kernel.
+///
+/// This struct has no constructor, and is marked `#[non_exhaustive]`. This is
+/// because it should never be directly initialized, and should only be used
+/// through a reference that was obtained using an unsafe cast from a raw task
+/// stack pointer.
+#[repr(C)]
+#[derive(Debug)]
+#[non_exhaustive] // disallow brace initialization
+pub struct UserStack {
+    /// Syscall number of the most recent syscall.
+    pub syscall_no: u8,
+    /// Syscall parameters. `regs[0]` doubles as the syscall return value.
+    pub regs: [usize; NUM_REGS],
+    /// Wakes up the task's backing thread.
+    pub(super) resume: mpsc::SyncSender<()>,
+}
+
+impl UserStack {
+    /// Create a new `UserStack` for a task whose backing thread is waiting on
+    /// `resume`'s corresponding `Receiver`.
+    pub(super) fn new(resume: mpsc::SyncSender<()>) -> UserStack {
+        UserStack {
+            syscall_no: 0,
+            regs: [0; NUM_REGS],
+            resume,
+        }
+    }
+
+    /// Inject a return value into the saved user stack.
+    ///
+    /// # Panics
+    ///
+    /// Currently only supports values with size equal to
+    /// `mem::size_of::<usize>()`
+    pub fn inject_return_value<T: Copy>(&mut self, val: T) {
+        assert_eq!(core::mem::size_of::<T>(), core::mem::size_of::<usize>());
+        self.regs[0] = unsafe { *(&val as *const _ as *const usize) };
+    }
+
+    /// Extract arguments for a saved user stack.
+    pub fn args(&mut self) -> UserStackArgs<'_> {
+        UserStackArgs {
+            stack: self,
+            idx: 0,
+        }
+    }
+}
+
+/// Helper to extract arguments from a user's stack.
+pub struct UserStackArgs<'a> {
+    stack: &'a UserStack,
+    idx: usize,
+}
+
+impl<'a> UserStackArgs<'a> {
+    /// Obtain a reference to the next argument in the user's stack.
+    ///
+    /// # Panics
+    ///
+    /// Currently only supports values with size equal to
+    /// `mem::size_of::<usize>()`, and at most 6 arguments.
+    pub unsafe fn extract_ref<T: Copy>(&mut self) -> &'a T {
+        assert_eq!(core::mem::size_of::<T>(), core::mem::size_of::<usize>());
+        let ret = &self.stack.regs[self.idx];
+        self.idx += 1;
+        &*(ret as *const usize as *const T)
+    }
+
+    /// Obtain a copy of the next argument in the user's stack.
+    ///
+    /// # Panics
+    ///
+    /// Currently only supports values with size equal to
+    /// `mem::size_of::<usize>()`, and at most 6 arguments.
+    pub unsafe fn extract<T: 'a + Copy>(&mut self) -> T {
+        *self.extract_ref()
+    }
+}
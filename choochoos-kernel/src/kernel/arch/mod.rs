@@ -3,9 +3,28 @@
 // HACK: the `cfg(target_arch)` calls really aught to be here,
 // but it breaks my editor's rust language support (it assumes target_arch =
 // "x86"), so they're commented out for now.
+//
+// Instead, the `host` "architecture" is selected by the `platform-host`
+// feature, with `arm` being used for everything else.
 
 // #[cfg(target_arch = "arm")]
+#[cfg(not(feature = "platform-host"))]
 mod arm;
 
 // #[cfg(target_arch = "arm")]
+#[cfg(not(feature = "platform-host"))]
 pub use arm::*;
+
+#[cfg(all(feature = "platform-host", not(test)))]
+mod host;
+
+#[cfg(all(feature = "platform-host", not(test)))]
+pub use host::*;
+
+// Unit tests drive the kernel directly (see `kernel::tests`), without ever
+// running any real tasks.
+#[cfg(all(feature = "platform-host", test))]
+mod fake;
+
+#[cfg(all(feature = "platform-host", test))]
+pub use fake::*;
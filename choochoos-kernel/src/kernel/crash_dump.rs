@@ -0,0 +1,121 @@
+//! Crash dumps, printed when the kernel (or a task) panics.
+
+use core::fmt;
+
+use abi::Tid;
+
+use crate::bwkprintln;
+
+use super::task::TaskState;
+use super::{arch, stack, Kernel};
+
+/// Single-line summary of a task's state (omitting the contents of any user
+/// buffers).
+pub(super) struct StateSummary<'a>(pub &'a TaskState);
+
+impl fmt::Display for StateSummary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            TaskState::Ready => write!(f, "Ready"),
+            TaskState::SendWait { receiver, next, .. } => {
+                write!(f, "SendWait(receiver={:?}, next={:?})", receiver, next)
+            }
+            TaskState::RecvWait { .. } => write!(f, "RecvWait"),
+            TaskState::ReplyWait { receiver, .. } => {
+                write!(f, "ReplyWait(receiver={:?})", receiver)
+            }
+            TaskState::EventWait => write!(f, "EventWait"),
+            TaskState::DelayWait => write!(f, "DelayWait"),
+            TaskState::MailboxWait { mailbox, next, .. } => {
+                write!(f, "MailboxWait(mailbox={:?}, next={:?})", mailbox, next)
+            }
+        }
+    }
+}
+
+/// Summary of a task slot's stack usage.
+#[cfg_attr(feature = "platform-host", allow(dead_code))]
+struct StackSummary(usize);
+
+impl fmt::Display for StackSummary {
+    #[cfg(not(feature = "platform-host"))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:#x}/{:#x}",
+            stack::high_water_mark(self.0),
+            super::USER_STACK_SIZE
+        )
+    }
+
+    // stack usage isn't tracked on the host
+    #[cfg(feature = "platform-host")]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "untracked")
+    }
+}
+
+impl Kernel {
+    /// Print a crash report describing the current task's saved registers (and
+    /// backtrace), the state of every task, and the most recent kernel log
+    /// output.
+    ///
+    /// Output bypasses the kernel log, and is printed directly via
+    /// `bwkprintln!`.
+    pub fn crash_dump(&self) {
+        bwkprintln!("========== crash dump ==========");
+
+        match self.current_tid {
+            None => bwkprintln!("no task was running"),
+            Some(tid) => {
+                bwkprintln!("current task: {:?}", tid);
+                match self.trap_frame {
+                    Some(frame) => arch::print_user_state(
+                        // SAFETY: the trap frame lives on the (live) current task's stack
+                        unsafe { frame.as_ref() },
+                        stack::base(tid.slot())..stack::top(tid.slot()),
+                    ),
+                    None => bwkprintln!("  (registers unavailable)"),
+                }
+            }
+        }
+
+        bwkprintln!("tasks:");
+        for (slot, task) in self.tasks.iter().enumerate() {
+            let task = match task {
+                Some(task) => task,
+                None => continue,
+            };
+
+            bwkprintln!(
+                "  {:?} priority={} parent={:?} state={} send_queue={:?}..{:?} stack={}",
+                Tid::new(slot, self.generations[slot] as usize),
+                task.priority,
+                task.parent_tid,
+                StateSummary(&task.state),
+                task.send_queue_head,
+                task.send_queue_tail,
+                StackSummary(slot),
+            );
+        }
+
+        bwkprintln!("recent kernel log:");
+        crate::kernel_log::print_recent();
+        bwkprintln!("================================");
+    }
+}
+
+/// Print a backtrace of the kernel, followed by a crash dump of the global
+/// kernel (if it has been initialized).
+///
+/// Called from the platform's panic handler.
+pub fn crash_dump() {
+    bwkprintln!("kernel backtrace:");
+    arch::print_kernel_backtrace();
+
+    unsafe {
+        if let Some(kernel) = &crate::KERNEL {
+            kernel.crash_dump();
+        }
+    }
+}
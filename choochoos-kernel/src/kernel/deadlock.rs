@@ -0,0 +1,100 @@
+//! Detecting Send-Receive-Reply deadlocks.
+//!
+//! Each task in the `SendWait` or `ReplyWait` state is blocked on exactly one
+//! other task (the receiver), and can only be unblocked by it. If following
+//! these edges from a task leads back to itself, none of the tasks in the cycle
+//! will ever run again (short of being destroyed).
+//!
+//! Tasks in the `RecvWait`, `EventWait`, and `MailboxWait` states aren't
+//! blocked on any particular task, and therefore never take part in a cycle.
+//! Neither do tasks which are sending with a timeout, as they'll eventually
+//! give up.
+
+use abi::Tid;
+
+use super::crash_dump::StateSummary;
+use super::task::TaskState;
+use super::{Kernel, MAX_TASKS};
+
+impl Kernel {
+    /// Returns the task which the task in `slot` is waiting on to Receive /
+    /// Reply (if any).
+    fn blocked_on(&self, slot: usize) -> Option<Tid> {
+        let tid = Tid::new(slot, self.generations[slot] as usize);
+        match self.tasks[slot].as_ref()?.state {
+            TaskState::SendWait { .. } if self.timers.contains(tid) => None,
+            TaskState::SendWait { receiver, .. } | TaskState::ReplyWait { receiver, .. } => {
+                Some(receiver)
+            }
+            _ => None,
+        }
+    }
+
+    /// Find every cycle in the blocked-on graph, calling `f` with the Tids of
+    /// the tasks in each cycle (where each task is blocked on the next, and the
+    /// last task is blocked on the first).
+    pub(super) fn for_each_deadlock(&self, mut f: impl FnMut(&[Tid])) {
+        // which walk (if any) first visited each slot
+        let mut visited = [None; MAX_TASKS];
+        let mut cycle = [Tid::from(0); MAX_TASKS];
+
+        for start in 0..MAX_TASKS {
+            // every task has at most one outgoing edge, so walks which reach a
+            // slot visited by an earlier walk can't find any new cycles.
+            let mut slot = start;
+            let found_cycle = loop {
+                if visited[slot].is_some() {
+                    break visited[slot] == Some(start);
+                }
+                visited[slot] = Some(start);
+                match self.blocked_on(slot) {
+                    Some(receiver) if self.task(receiver).is_some() => slot = receiver.slot(),
+                    _ => break false,
+                }
+            };
+
+            if !found_cycle {
+                continue;
+            }
+
+            // `slot` is part of the cycle found by this walk
+            let mut len = 0;
+            let mut tid = Tid::new(slot, self.generations[slot] as usize);
+            loop {
+                cycle[len] = tid;
+                len += 1;
+                tid = self.blocked_on(tid.slot()).unwrap();
+                if tid.slot() == slot {
+                    break;
+                }
+            }
+            f(&cycle[..len]);
+        }
+    }
+
+    /// Print the Tids and states of the tasks in a deadlocked cycle.
+    pub(super) fn report_deadlock(&self, cycle: &[Tid]) {
+        kprintln!("deadlock detected between {} task(s):", cycle.len());
+        for &tid in cycle {
+            let task = self.task(tid).unwrap();
+            kprintln!("  {:?} {}", tid, StateSummary(&task.state));
+        }
+    }
+
+    /// Report any deadlocks which haven't been reported already.
+    ///
+    /// Called whenever the ready queue empties.
+    pub(super) fn check_for_deadlocks(&mut self) {
+        let mut deadlocked = [None; MAX_TASKS];
+        self.for_each_deadlock(|cycle| {
+            // deadlocked tasks remain deadlocked, so only report new cycles
+            if cycle.iter().any(|t| self.deadlocked[t.slot()] != Some(*t)) {
+                self.report_deadlock(cycle);
+            }
+            for &tid in cycle {
+                deadlocked[tid.slot()] = Some(tid);
+            }
+        });
+        self.deadlocked = deadlocked;
+    }
+}
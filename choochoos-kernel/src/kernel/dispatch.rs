@@ -0,0 +1,612 @@
+//! Architecture-agnostic syscall dispatch.
+//!
+//! Each `dispatch_*` function unpacks the syscall's arguments from the calling
+//! task's suspended [`UserStack`], invokes the corresponding
+//! `Kernel::syscall_*` handler, and writes back the syscall's return value (if
+//! any).
+
+use core::ptr;
+
+use abi::{syscall::SyscallNo, MailboxId, Tid};
+
+use crate::kernel::Kernel;
+use crate::util::user_slice::{self, UserSlice, UserSliceMut};
+
+use super::arch::UserStack;
+
+/// Check that the `len` element buffer at `ptr` is readable by the current
+/// task, killing the task if it isn't. Null buffers are always accepted.
+///
+/// Buffers are only validated when the `mmu` feature is enabled, as tasks can
+/// access any memory they like otherwise.
+fn check_user_buffer<T>(kernel: &mut Kernel, ptr: *const T, len: usize) -> bool {
+    check_user_access(kernel, ptr, len, false)
+}
+
+/// Like [`check_user_buffer`], but for buffers the kernel writes to, which
+/// must be writable by the current task (e.g: not its code).
+fn check_user_buffer_mut<T>(kernel: &mut Kernel, ptr: *mut T, len: usize) -> bool {
+    check_user_access(kernel, ptr, len, true)
+}
+
+fn check_user_access<T>(kernel: &mut Kernel, ptr: *const T, len: usize, write: bool) -> bool {
+    #[cfg(feature = "mmu")]
+    {
+        let tid = kernel.current_tid.expect("no task is running");
+        let accessible = ptr.is_null()
+            || match len.checked_mul(core::mem::size_of::<T>()) {
+                Some(size) => super::arch::user_accessible(tid.slot(), ptr as usize, size, write),
+                None => false,
+            };
+
+        if !accessible {
+            kernel.kill_current_task(format_args!(
+                "passed an invalid buffer to the kernel (ptr={:p}, len={})",
+                ptr, len
+            ));
+        }
+
+        accessible
+    }
+
+    #[cfg(not(feature = "mmu"))]
+    {
+        let _ = (kernel, ptr, len, write);
+        true
+    }
+}
+
+fn dispatch_yield(kernel: &mut Kernel, _stack: &mut UserStack) {
+    kernel.syscall_yield()
+}
+
+fn dispatch_exit(kernel: &mut Kernel, _stack: &mut UserStack) {
+    kernel.syscall_exit();
+}
+
+fn dispatch_my_tid(kernel: &mut Kernel, stack: &mut UserStack) {
+    let ret = kernel.syscall_my_tid().into();
+    stack.inject_return_value(ret)
+}
+
+fn dispatch_my_parent_tid(kernel: &mut Kernel, stack: &mut UserStack) {
+    let ret = match kernel.syscall_my_parent_tid() {
+        Ok(tid) => tid.into() as isize,
+        Err(code) => code as isize,
+    };
+
+    stack.inject_return_value(ret)
+}
+
+fn dispatch_create(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let priority = unsafe { args.extract::<isize>() };
+    let function = unsafe { args.extract::<Option<unsafe extern "C" fn()>>() };
+
+    let ret = match kernel.syscall_create(priority, function) {
+        Ok(tid) => tid.into() as isize,
+        Err(code) => code as isize,
+    };
+
+    stack.inject_return_value(ret);
+}
+
+fn dispatch_reply(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let tid = unsafe { args.extract::<Tid>() };
+    let reply_ptr = unsafe { args.extract::<*mut u8>() };
+    let reply_len = unsafe { args.extract::<usize>() };
+
+    if !check_user_buffer(kernel, reply_ptr, reply_len) {
+        return;
+    }
+
+    let reply = if reply_ptr.is_null() {
+        UserSlice::empty()
+    } else {
+        unsafe { user_slice::from_raw_parts(ptr::NonNull::new_unchecked(reply_ptr), reply_len) }
+    };
+
+    let ret = match kernel.syscall_reply(tid, reply) {
+        Ok(response_len) => response_len,
+        Err(code) => code as usize,
+    };
+
+    stack.inject_return_value(ret)
+}
+
+/// Validate the buffers passed to one of the `Receive` variants, returning
+/// `None` if the current task was killed for passing an invalid buffer.
+fn receive_buffers(
+    kernel: &mut Kernel,
+    sender_tid_dst: *mut Tid,
+    msg_ptr: *mut u8,
+    msg_len: usize,
+) -> Option<(Option<ptr::NonNull<Tid>>, UserSliceMut<u8>)> {
+    if !(check_user_buffer_mut(kernel, sender_tid_dst, 1)
+        && check_user_buffer_mut(kernel, msg_ptr, msg_len))
+    {
+        return None;
+    }
+
+    let sender_tid_dst = if sender_tid_dst.is_null() {
+        None
+    } else {
+        unsafe { Some(ptr::NonNull::new_unchecked(sender_tid_dst)) }
+    };
+
+    let msg = if msg_ptr.is_null() {
+        UserSliceMut::empty()
+    } else {
+        unsafe { user_slice::from_raw_parts_mut(ptr::NonNull::new_unchecked(msg_ptr), msg_len) }
+    };
+
+    Some((sender_tid_dst, msg))
+}
+
+fn dispatch_receive(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let sender_tid_dst = unsafe { args.extract::<*mut Tid>() };
+    let msg_ptr = unsafe { args.extract::<*mut u8>() };
+    let msg_len = unsafe { args.extract::<usize>() };
+
+    let (sender_tid_dst, msg) = match receive_buffers(kernel, sender_tid_dst, msg_ptr, msg_len) {
+        Some(buffers) => buffers,
+        None => return,
+    };
+
+    if let Some(response_len) = kernel.syscall_receive(sender_tid_dst, msg) {
+        stack.inject_return_value(response_len)
+    };
+}
+
+fn dispatch_receive_timeout(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let sender_tid_dst = unsafe { args.extract::<*mut Tid>() };
+    let msg_ptr = unsafe { args.extract::<*mut u8>() };
+    let msg_len = unsafe { args.extract::<usize>() };
+    let timeout = unsafe { args.extract::<usize>() };
+
+    let (sender_tid_dst, msg) = match receive_buffers(kernel, sender_tid_dst, msg_ptr, msg_len) {
+        Some(buffers) => buffers,
+        None => return,
+    };
+
+    match kernel.syscall_receive_timeout(sender_tid_dst, msg, timeout) {
+        Ok(None) => {} // return value injected as part of the `Send` syscall
+        Ok(Some(response_len)) => stack.inject_return_value(response_len),
+        Err(code) => stack.inject_return_value(code),
+    };
+}
+
+/// Validate the buffers passed to one of the `Send` variants, returning `None`
+/// if the current task was killed for passing an invalid buffer.
+fn send_buffers(
+    kernel: &mut Kernel,
+    msg_ptr: *mut u8,
+    msg_len: usize,
+    reply_ptr: *mut u8,
+    reply_len: usize,
+) -> Option<(UserSlice<u8>, UserSliceMut<u8>)> {
+    if !(check_user_buffer(kernel, msg_ptr, msg_len)
+        && check_user_buffer_mut(kernel, reply_ptr, reply_len))
+    {
+        return None;
+    }
+
+    let msg = if msg_ptr.is_null() {
+        UserSlice::empty()
+    } else {
+        unsafe { user_slice::from_raw_parts(ptr::NonNull::new_unchecked(msg_ptr), msg_len) }
+    };
+
+    let reply = if reply_ptr.is_null() {
+        UserSliceMut::empty()
+    } else {
+        unsafe { user_slice::from_raw_parts_mut(ptr::NonNull::new_unchecked(reply_ptr), reply_len) }
+    };
+
+    Some((msg, reply))
+}
+
+fn dispatch_send(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let receiver_tid = unsafe { args.extract::<Tid>() };
+    let msg_ptr = unsafe { args.extract::<*mut u8>() };
+    let msg_len = unsafe { args.extract::<usize>() };
+    let reply_ptr = unsafe { args.extract::<*mut u8>() };
+    let reply_len = unsafe { args.extract::<usize>() };
+
+    let (msg, reply) = match send_buffers(kernel, msg_ptr, msg_len, reply_ptr, reply_len) {
+        Some(buffers) => buffers,
+        None => return,
+    };
+
+    match kernel.syscall_send(receiver_tid, msg, reply) {
+        Ok(()) => {} // return value injected as part of the `Reply` syscall
+        Err(code) => stack.inject_return_value(code),
+    };
+}
+
+fn dispatch_try_send(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let receiver_tid = unsafe { args.extract::<Tid>() };
+    let msg_ptr = unsafe { args.extract::<*mut u8>() };
+    let msg_len = unsafe { args.extract::<usize>() };
+    let reply_ptr = unsafe { args.extract::<*mut u8>() };
+    let reply_len = unsafe { args.extract::<usize>() };
+
+    let (msg, reply) = match send_buffers(kernel, msg_ptr, msg_len, reply_ptr, reply_len) {
+        Some(buffers) => buffers,
+        None => return,
+    };
+
+    match kernel.syscall_try_send(receiver_tid, msg, reply) {
+        Ok(()) => {} // return value injected as part of the `Reply` syscall
+        Err(code) => stack.inject_return_value(code),
+    };
+}
+
+fn dispatch_send_timeout(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let receiver_tid = unsafe { args.extract::<Tid>() };
+    let msg_ptr = unsafe { args.extract::<*mut u8>() };
+    let msg_len = unsafe { args.extract::<usize>() };
+    let reply_ptr = unsafe { args.extract::<*mut u8>() };
+    let reply_len = unsafe { args.extract::<usize>() };
+    let timeout = unsafe { args.extract::<usize>() };
+
+    let (msg, reply) = match send_buffers(kernel, msg_ptr, msg_len, reply_ptr, reply_len) {
+        Some(buffers) => buffers,
+        None => return,
+    };
+
+    match kernel.syscall_send_timeout(receiver_tid, msg, reply, timeout) {
+        Ok(()) => {} // return value injected as part of the `Reply` syscall
+        Err(code) => stack.inject_return_value(code),
+    };
+}
+
+fn dispatch_await_event(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let event_id = unsafe { args.extract::<usize>() };
+
+    match kernel.syscall_await_event(event_id) {
+        Ok(None) => {} // return value will be injected once an IRQ occurs
+        Ok(Some(val)) => stack.inject_return_value(val),
+        Err(code) => stack.inject_return_value(code),
+    };
+}
+
+fn dispatch_perf(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let perf_data = unsafe { args.extract::<*mut abi::PerfData>() };
+
+    if !check_user_buffer_mut(kernel, perf_data, 1) {
+        return;
+    }
+
+    let perf_data = if perf_data.is_null() {
+        None
+    } else {
+        unsafe { Some(ptr::NonNull::new_unchecked(perf_data)) }
+    };
+
+    kernel.syscall_perf(perf_data);
+}
+
+fn dispatch_perf_task(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let tid = unsafe { args.extract::<Tid>() };
+    let perf_data = unsafe { args.extract::<*mut abi::TaskPerfData>() };
+
+    if !check_user_buffer_mut(kernel, perf_data, 1) {
+        return;
+    }
+
+    let perf_data = if perf_data.is_null() {
+        None
+    } else {
+        unsafe { Some(ptr::NonNull::new_unchecked(perf_data)) }
+    };
+
+    let ret = match kernel.syscall_perf_task(tid, perf_data) {
+        Ok(()) => 0,
+        Err(code) => code as isize,
+    };
+
+    stack.inject_return_value(ret)
+}
+
+fn dispatch_destroy(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let tid = unsafe { args.extract::<Tid>() };
+
+    let ret = match kernel.syscall_destroy(tid) {
+        Ok(()) => 0,
+        Err(code) => code as isize,
+    };
+
+    stack.inject_return_value(ret)
+}
+
+fn dispatch_stack_usage(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let tid = unsafe { args.extract::<Tid>() };
+
+    let ret = match kernel.syscall_stack_usage(tid) {
+        Ok(bytes) => bytes as isize,
+        Err(code) => code as isize,
+    };
+
+    stack.inject_return_value(ret)
+}
+
+fn dispatch_read_log(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let buf_ptr = unsafe { args.extract::<*mut u8>() };
+    let buf_len = unsafe { args.extract::<usize>() };
+
+    if !check_user_buffer_mut(kernel, buf_ptr, buf_len) {
+        return;
+    }
+
+    let buf = if buf_ptr.is_null() {
+        UserSliceMut::empty()
+    } else {
+        unsafe { user_slice::from_raw_parts_mut(ptr::NonNull::new_unchecked(buf_ptr), buf_len) }
+    };
+
+    let ret = kernel.syscall_read_log(buf);
+    stack.inject_return_value(ret)
+}
+
+fn dispatch_set_log_level(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let module_ptr = unsafe { args.extract::<*mut u8>() };
+    let module_len = unsafe { args.extract::<usize>() };
+    let level = unsafe { args.extract::<usize>() };
+
+    if !check_user_buffer(kernel, module_ptr, module_len) {
+        return;
+    }
+
+    let module = if module_ptr.is_null() {
+        UserSlice::empty()
+    } else {
+        unsafe { user_slice::from_raw_parts(ptr::NonNull::new_unchecked(module_ptr), module_len) }
+    };
+
+    let ret = match kernel.syscall_set_log_level(module, level) {
+        Ok(()) => 0,
+        Err(code) => code as isize,
+    };
+
+    stack.inject_return_value(ret)
+}
+
+fn dispatch_read_trace(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let buf_ptr = unsafe { args.extract::<*mut abi::trace::Record>() };
+    let buf_len = unsafe { args.extract::<usize>() };
+
+    if !check_user_buffer_mut(kernel, buf_ptr, buf_len) {
+        return;
+    }
+
+    let buf = if buf_ptr.is_null() {
+        UserSliceMut::empty()
+    } else {
+        unsafe { user_slice::from_raw_parts_mut(ptr::NonNull::new_unchecked(buf_ptr), buf_len) }
+    };
+
+    let ret = kernel.syscall_read_trace(buf);
+    stack.inject_return_value(ret)
+}
+
+fn dispatch_detect_deadlocks(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let tids_ptr = unsafe { args.extract::<*mut Tid>() };
+    let tids_len = unsafe { args.extract::<usize>() };
+
+    if !check_user_buffer_mut(kernel, tids_ptr, tids_len) {
+        return;
+    }
+
+    let tids = if tids_ptr.is_null() {
+        UserSliceMut::empty()
+    } else {
+        unsafe { user_slice::from_raw_parts_mut(ptr::NonNull::new_unchecked(tids_ptr), tids_len) }
+    };
+
+    let ret = kernel.syscall_detect_deadlocks(tids);
+    stack.inject_return_value(ret)
+}
+
+fn dispatch_time(kernel: &mut Kernel, stack: &mut UserStack) {
+    let ret = kernel.syscall_time();
+    stack.inject_return_value(ret)
+}
+
+fn dispatch_delay(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let ticks = unsafe { args.extract::<isize>() };
+
+    match kernel.syscall_delay(ticks) {
+        Ok(None) => {} // return value injected once the delay elapses
+        Ok(Some(tick)) => stack.inject_return_value(tick),
+        Err(code) => stack.inject_return_value(code),
+    };
+}
+
+fn dispatch_delay_until(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let tick = unsafe { args.extract::<usize>() };
+
+    match kernel.syscall_delay_until(tick) {
+        Ok(None) => {} // return value injected once the delay elapses
+        Ok(Some(tick)) => stack.inject_return_value(tick),
+        Err(code) => stack.inject_return_value(code),
+    };
+}
+
+fn dispatch_mailbox_create(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let slot_size = unsafe { args.extract::<usize>() };
+    let count = unsafe { args.extract::<usize>() };
+
+    let ret = match kernel.syscall_mailbox_create(slot_size, count) {
+        Ok(id) => id.into() as isize,
+        Err(code) => code as isize,
+    };
+
+    stack.inject_return_value(ret);
+}
+
+fn dispatch_mailbox_post(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let id = unsafe { args.extract::<MailboxId>() };
+    let msg_ptr = unsafe { args.extract::<*mut u8>() };
+    let msg_len = unsafe { args.extract::<usize>() };
+
+    if !check_user_buffer(kernel, msg_ptr, msg_len) {
+        return;
+    }
+
+    let msg = if msg_ptr.is_null() {
+        UserSlice::empty()
+    } else {
+        unsafe { user_slice::from_raw_parts(ptr::NonNull::new_unchecked(msg_ptr), msg_len) }
+    };
+
+    let ret = match kernel.syscall_mailbox_post(id, msg) {
+        Ok(()) => 0,
+        Err(code) => code as isize,
+    };
+
+    stack.inject_return_value(ret);
+}
+
+/// Validate the buffer passed to one of the `MailboxReceive` variants,
+/// returning `None` if the current task was killed for passing an invalid
+/// buffer.
+fn mailbox_receive_buffer(
+    kernel: &mut Kernel,
+    msg_ptr: *mut u8,
+    msg_len: usize,
+) -> Option<UserSliceMut<u8>> {
+    if !check_user_buffer_mut(kernel, msg_ptr, msg_len) {
+        return None;
+    }
+
+    let msg = if msg_ptr.is_null() {
+        UserSliceMut::empty()
+    } else {
+        unsafe { user_slice::from_raw_parts_mut(ptr::NonNull::new_unchecked(msg_ptr), msg_len) }
+    };
+
+    Some(msg)
+}
+
+fn dispatch_mailbox_receive(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let id = unsafe { args.extract::<MailboxId>() };
+    let msg_ptr = unsafe { args.extract::<*mut u8>() };
+    let msg_len = unsafe { args.extract::<usize>() };
+
+    let msg = match mailbox_receive_buffer(kernel, msg_ptr, msg_len) {
+        Some(msg) => msg,
+        None => return,
+    };
+
+    match kernel.syscall_mailbox_receive(id, msg) {
+        Ok(None) => {} // return value injected as part of the `MailboxPost` syscall
+        Ok(Some(msg_len)) => stack.inject_return_value(msg_len),
+        Err(code) => stack.inject_return_value(code),
+    };
+}
+
+fn dispatch_mailbox_try_receive(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let id = unsafe { args.extract::<MailboxId>() };
+    let msg_ptr = unsafe { args.extract::<*mut u8>() };
+    let msg_len = unsafe { args.extract::<usize>() };
+
+    let msg = match mailbox_receive_buffer(kernel, msg_ptr, msg_len) {
+        Some(msg) => msg,
+        None => return,
+    };
+
+    let ret = match kernel.syscall_mailbox_try_receive(id, msg) {
+        Ok(msg_len) => msg_len as isize,
+        Err(code) => code as isize,
+    };
+
+    stack.inject_return_value(ret);
+}
+
+fn dispatch_mailbox_destroy(kernel: &mut Kernel, stack: &mut UserStack) {
+    let mut args = stack.args();
+    let id = unsafe { args.extract::<MailboxId>() };
+
+    let ret = match kernel.syscall_mailbox_destroy(id) {
+        Ok(()) => 0,
+        Err(code) => code as isize,
+    };
+
+    stack.inject_return_value(ret);
+}
+
+fn dispatch_shutdown(kernel: &mut Kernel, _stack: &mut UserStack) {
+    kernel.syscall_shutdown();
+}
+
+fn dispatch_abort(kernel: &mut Kernel, _stack: &mut UserStack) {
+    kernel.syscall_abort();
+}
+
+/// Dispatch a syscall to the appropriate kernel syscall handler.
+///
+/// Called from the architecture-specific syscall entry points (e.g:
+/// `arch::arm::swi_handler::handle_syscall`).
+pub fn dispatch_syscall(kernel: &mut Kernel, syscall_no: SyscallNo, stack: &mut UserStack) {
+    kernel.count_syscall(syscall_no);
+    kernel.trap_frame = Some(ptr::NonNull::from(&mut *stack));
+    if let Some(tid) = kernel.current_tid {
+        super::trace::syscall(tid, syscall_no, stack);
+    }
+
+    match syscall_no {
+        SyscallNo::Yield => dispatch_yield(kernel, stack),
+        SyscallNo::Exit => dispatch_exit(kernel, stack),
+        SyscallNo::MyParentTid => dispatch_my_parent_tid(kernel, stack),
+        SyscallNo::MyTid => dispatch_my_tid(kernel, stack),
+        SyscallNo::Create => dispatch_create(kernel, stack),
+        SyscallNo::Send => dispatch_send(kernel, stack),
+        SyscallNo::Receive => dispatch_receive(kernel, stack),
+        SyscallNo::Reply => dispatch_reply(kernel, stack),
+        SyscallNo::AwaitEvent => dispatch_await_event(kernel, stack),
+        // custom extensions
+        SyscallNo::Perf => dispatch_perf(kernel, stack),
+        SyscallNo::Shutdown => dispatch_shutdown(kernel, stack),
+        SyscallNo::PerfTask => dispatch_perf_task(kernel, stack),
+        SyscallNo::Destroy => dispatch_destroy(kernel, stack),
+        SyscallNo::StackUsage => dispatch_stack_usage(kernel, stack),
+        SyscallNo::Abort => dispatch_abort(kernel, stack),
+        SyscallNo::ReadLog => dispatch_read_log(kernel, stack),
+        SyscallNo::SetLogLevel => dispatch_set_log_level(kernel, stack),
+        SyscallNo::ReadTrace => dispatch_read_trace(kernel, stack),
+        SyscallNo::DetectDeadlocks => dispatch_detect_deadlocks(kernel, stack),
+        SyscallNo::TrySend => dispatch_try_send(kernel, stack),
+        SyscallNo::SendTimeout => dispatch_send_timeout(kernel, stack),
+        SyscallNo::ReceiveTimeout => dispatch_receive_timeout(kernel, stack),
+        SyscallNo::Time => dispatch_time(kernel, stack),
+        SyscallNo::Delay => dispatch_delay(kernel, stack),
+        SyscallNo::DelayUntil => dispatch_delay_until(kernel, stack),
+        SyscallNo::MailboxCreate => dispatch_mailbox_create(kernel, stack),
+        SyscallNo::MailboxPost => dispatch_mailbox_post(kernel, stack),
+        SyscallNo::MailboxReceive => dispatch_mailbox_receive(kernel, stack),
+        SyscallNo::MailboxTryReceive => dispatch_mailbox_try_receive(kernel, stack),
+        SyscallNo::MailboxDestroy => dispatch_mailbox_destroy(kernel, stack),
+    };
+}
@@ -0,0 +1,420 @@
+//! A GDB Remote Serial Protocol stub (using the `gdbstub` crate).
+//!
+//! When built with the `gdb` feature, the kernel waits for GDB to connect
+//! (see `platform::gdb` for which UART is used) before running any tasks, and
+//! then runs under GDB's control. Each task is exposed to GDB as a thread
+//! (whose GDB thread id is its raw [`Tid`] plus one, as GDB thread ids can't be
+//! zero).
+//!
+//! Software breakpoints are implemented by patching user code with an
+//! undefined instruction (see `arch::gdb`), and single-steps are implemented
+//! by placing a temporary breakpoint on whichever instruction the stepped task
+//! will execute next. Any other task which hits the temporary breakpoint is
+//! held (i.e: kept out of the ready queue) until the step completes.
+
+use core::convert::Infallible;
+use core::num::NonZeroUsize;
+use core::ptr;
+
+use gdbstub::target::ext::base::multithread::{
+    GdbInterrupt, MultiThreadOps, ResumeAction, ThreadStopReason,
+};
+use gdbstub::target::ext::base::BaseOps;
+use gdbstub::target::ext::breakpoints::{
+    Breakpoints, BreakpointsOps, SwBreakpoint, SwBreakpointOps,
+};
+use gdbstub::target::{Target, TargetError, TargetResult};
+use gdbstub::{DisconnectReason, GdbStub};
+use gdbstub_arch::arm::reg::ArmCoreRegs;
+use gdbstub_arch::arm::{ArmBreakpointKind, Armv4t};
+use heapless::LinearMap;
+
+use abi::Tid;
+
+use crate::bwkprintln;
+
+use super::arch::{self, UserStack};
+use super::{Kernel, MAX_TASKS};
+
+/// Maximum number of software breakpoints which can be set at once.
+type MaxBreakpoints = heapless::consts::U32;
+
+/// Size of the buffer used to receive packets from GDB.
+const PACKET_BUFFER_SIZE: usize = 4096;
+
+static mut PACKET_BUFFER: [u8; PACKET_BUFFER_SIZE] = [0; PACKET_BUFFER_SIZE];
+
+/// GDB-related kernel state.
+pub(super) struct GdbState {
+    /// Breakpoints set by GDB, mapped to the instructions they replaced.
+    breakpoints: LinearMap<u32, u32, MaxBreakpoints>,
+    /// The task to single-step the next time the kernel is resumed.
+    step_request: Option<Tid>,
+    /// The task being single-stepped, the address of the temporary breakpoint
+    /// on its next instruction, and the instruction it replaced.
+    stepping: Option<(Tid, u32, u32)>,
+    /// A task which hit the temporary single-step breakpoint intended for a
+    /// different task, and has yet to be held.
+    pending_hold: Option<Tid>,
+    /// Tasks which are held until the current single-step completes, indexed
+    /// by slot.
+    held: [Option<Tid>; MAX_TASKS],
+    /// Why the kernel stopped.
+    stop: Option<ThreadStopReason<u32>>,
+}
+
+impl GdbState {
+    pub fn new() -> GdbState {
+        GdbState {
+            breakpoints: LinearMap::new(),
+            step_request: None,
+            stepping: None,
+            pending_hold: None,
+            held: [None; MAX_TASKS],
+            stop: None,
+        }
+    }
+}
+
+fn to_gdb_tid(tid: Tid) -> NonZeroUsize {
+    NonZeroUsize::new(tid.into() + 1).unwrap()
+}
+
+fn from_gdb_tid(tid: NonZeroUsize) -> Tid {
+    Tid::from(tid.get() - 1)
+}
+
+/// Check if the `len` bytes starting at `addr` can be safely accessed by the
+/// kernel (i.e: they're in RAM).
+fn mem_accessible(addr: u32, len: usize) -> bool {
+    match (addr as usize).checked_add(len) {
+        Some(end) => end <= crate::platform::ram_end(),
+        None => false,
+    }
+}
+
+fn read_word(addr: u32) -> u32 {
+    if addr % 4 != 0 || !mem_accessible(addr, 4) {
+        return 0;
+    }
+    unsafe { ptr::read_volatile(addr as *const u32) }
+}
+
+/// Overwrite the instruction at `addr`.
+unsafe fn patch_instruction(addr: u32, instr: u32) {
+    ptr::write_volatile(addr as *mut u32, instr);
+    arch::sync_instruction(addr as usize);
+}
+
+impl Kernel {
+    /// Called when the current task executes an undefined instruction,
+    /// returning `true` if it was one of GDB's breakpoints (in which case the
+    /// task is stopped, instead of being killed).
+    pub(super) fn gdb_breakpoint_hit(&mut self, saved: &UserStack) -> bool {
+        let tid = match self.current_tid {
+            Some(tid) => tid,
+            None => return false,
+        };
+        let pc = saved.pc as usize as u32;
+
+        let (stepped, step_breakpoint) = match self.gdb.stepping {
+            Some((step_tid, addr, _)) if addr == pc => (step_tid == tid, true),
+            _ => (false, false),
+        };
+
+        if stepped {
+            self.gdb.stop = Some(ThreadStopReason::DoneStep);
+        } else if self.gdb.breakpoints.contains_key(&pc) {
+            self.gdb.stop = Some(ThreadStopReason::SwBreak(to_gdb_tid(tid)));
+        } else if step_breakpoint {
+            self.gdb.pending_hold = Some(tid);
+        } else {
+            return false;
+        }
+
+        true
+    }
+
+    /// Place a temporary breakpoint on the next instruction `tid` will
+    /// execute.
+    fn gdb_insert_step_breakpoint(&mut self, tid: Tid) {
+        let task = match self.task(tid) {
+            Some(task) => task,
+            None => return,
+        };
+        let saved = unsafe { task.sp.as_ref() };
+
+        let pc = saved.pc as usize as u32;
+        let instr = match self.gdb.breakpoints.get(&pc) {
+            Some(&instr) => instr,
+            None => read_word(pc),
+        };
+
+        let next = arch::next_pc(saved, instr, read_word);
+        if next % 4 != 0 || !mem_accessible(next, 4) {
+            // the task is about to fault
+            return;
+        }
+
+        let orig = read_word(next);
+        unsafe { patch_instruction(next, arch::BREAKPOINT) };
+        self.gdb.stepping = Some((tid, next, orig));
+    }
+
+    /// Remove the temporary single-step breakpoint (if any), and return any
+    /// held tasks to the ready queue.
+    fn gdb_finish_step(&mut self) {
+        if let Some((_, addr, orig)) = self.gdb.stepping.take() {
+            unsafe { patch_instruction(addr, orig) };
+        }
+
+        for slot in 0..MAX_TASKS {
+            if let Some(tid) = self.gdb.held[slot].take() {
+                if let Some(task) = self.task(tid) {
+                    let priority = task.priority;
+                    self.ready_queue.push_front(tid, priority);
+                }
+            }
+        }
+    }
+
+    /// Remove every breakpoint set by GDB.
+    fn gdb_remove_breakpoints(&mut self) {
+        for (&addr, &orig) in self.gdb.breakpoints.iter() {
+            unsafe { patch_instruction(addr, orig) };
+        }
+        self.gdb.breakpoints.clear();
+    }
+
+    /// Wait for GDB to connect, and then run the kernel under its control
+    /// until every task has exited, or GDB kills the session.
+    ///
+    /// If GDB detaches, the kernel keeps running as usual.
+    pub(super) fn run_under_gdb(&mut self) {
+        bwkprintln!("waiting for GDB to connect...");
+
+        let conn = unsafe { crate::platform::gdb::connection() };
+        let mut gdb = GdbStub::builder(conn)
+            .with_packet_buffer(unsafe { &mut PACKET_BUFFER })
+            .build()
+            .unwrap();
+
+        let reason = gdb.run(&mut GdbTarget(self));
+        self.gdb_remove_breakpoints();
+        self.gdb_finish_step();
+
+        match reason {
+            Ok(DisconnectReason::TargetExited(_)) => {}
+            Ok(DisconnectReason::Kill) => bwkprintln!("killed by GDB"),
+            Ok(reason) => {
+                bwkprintln!("GDB disconnected ({:?}), resuming", reason);
+                while self.tick() {}
+            }
+            Err(e) => {
+                bwkprintln!("GDB session failed ({:?}), resuming", e);
+                while self.tick() {}
+            }
+        }
+    }
+}
+
+/// Exposes the kernel (and its tasks) to `gdbstub`.
+struct GdbTarget<'a>(&'a mut Kernel);
+
+impl Target for GdbTarget<'_> {
+    type Arch = Armv4t;
+    type Error = Infallible;
+
+    fn base_ops(&mut self) -> BaseOps<Armv4t, Infallible> {
+        BaseOps::MultiThread(self)
+    }
+
+    fn breakpoints(&mut self) -> Option<BreakpointsOps<Self>> {
+        Some(self)
+    }
+}
+
+impl MultiThreadOps for GdbTarget<'_> {
+    fn resume(
+        &mut self,
+        _default_resume_action: ResumeAction,
+        gdb_interrupt: GdbInterrupt<'_>,
+    ) -> Result<ThreadStopReason<u32>, Infallible> {
+        let kernel = &mut *self.0;
+        let mut gdb_interrupt = gdb_interrupt.no_async();
+
+        if let Some(tid) = kernel.gdb.step_request {
+            kernel.gdb_insert_step_breakpoint(tid);
+        }
+
+        let reason = loop {
+            if !kernel.tick() {
+                // held tasks are waiting on a single-step which will never
+                // complete, so report them as having hit a breakpoint instead
+                break match kernel.gdb.held.iter().flatten().next() {
+                    Some(&tid) => ThreadStopReason::SwBreak(to_gdb_tid(tid)),
+                    None => ThreadStopReason::Exited(0),
+                };
+            }
+
+            if let Some(tid) = kernel.gdb.pending_hold.take() {
+                let priority = kernel.task(tid).unwrap().priority;
+                kernel.ready_queue.remove(tid, priority);
+                kernel.gdb.held[tid.slot()] = Some(tid);
+            }
+
+            if let Some(reason) = kernel.gdb.stop.take() {
+                break reason;
+            }
+
+            if gdb_interrupt.pending() {
+                break ThreadStopReason::GdbInterrupt;
+            }
+        };
+
+        kernel.gdb_finish_step();
+        Ok(reason)
+    }
+
+    fn clear_resume_actions(&mut self) -> Result<(), Infallible> {
+        self.0.gdb.step_request = None;
+        Ok(())
+    }
+
+    fn set_resume_action(
+        &mut self,
+        tid: NonZeroUsize,
+        action: ResumeAction,
+    ) -> Result<(), Infallible> {
+        // signals aren't supported, and are ignored
+        match action {
+            ResumeAction::Step | ResumeAction::StepWithSignal(_) => {
+                self.0.gdb.step_request = Some(from_gdb_tid(tid))
+            }
+            ResumeAction::Continue | ResumeAction::ContinueWithSignal(_) => {}
+        }
+        Ok(())
+    }
+
+    fn read_registers(
+        &mut self,
+        regs: &mut ArmCoreRegs,
+        tid: NonZeroUsize,
+    ) -> TargetResult<(), Self> {
+        let task = self
+            .0
+            .task(from_gdb_tid(tid))
+            .ok_or(TargetError::NonFatal)?;
+        arch::read_registers(unsafe { task.sp.as_ref() }, regs);
+        Ok(())
+    }
+
+    fn write_registers(&mut self, regs: &ArmCoreRegs, tid: NonZeroUsize) -> TargetResult<(), Self> {
+        let task = self
+            .0
+            .task_mut(from_gdb_tid(tid))
+            .ok_or(TargetError::NonFatal)?;
+        arch::write_registers(unsafe { task.sp.as_mut() }, regs)?;
+        Ok(())
+    }
+
+    fn read_addrs(
+        &mut self,
+        start_addr: u32,
+        data: &mut [u8],
+        _tid: NonZeroUsize,
+    ) -> TargetResult<(), Self> {
+        if !mem_accessible(start_addr, data.len()) {
+            return Err(TargetError::NonFatal);
+        }
+
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = unsafe { ptr::read_volatile((start_addr as usize + i) as *const u8) };
+        }
+        Ok(())
+    }
+
+    fn write_addrs(
+        &mut self,
+        start_addr: u32,
+        data: &[u8],
+        _tid: NonZeroUsize,
+    ) -> TargetResult<(), Self> {
+        if !mem_accessible(start_addr, data.len()) {
+            return Err(TargetError::NonFatal);
+        }
+
+        for (i, &b) in data.iter().enumerate() {
+            let addr = start_addr as usize + i;
+            unsafe {
+                ptr::write_volatile(addr as *mut u8, b);
+                // GDB may be writing code
+                arch::sync_instruction(addr);
+            }
+        }
+        Ok(())
+    }
+
+    fn list_active_threads(
+        &mut self,
+        thread_is_active: &mut dyn FnMut(NonZeroUsize),
+    ) -> Result<(), Infallible> {
+        let kernel = &*self.0;
+        for (slot, task) in kernel.tasks.iter().enumerate() {
+            if task.is_some() {
+                let tid = Tid::new(slot, kernel.generations[slot] as usize);
+                thread_is_active(to_gdb_tid(tid));
+            }
+        }
+        Ok(())
+    }
+
+    fn is_thread_alive(&mut self, tid: NonZeroUsize) -> Result<bool, Infallible> {
+        Ok(self.0.task(from_gdb_tid(tid)).is_some())
+    }
+}
+
+impl Breakpoints for GdbTarget<'_> {
+    fn sw_breakpoint(&mut self) -> Option<SwBreakpointOps<Self>> {
+        Some(self)
+    }
+}
+
+impl SwBreakpoint for GdbTarget<'_> {
+    fn add_sw_breakpoint(
+        &mut self,
+        addr: u32,
+        kind: ArmBreakpointKind,
+    ) -> TargetResult<bool, Self> {
+        // tasks only run ARM code
+        if !matches!(kind, ArmBreakpointKind::Arm32) || addr % 4 != 0 || !mem_accessible(addr, 4) {
+            return Ok(false);
+        }
+
+        let breakpoints = &mut self.0.gdb.breakpoints;
+        if breakpoints.contains_key(&addr) {
+            return Ok(true);
+        }
+
+        let orig = read_word(addr);
+        if breakpoints.insert(addr, orig).is_err() {
+            return Ok(false);
+        }
+        unsafe { patch_instruction(addr, arch::BREAKPOINT) };
+        Ok(true)
+    }
+
+    fn remove_sw_breakpoint(
+        &mut self,
+        addr: u32,
+        _kind: ArmBreakpointKind,
+    ) -> TargetResult<bool, Self> {
+        match self.0.gdb.breakpoints.remove(&addr) {
+            Some(orig) => {
+                unsafe { patch_instruction(addr, orig) };
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! Idle time accounting.
+
+use core::time::Duration;
+
+/// Number of buckets in the sliding window.
+const WINDOW_BUCKETS: usize = 10;
+/// Length of time covered by each bucket in the sliding window.
+const BUCKET_LEN: Duration = Duration::from_millis(100);
+
+/// Tracks how much time the kernel spends idle, both since boot, and over a
+/// sliding window of the last ~[`WINDOW_BUCKETS`] * [`BUCKET_LEN`].
+///
+/// All timestamps are time since boot (i.e: `platform::time::uptime()`).
+#[derive(Debug)]
+pub struct IdleTracker {
+    /// Total time spent idle since boot.
+    total_idle: Duration,
+    /// Time spent idle during each bucket of the sliding window.
+    buckets: [Duration; WINDOW_BUCKETS],
+    /// Index of the current bucket in `buckets`.
+    current: usize,
+    /// Start time of the current bucket.
+    bucket_start: Duration,
+}
+
+/// Compute `part / whole` as a percentage (clamped to 0 - 100).
+fn pct(part: Duration, whole: Duration) -> u32 {
+    if whole.as_nanos() == 0 {
+        return 0;
+    }
+    core::cmp::min(100, part.as_nanos() * 100 / whole.as_nanos()) as u32
+}
+
+impl IdleTracker {
+    /// Create a new IdleTracker.
+    pub fn new() -> IdleTracker {
+        IdleTracker {
+            total_idle: Duration::new(0, 0),
+            buckets: [Duration::new(0, 0); WINDOW_BUCKETS],
+            current: 0,
+            bucket_start: Duration::new(0, 0),
+        }
+    }
+
+    /// Advance the sliding window up to `now`, clearing any buckets which
+    /// have fallen out of it.
+    fn advance(&mut self, now: Duration) {
+        // skip ahead if the entire window has gone by
+        if now >= self.bucket_start + BUCKET_LEN * WINDOW_BUCKETS as u32 {
+            let elapsed_buckets = (now - self.bucket_start).as_nanos() / BUCKET_LEN.as_nanos();
+            self.bucket_start += BUCKET_LEN * (elapsed_buckets - WINDOW_BUCKETS as u128) as u32;
+            self.buckets = [Duration::new(0, 0); WINDOW_BUCKETS];
+        }
+
+        while now >= self.bucket_start + BUCKET_LEN {
+            self.bucket_start += BUCKET_LEN;
+            self.current = (self.current + 1) % WINDOW_BUCKETS;
+            self.buckets[self.current] = Duration::new(0, 0);
+        }
+    }
+
+    /// Record that the kernel was idle between `start` and `end`.
+    pub fn record_idle(&mut self, start: Duration, end: Duration) {
+        self.total_idle += end - start;
+
+        // split the idle period across any buckets it spans
+        let mut t = start;
+        while t < end {
+            self.advance(t);
+            let segment_end = core::cmp::min(end, self.bucket_start + BUCKET_LEN);
+            // idle periods which started before the window are clamped
+            let segment_start = core::cmp::max(t, self.bucket_start);
+            self.buckets[self.current] += segment_end - segment_start;
+            t = segment_end;
+        }
+    }
+
+    /// Percentage of time (0 - 100) spent idle since boot.
+    pub fn since_boot_pct(&self, now: Duration) -> u32 {
+        pct(self.total_idle, now)
+    }
+
+    /// Percentage of time (0 - 100) spent idle over the sliding window.
+    pub fn window_pct(&mut self, now: Duration) -> u32 {
+        self.advance(now);
+
+        let idle = self.buckets.iter().sum();
+        // the current bucket is only partially complete
+        let window = BUCKET_LEN * (WINDOW_BUCKETS as u32 - 1) + (now - self.bucket_start);
+        // ...and the window can't extend past boot
+        pct(idle, core::cmp::min(window, now))
+    }
+}
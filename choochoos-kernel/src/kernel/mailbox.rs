@@ -0,0 +1,281 @@
+//! Kernel-backed bounded mailboxes, for asynchronous message passing.
+//!
+//! Each mailbox holds up to `count` messages of up to `slot_size` bytes, which
+//! are copied into (and out of) storage carved from a fixed-size kernel pool.
+//! Posting a message never blocks, and a mailbox's storage is returned to the
+//! pool when it's destroyed, either explicitly, or when its owner exits.
+//!
+//! Tasks blocked in `MailboxReceive` are queued on the mailbox (in the order
+//! they started waiting), and are handed posted messages directly.
+
+use core::mem;
+use core::ptr;
+
+use abi::{MailboxId, Tid};
+
+use crate::util::user_slice::{self, UserSlice, UserSliceMut};
+
+use super::task::TaskState;
+use super::{Kernel, MAILBOX_POOL_SIZE};
+
+/// Each slot starts with the length of the message it contains.
+const SLOT_HEADER: usize = mem::size_of::<usize>();
+
+#[repr(align(8))]
+struct MailboxPool([u8; MAILBOX_POOL_SIZE]);
+
+/// Backing storage for every mailbox's slots.
+static mut MAILBOX_POOL: MailboxPool = MailboxPool([0; MAILBOX_POOL_SIZE]);
+
+/// Returns a pointer to the byte at `offset` in the mailbox pool.
+fn pool_ptr(offset: usize) -> ptr::NonNull<u8> {
+    debug_assert!(offset < MAILBOX_POOL_SIZE);
+    unsafe { ptr::NonNull::new_unchecked(MAILBOX_POOL.0.as_mut_ptr().add(offset)) }
+}
+
+/// A bounded FIFO queue of messages.
+#[derive(Debug)]
+pub struct Mailbox {
+    /// The task which created the mailbox.
+    pub owner: Tid,
+    /// Offset of the mailbox's first slot in the pool.
+    base: usize,
+    /// Maximum length of a message.
+    pub slot_size: usize,
+    /// Maximum number of queued messages.
+    count: usize,
+    /// Index of the slot containing the oldest message.
+    head: usize,
+    /// Number of queued messages.
+    len: usize,
+    /// The first task waiting to receive a message.
+    pub waiters_head: Option<Tid>,
+    /// The last task waiting to receive a message.
+    pub waiters_tail: Option<Tid>,
+}
+
+impl Mailbox {
+    /// Returns the distance between consecutive slots (keeping each slot's
+    /// header aligned).
+    fn stride(slot_size: usize) -> Option<usize> {
+        let align = mem::align_of::<usize>();
+        let padded = slot_size.checked_add(align - 1)? & !(align - 1);
+        padded.checked_add(SLOT_HEADER)
+    }
+
+    /// Returns the number of bytes of storage required by a mailbox, or `None`
+    /// if it would overflow.
+    pub fn storage_size(slot_size: usize, count: usize) -> Option<usize> {
+        Mailbox::stride(slot_size)?.checked_mul(count)
+    }
+
+    /// Create a new, empty Mailbox, using the `storage_size` bytes of the pool
+    /// starting at `base`.
+    pub fn new(owner: Tid, base: usize, slot_size: usize, count: usize) -> Mailbox {
+        Mailbox {
+            owner,
+            base,
+            slot_size,
+            count,
+            head: 0,
+            len: 0,
+            waiters_head: None,
+            waiters_tail: None,
+        }
+    }
+
+    /// Returns the range of pool offsets used by the mailbox.
+    fn storage(&self) -> core::ops::Range<usize> {
+        // can't overflow, as it was checked when the mailbox was created
+        self.base..self.base + Mailbox::storage_size(self.slot_size, self.count).unwrap()
+    }
+
+    fn slot_offset(&self, idx: usize) -> usize {
+        self.base + (idx % self.count) * Mailbox::stride(self.slot_size).unwrap()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == self.count
+    }
+
+    /// Copy `msg` into the next free slot.
+    ///
+    /// The mailbox must not be full, and the message must fit in a slot.
+    pub fn push(&mut self, msg: UserSlice<u8>) {
+        assert!(!self.is_full() && msg.len() <= self.slot_size);
+
+        let offset = self.slot_offset(self.head + self.len);
+        unsafe {
+            ptr::write(pool_ptr(offset).cast::<usize>().as_ptr(), msg.len());
+            user_slice::from_raw_parts_mut(pool_ptr(offset + SLOT_HEADER), msg.len())
+                .copy_from_slice_min(msg);
+        }
+        self.len += 1;
+    }
+
+    /// Copy the oldest message into `dst` (truncating it if necessary), and
+    /// remove it from the mailbox. Returns the message's original length.
+    ///
+    /// The mailbox must not be empty.
+    pub fn pop(&mut self, mut dst: UserSliceMut<u8>) -> usize {
+        assert!(!self.is_empty());
+
+        let offset = self.slot_offset(self.head);
+        let msg_len = unsafe {
+            let msg_len = ptr::read(pool_ptr(offset).cast::<usize>().as_ptr());
+            dst.copy_from_slice_min(user_slice::from_raw_parts(
+                pool_ptr(offset + SLOT_HEADER),
+                msg_len,
+            ));
+            msg_len
+        };
+        self.head = (self.head + 1) % self.count;
+        self.len -= 1;
+        msg_len
+    }
+}
+
+impl Kernel {
+    /// Return a mutable reference to the mailbox with the given `id` (if it
+    /// exists).
+    pub(super) fn mailbox_mut(&mut self, id: MailboxId) -> Option<&mut Mailbox> {
+        if id.slot() >= self.mailboxes.len()
+            || id.generation() != self.mailbox_generations[id.slot()] as usize
+        {
+            return None;
+        }
+        self.mailboxes[id.slot()].as_mut()
+    }
+
+    /// Find `size` bytes of free space in the mailbox pool (using a first-fit
+    /// strategy), returning the offset of the free space.
+    pub(super) fn alloc_mailbox_storage(&self, size: usize) -> Option<usize> {
+        let overlaps = |base: usize| {
+            let end = base + size;
+            self.mailboxes
+                .iter()
+                .flatten()
+                .map(Mailbox::storage)
+                .any(|used| base < used.end && used.start < end)
+        };
+
+        // free space always starts at the beginning of the pool, or right
+        // after an existing mailbox's storage
+        core::iter::once(0)
+            .chain(self.mailboxes.iter().flatten().map(|m| m.storage().end))
+            .filter(|&base| size <= MAILBOX_POOL_SIZE - base && !overlaps(base))
+            .min()
+    }
+
+    /// Destroy a mailbox, failing any tasks waiting on it, and freeing its slot
+    /// (and storage) for reuse. `id` must refer to an existing mailbox.
+    pub(super) fn destroy_mailbox(&mut self, id: MailboxId) {
+        use abi::syscall::error::MailboxReceive as ReceiveError;
+
+        let mailbox = self.mailboxes[id.slot()].take().unwrap();
+        log::trace!("destroying {:?} (owned by {:?})", id, mailbox.owner);
+
+        let mut next_waiter = mailbox.waiters_head;
+        while let Some(tid) = next_waiter {
+            let waiter = self.tasks[tid.slot()].as_mut().unwrap();
+            next_waiter = match waiter.state {
+                TaskState::MailboxWait { next, .. } => next,
+                _ => panic!("mailbox waiter was not in MailboxWait state"),
+            };
+            waiter.inject_return_value(ReceiveError::MailboxDestroyed);
+            waiter.set_state(TaskState::Ready);
+            let priority = waiter.priority;
+            self.ready_queue.push_back(tid, priority);
+        }
+
+        let generation = &mut self.mailbox_generations[id.slot()];
+        *generation = if *generation as usize == MailboxId::MAX_GENERATION {
+            0
+        } else {
+            *generation + 1
+        };
+    }
+
+    /// Destroy every mailbox owned by `owner`.
+    pub(super) fn destroy_owned_mailboxes(&mut self, owner: Tid) {
+        for slot in 0..self.mailboxes.len() {
+            match &self.mailboxes[slot] {
+                Some(mailbox) if mailbox.owner == owner => {
+                    let id = MailboxId::new(slot, self.mailbox_generations[slot] as usize);
+                    self.destroy_mailbox(id);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Append `tid` (which is in the `MailboxWait` state) to the mailbox's
+    /// queue of waiting tasks.
+    pub(super) fn enqueue_mailbox_waiter(&mut self, id: MailboxId, tid: Tid) {
+        let mailbox = self.mailboxes[id.slot()].as_mut().unwrap();
+        let prev_tail = mailbox.waiters_tail.replace(tid);
+        match prev_tail {
+            None => mailbox.waiters_head = Some(tid),
+            Some(prev_tail) => match self.tasks[prev_tail.slot()].as_mut().unwrap().state {
+                TaskState::MailboxWait { ref mut next, .. } => *next = Some(tid),
+                _ => panic!("mailbox waiter was not in MailboxWait state"),
+            },
+        }
+    }
+
+    /// Remove the first task from the mailbox's queue of waiting tasks.
+    pub(super) fn dequeue_mailbox_waiter(&mut self, id: MailboxId) -> Option<Tid> {
+        let tid = self.mailboxes[id.slot()].as_ref().unwrap().waiters_head?;
+        let next = match self.tasks[tid.slot()].as_mut().unwrap().state {
+            TaskState::MailboxWait { ref mut next, .. } => next.take(),
+            _ => panic!("mailbox waiter was not in MailboxWait state"),
+        };
+
+        let mailbox = self.mailboxes[id.slot()].as_mut().unwrap();
+        mailbox.waiters_head = next;
+        if next.is_none() {
+            mailbox.waiters_tail = None;
+        }
+        Some(tid)
+    }
+
+    /// Remove `tid` from the queue of tasks waiting on a mailbox.
+    pub(super) fn unlink_mailbox_waiter(&mut self, id: MailboxId, tid: Tid) {
+        macro_rules! next_of {
+            ($tid:expr) => {
+                match self.tasks[$tid.slot()].as_mut().unwrap().state {
+                    TaskState::MailboxWait { ref mut next, .. } => next,
+                    _ => panic!("mailbox waiter was not in MailboxWait state"),
+                }
+            };
+        }
+
+        let tid_next = next_of!(tid).take();
+
+        // find the task before `tid` in the queue
+        let mut prev: Option<Tid> = None;
+        let mut cur = self.mailboxes[id.slot()].as_ref().unwrap().waiters_head;
+        while let Some(c) = cur {
+            if c == tid {
+                break;
+            }
+            prev = cur;
+            cur = *next_of!(c);
+        }
+        assert!(cur.is_some(), "task was not in the mailbox's wait queue");
+
+        match prev {
+            Some(prev) => *next_of!(prev) = tid_next,
+            None => self.mailboxes[id.slot()].as_mut().unwrap().waiters_head = tid_next,
+        }
+
+        let mailbox = self.mailboxes[id.slot()].as_mut().unwrap();
+        if mailbox.waiters_tail == Some(tid) {
+            mailbox.waiters_tail = prev;
+        }
+    }
+}
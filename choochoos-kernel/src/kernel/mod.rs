@@ -1,86 +1,235 @@
-use heapless::binary_heap::{BinaryHeap, Max};
-use heapless::consts::*;
-use heapless::LinearMap;
+use core::ptr;
+use core::time::Duration;
 
-use abi::Tid;
+use heapless::{LinearMap, Vec};
+
+use abi::{syscall::SyscallNo, Tid};
 
 mod arch;
+mod crash_dump;
+mod deadlock;
+mod dispatch;
+#[cfg(feature = "gdb")]
+mod gdb;
+mod idle;
+mod mailbox;
+#[cfg(feature = "monitor")]
+pub mod monitor;
+mod ready_queue;
+mod reap;
+mod stack;
 mod syscalls;
+#[cfg(test)]
+mod tests;
+mod timeout;
+mod timer_wheel;
+mod trace;
+mod volatile_data;
 
 pub mod task;
 
+pub(crate) use arch::UserStack;
+use idle::IdleTracker;
+use mailbox::Mailbox;
+use ready_queue::ReadyQueue;
 use task::{TaskDescriptor, TaskState};
+use timer_wheel::TimerWheel;
+use volatile_data::VolatileDataQueue;
 
-/// A pair of `Tid` and it's `priority`.
-///
-/// Implements [`Ord`] using the `priority` field.
-#[derive(Debug, Eq, PartialEq)]
-struct ReadyQueueItem {
-    pub priority: isize,
-    pub tid: Tid,
+/// Either the Tids of Tasks waiting for an Event (in the order they started
+/// waiting), or VolatileData from interrupts that no Task was waiting for.
+#[derive(Debug)]
+enum EventQueueItem {
+    BlockedTids(Vec<Tid, MAX_EVENT_WAITERS>),
+    VolatileData(VolatileDataQueue),
 }
 
-impl PartialOrd for ReadyQueueItem {
-    fn partial_cmp(&self, rhs: &Self) -> Option<core::cmp::Ordering> {
-        Some(self.cmp(rhs))
-    }
+/// Determines which of the tasks waiting on an event are unblocked when the
+/// event occurs. Selected on a per-event basis by the platform (see
+/// `platform::interrupts::wake_policy`).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum WakePolicy {
+    /// Unblock all waiting tasks, each of which receives the same volatile
+    /// data.
+    #[cfg_attr(feature = "platform-host", allow(dead_code))]
+    WakeAll,
+    /// Only unblock the highest priority waiting task, with ties broken in
+    /// favor of whichever task has been waiting the longest. Any other tasks
+    /// remain blocked until subsequent occurrences of the event.
+    WakeHighestPriority,
 }
 
-impl Ord for ReadyQueueItem {
-    fn cmp(&self, rhs: &Self) -> core::cmp::Ordering {
-        self.priority.cmp(&rhs.priority)
-    }
+/// Kernel limits, as configured by the distro at build time.
+mod config {
+    // oh const generics, please land soon
+    include!(concat!(env!("OUT_DIR"), "/config.rs"));
 }
 
-/// Either the Tid of a Task waiting for an Event, or some VolatileData from an
-/// interrupt that no Task was waiting for.
-#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
-enum EventQueueItem {
-    BlockedTid(Tid),
-    VolatileData(usize),
+#[cfg(feature = "trace")]
+pub(crate) use config::TRACE_RECORDS;
+pub(crate) use config::{
+    KERNEL_LOG_SIZE, LOG_LEVEL, LOG_MODULE_LEVELS, MAILBOX_POOL_SIZE, MAX_EVENTS,
+    MAX_EVENT_WAITERS, MAX_LOG_FILTERS, MAX_LOG_MODULE_LEN, MAX_MAILBOXES, MAX_PENDING_EVENT_DATA,
+    MAX_TASKS,
+};
+#[cfg(not(feature = "platform-host"))]
+pub(crate) use config::{STACK_PAINT_SIZE, USER_STACK_SIZE};
+
+#[cfg_attr(test, allow(unused_imports))]
+pub use crash_dump::crash_dump;
+#[cfg(feature = "kernel-clock")]
+pub use timeout::TICK;
+
+/// Abstraction over "run this task until it traps back into the kernel".
+///
+/// Decoupling the kernel's scheduling / SRR state machine from the
+/// architecture-specific context switch makes it possible to drive the kernel
+/// without real tasks, e.g: by scripting syscalls on behalf of fake tasks.
+pub trait Activate {
+    /// Run the task `tid` (with saved stack pointer `sp`) until it traps back
+    /// into the kernel, returning the task's new stack pointer.
+    ///
+    /// Any syscalls made by the task must be handled (via the corresponding
+    /// `Kernel::syscall_*` method) before returning. `kernel.current_tid` is
+    /// set to `tid` for the duration of the call.
+    fn activate(
+        &mut self,
+        kernel: &mut Kernel,
+        tid: Tid,
+        sp: ptr::NonNull<UserStack>,
+    ) -> ptr::NonNull<UserStack>;
 }
 
-// oh const generics, please land soon
-#[allow(non_camel_case_types)]
-type MAX_TASKS = U16;
-const MAX_TASKS: usize = 16;
-#[allow(non_camel_case_types)]
-type MAX_EVENTS = U16;
+/// Activates tasks using the architecture's context switch routines.
+///
+/// Syscalls and interrupts are dispatched to the global kernel instance by the
+/// architecture's exception handlers.
+pub struct ArchActivate;
+
+impl Activate for ArchActivate {
+    fn activate(
+        &mut self,
+        _kernel: &mut Kernel,
+        _tid: Tid,
+        sp: ptr::NonNull<UserStack>,
+    ) -> ptr::NonNull<UserStack> {
+        #[cfg(feature = "mmu")]
+        unsafe {
+            arch::activate_task_memory(_tid.slot())
+        };
+
+        unsafe { arch::_activate_task(sp) }
+    }
+}
+
+/// The outcome of a single [`Kernel::step`].
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Step {
+    /// The task was activated, and has since trapped back into the kernel.
+    Activated(Tid),
+    /// There are no ready tasks, but some tasks are waiting for events.
+    Idle,
+    /// There are no ready tasks, and no tasks waiting for events.
+    Done,
+}
 
 /// The core choochoos kernel!
 pub struct Kernel {
-    /// Fixed-size array of TaskDescriptor.
-    ///
-    /// At the moment, [`Tid`]s are direct indexes into this array.
+    /// Fixed-size array of TaskDescriptor, indexed by [`Tid::slot`].
     tasks: [Option<TaskDescriptor>; MAX_TASKS],
+    /// The current generation of each slot in `tasks`.
+    generations: [u16; MAX_TASKS],
+    /// Number of interrupts whose volatile data was dropped (due to their
+    /// event's volatile data queue being full).
+    dropped_events: usize,
+    /// Idle time accounting.
+    idle: IdleTracker,
+    /// Total time spent handling interrupts.
+    irq_time: Duration,
     /// The currently running Tid.
     current_tid: Option<Tid>,
+    /// The current task's saved state, while it is trapped in the kernel via a
+    /// syscall (or fault). Used for crash dumps.
+    trap_frame: Option<ptr::NonNull<UserStack>>,
     /// Priority queue of tasks ready to be scheduled.
-    ready_queue: BinaryHeap<ReadyQueueItem, MAX_TASKS, Max>, // matches number of tasks
+    ready_queue: ReadyQueue,
     /// A map of `event_id`s to either a blocked task, or some unclaimed
     /// volatile data.
     event_queue: LinearMap<usize, EventQueueItem, MAX_EVENTS>,
+    /// Pending syscall timeouts and delays.
+    timers: TimerWheel,
+    /// Fixed-size array of Mailboxes, indexed by [`MailboxId::slot`].
+    ///
+    /// [`MailboxId::slot`]: abi::MailboxId::slot
+    mailboxes: [Option<Mailbox>; MAX_MAILBOXES],
+    /// The current generation of each slot in `mailboxes`.
+    mailbox_generations: [u16; MAX_MAILBOXES],
+    /// Tasks which were part of a deadlocked cycle the last time deadlocks
+    /// were checked for, indexed by slot.
+    deadlocked: [Option<Tid>; MAX_TASKS],
+    /// Breakpoints, single-stepping, and stop state for the GDB stub.
+    #[cfg(feature = "gdb")]
+    gdb: gdb::GdbState,
+    /// Set when the currently running task is interrupted by an IRQ (as
+    /// opposed to trapping into the kernel via a syscall).
+    interrupted: bool,
+    /// Set when the currently running task's scheduling quantum expires.
+    #[cfg(feature = "preemption")]
+    quantum_expired: bool,
+    /// The most recently activated task.
+    #[cfg(feature = "preemption")]
+    last_activated: Option<Tid>,
 }
 
 impl Kernel {
+    // `Default` is only implemented for arrays of up to 32 elements
+    const NO_TASK: Option<TaskDescriptor> = None;
+    const NO_MAILBOX: Option<Mailbox> = None;
+
+    /// Create a fresh kernel instance, without any tasks.
+    ///
+    /// This does _not_ perform any architecture / platform initialization, and
+    /// is therefore safe to use off-target (e.g: when driving the kernel via a
+    /// custom [`Activate`] implementation). Use [`Kernel::init`] to set up the
+    /// global kernel context instead.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Kernel {
+        Kernel {
+            tasks: [Kernel::NO_TASK; MAX_TASKS],
+            generations: [0; MAX_TASKS],
+            dropped_events: 0,
+            idle: IdleTracker::new(),
+            irq_time: Duration::new(0, 0),
+            current_tid: None,
+            trap_frame: None,
+            ready_queue: ReadyQueue::new(),
+            event_queue: LinearMap::new(),
+            timers: TimerWheel::new(),
+            mailboxes: [Kernel::NO_MAILBOX; MAX_MAILBOXES],
+            mailbox_generations: [0; MAX_MAILBOXES],
+            deadlocked: [None; MAX_TASKS],
+            #[cfg(feature = "gdb")]
+            gdb: gdb::GdbState::new(),
+            interrupted: false,
+            #[cfg(feature = "preemption")]
+            quantum_expired: false,
+            #[cfg(feature = "preemption")]
+            last_activated: None,
+        }
+    }
+
     /// Set up the global kernel context.
     pub unsafe fn init() -> &'static mut Kernel {
         if let Some(ref mut kernel) = &mut crate::KERNEL {
             return kernel;
         }
 
-        // Initialize the kernel's static state.
-        let kernel = Kernel {
-            tasks: Default::default(),
-            current_tid: None,
-            ready_queue: BinaryHeap::new(),
-            event_queue: LinearMap::new(),
-        };
-
         // Set the global kernel context.
-        crate::KERNEL = Some(kernel);
+        crate::KERNEL = Some(Kernel::new());
         let kernel = crate::KERNEL.as_mut().unwrap();
 
+        crate::kernel_log::init();
+
         // perform any architecture specific initialization
         // (e.g: registering interrupt/exception handlers)
         arch::init();
@@ -109,96 +258,308 @@ impl Kernel {
         assert_eq!(ns_tid, abi::NAMESERVER_TID);
 
         // enter the main kernel loop
-        loop {
-            // determine which tid to schedule next
-            let tid = match self.ready_queue.pop() {
-                Some(item) => item.tid,
-                None => {
-                    if self.event_queue.is_empty() {
-                        break;
-                    }
+        #[cfg(not(feature = "gdb"))]
+        while self.tick() {}
+        #[cfg(feature = "gdb")]
+        self.run_under_gdb();
 
-                    // TODO: track idle time
-                    let _time_asleep = unsafe { crate::platform::idle_task() };
-                    continue;
-                }
-            };
-
-            // activate the task
-            self.current_tid = Some(tid);
-            let sp = self.tasks[tid.into()].as_mut().unwrap().sp;
-            let next_sp = unsafe { arch::_activate_task(sp) };
-            self.current_tid = None;
-
-            // there's a chance that the task was exited / destroyed
-            if let Some(ref mut task) = self.tasks[tid.into()] {
-                task.sp = next_sp;
-
-                if matches!(task.state, TaskState::Ready) {
-                    self.ready_queue
-                        .push(ReadyQueueItem {
-                            priority: task.priority,
-                            tid,
-                        })
-                        .expect("out of space on the ready queue");
+        // print anything userspace didn't get around to reading
+        crate::kernel_log::flush();
+        trace::dump();
+
+        unsafe { crate::platform::teardown() };
+    }
+
+    /// Run a single iteration of the main kernel loop (i.e: run a task, or idle
+    /// until an interrupt occurs), returning `false` once there's nothing left
+    /// to run.
+    fn tick(&mut self) -> bool {
+        match self.step(&mut ArchActivate) {
+            Step::Activated(_) => {}
+            Step::Idle => {
+                let start = crate::platform::time::uptime();
+                let time_asleep = unsafe { crate::platform::idle_task() };
+                self.idle.record_idle(start, start + time_asleep);
+            }
+            Step::Done => return false,
+        }
+        true
+    }
+
+    /// Schedule and activate the next ready task (if any).
+    pub fn step(&mut self, activator: &mut impl Activate) -> Step {
+        // determine which tid to schedule next
+        let tid = match self.ready_queue.pop() {
+            Some(tid) => tid,
+            None => {
+                self.check_for_deadlocks();
+
+                return if self.event_queue.is_empty() && self.timers.is_empty() {
+                    Step::Done
+                } else {
+                    Step::Idle
+                };
+            }
+        };
+
+        // each newly scheduled task gets a fresh scheduling quantum
+        #[cfg(feature = "preemption")]
+        {
+            if self.last_activated != Some(tid) {
+                unsafe { crate::platform::preemption::reset_quantum() };
+                self.last_activated = Some(tid);
+            }
+        }
+
+        // activate the task
+        self.current_tid = Some(tid);
+        let sp = self.tasks[tid.slot()].as_mut().unwrap().sp;
+        trace::switch_in(tid, unsafe { sp.as_ref() });
+        let irq_time_before = self.irq_time;
+        let start = crate::platform::time::uptime();
+        let next_sp = activator.activate(self, tid, sp);
+        let end = crate::platform::time::uptime();
+        self.current_tid = None;
+        self.trap_frame = None;
+
+        // don't charge the task for any interrupts that occurred while it ran
+        let cpu_time = (end - start).saturating_sub(self.irq_time - irq_time_before);
+
+        let interrupted = core::mem::replace(&mut self.interrupted, false);
+        #[cfg(feature = "preemption")]
+        let quantum_expired = core::mem::replace(&mut self.quantum_expired, false);
+        #[cfg(not(feature = "preemption"))]
+        let quantum_expired = false;
+
+        // a task which overflowed its stack may have corrupted its neighbour's
+        // stack, and can't be trusted to keep running.
+        #[cfg(not(feature = "platform-host"))]
+        {
+            if self.tasks[tid.slot()].is_some() && !stack::canary_intact(tid.slot()) {
+                kprintln!(
+                    "{:?} overflowed its stack (base={:#x}, high-water mark={:#x} bytes), killing it",
+                    tid,
+                    stack::base(tid.slot()),
+                    stack::high_water_mark(tid.slot())
+                );
+                self.reap(tid);
+            }
+        }
+
+        trace::switch_out(tid, self.task(tid).map(|task| &task.state));
+
+        // there's a chance that the task was exited / destroyed
+        if let Some(ref mut task) = self.tasks[tid.slot()] {
+            task.sp = next_sp;
+            task.perf.cpu_time += cpu_time;
+            task.perf.context_switches += 1;
+
+            if matches!(task.state, TaskState::Ready) {
+                if interrupted && !quantum_expired {
+                    // the task was interrupted part-way through it's quantum,
+                    // and should resume before any other equal-priority tasks.
+                    self.ready_queue.push_front(tid, task.priority);
+                } else {
+                    self.ready_queue.push_back(tid, task.priority);
                 }
             }
         }
 
-        unsafe { crate::platform::teardown() };
+        Step::Activated(tid)
+    }
+
+    /// Count a syscall made by the current task.
+    fn count_syscall(&mut self, syscall_no: SyscallNo) {
+        if let Some(tid) = self.current_tid {
+            if let Some(task) = self.tasks[tid.slot()].as_mut() {
+                task.perf.syscalls[syscall_no as usize] += 1;
+            }
+        }
+    }
+
+    /// Check if `tid` refers to a live task, i.e: it's in range, and its
+    /// generation matches the current generation of its slot.
+    fn tid_is_live(&self, tid: Tid) -> bool {
+        match self.generations.get(tid.slot()) {
+            Some(&generation) => generation as usize == tid.generation(),
+            None => false,
+        }
     }
 
-    /// Utility method to retrieve the current_tid. Only used when the `kdebug`
-    /// feature is active.
+    /// Return a reference to the task with the given `tid` (if it exists).
+    pub fn task(&self, tid: Tid) -> Option<&TaskDescriptor> {
+        if !self.tid_is_live(tid) {
+            return None;
+        }
+        self.tasks[tid.slot()].as_ref()
+    }
+
+    /// Return a mutable reference to the task with the given `tid` (if it
+    /// exists).
+    fn task_mut(&mut self, tid: Tid) -> Option<&mut TaskDescriptor> {
+        if !self.tid_is_live(tid) {
+            return None;
+        }
+        self.tasks[tid.slot()].as_mut()
+    }
+
+    /// Free the slot used by `tid`, bumping its generation so that any
+    /// lingering copies of `tid` become stale.
+    fn free_slot(&mut self, tid: Tid) {
+        let task = self.tasks[tid.slot()].take().unwrap();
+        // SAFETY: the task is never activated again
+        unsafe { arch::free_stack(task.sp) };
+
+        let generation = &mut self.generations[tid.slot()];
+        *generation = if *generation as usize == Tid::MAX_GENERATION {
+            0
+        } else {
+            *generation + 1
+        };
+    }
+
+    /// Utility method to retrieve the current_tid. Used by the kernel logger.
     #[doc(hidden)]
-    #[allow(dead_code)]
-    #[cfg(feature = "kdebug")]
     pub(crate) fn current_tid(&self) -> Option<Tid> {
         self.current_tid
     }
 
+    /// Handle any pending interrupts, dispatching them via
+    /// [`Kernel::handle_event`].
     pub unsafe fn handle_irq(&mut self) {
+        // IRQs that occur while the kernel is idle don't interrupt any task
+        if self.current_tid.is_some() {
+            self.interrupted = true;
+        }
+
+        let start = crate::platform::time::uptime();
         crate::platform::interrupts::handle_irq(|event_id: usize, volatile_data: usize| {
-            match self.event_queue.remove(&event_id) {
-                None => {
-                    kdebug!(
-                        "no tasks are waiting for event_id {}, storing data {:#x?}",
-                        event_id,
-                        volatile_data
-                    );
-                    self.event_queue
-                        .insert(event_id, EventQueueItem::VolatileData(volatile_data))
-                        .expect("no more room in event_queue");
+            trace::irq(self.current_tid, event_id, volatile_data);
+            self.handle_event(event_id, volatile_data)
+        });
+
+        // time spent in the monitor isn't charged to the interrupted task
+        #[cfg(feature = "monitor")]
+        {
+            if crate::platform::interrupts::take_break_received()
+                && self.monitor(monitor::Entry::Break) == monitor::Exit::RedBoot
+            {
+                crate::kernel_log::flush();
+                crate::platform::teardown();
+                crate::platform::exit_to_redboot();
+            }
+        }
+
+        self.irq_time += crate::platform::time::uptime() - start;
+    }
+
+    /// Unblock the task waiting for `event_id` (if any), or store the
+    /// `volatile_data` for a future `AwaitEvent` call.
+    pub fn handle_event(&mut self, event_id: usize, volatile_data: usize) {
+        // scheduling ticks are handled by the kernel itself
+        #[cfg(feature = "preemption")]
+        {
+            if crate::platform::preemption::is_tick(event_id) {
+                if self.current_tid.is_some() {
+                    self.quantum_expired = true;
                 }
-                Some(EventQueueItem::BlockedTid(tid)) => {
-                    let blocked_task = match self.tasks[tid.into()] {
-                        Some(ref mut task) => task,
-                        None => {
-                            kdebug!(
-                                "{:?} was terminated while waiting for event_id {}",
-                                tid,
-                                event_id
-                            );
-                            return;
+                return;
+            }
+        }
+
+        // ...as are kernel clock ticks
+        #[cfg(feature = "kernel-clock")]
+        {
+            if crate::platform::clock::is_tick(event_id) {
+                self.expire_timeouts();
+                return;
+            }
+        }
+
+        log::trace!("event_id {} occurred, data {:#x?}", event_id, volatile_data);
+
+        match self.event_queue.remove(&event_id) {
+            None => {
+                log::debug!(
+                    "no tasks are waiting for event_id {}, storing data {:#x?}",
+                    event_id,
+                    volatile_data
+                );
+                let mut pending = VolatileDataQueue::new();
+                pending.push(volatile_data);
+                self.event_queue
+                    .insert(event_id, EventQueueItem::VolatileData(pending))
+                    .expect("no more room in event_queue");
+            }
+            Some(EventQueueItem::BlockedTids(mut tids)) => {
+                match crate::platform::interrupts::wake_policy(event_id) {
+                    WakePolicy::WakeAll => {
+                        for tid in tids {
+                            self.wake_event_waiter(tid, event_id, volatile_data)
+                        }
+                    }
+                    WakePolicy::WakeHighestPriority => {
+                        let priority = |tid: Tid| self.task(tid).map(|t| t.priority);
+
+                        // strict comparison favors the longest-waiting task
+                        let mut best = 0;
+                        for (i, &tid) in tids.iter().enumerate() {
+                            if priority(tid) > priority(tids[best]) {
+                                best = i;
+                            }
+                        }
+
+                        // remove the task while preserving the order of the
+                        // remaining waiters
+                        tids[best..].rotate_left(1);
+                        let tid = tids.pop().unwrap();
+
+                        if !tids.is_empty() {
+                            self.event_queue
+                                .insert(event_id, EventQueueItem::BlockedTids(tids))
+                                .unwrap(); // removed an item, so there must be
+                                           // room
                         }
-                    };
-                    assert!(matches!(blocked_task.state, TaskState::EventWait));
-                    blocked_task.inject_return_value(volatile_data);
-                    blocked_task.state = TaskState::Ready;
-
-                    self.ready_queue
-                        .push(ReadyQueueItem {
-                            tid,
-                            priority: blocked_task.priority,
-                        })
-                        .expect("no more space on ready_queue");
+
+                        self.wake_event_waiter(tid, event_id, volatile_data)
+                    }
                 }
-                Some(EventQueueItem::VolatileData(_old_data)) => {
-                    self.event_queue
-                        .insert(event_id, EventQueueItem::VolatileData(volatile_data))
-                        .unwrap(); // removed an item, so there must be room
+            }
+            Some(EventQueueItem::VolatileData(mut pending)) => {
+                if !pending.push(volatile_data) {
+                    log::debug!(
+                        "volatile data queue for event_id {} is full, dropping {:#x?}",
+                        event_id,
+                        volatile_data
+                    );
+                    self.dropped_events += 1;
                 }
+                self.event_queue
+                    .insert(event_id, EventQueueItem::VolatileData(pending))
+                    .unwrap(); // removed an item, so there must be room
             }
-        });
+        }
+    }
+
+    /// Unblock a task waiting on `event_id`, returning `volatile_data` from
+    /// its `AwaitEvent` call.
+    fn wake_event_waiter(&mut self, tid: Tid, event_id: usize, volatile_data: usize) {
+        let blocked_task = match self.task_mut(tid) {
+            Some(task) => task,
+            None => {
+                log::debug!(
+                    "{:?} was terminated while waiting for event_id {}",
+                    tid,
+                    event_id
+                );
+                return;
+            }
+        };
+        assert!(matches!(blocked_task.state, TaskState::EventWait));
+        blocked_task.inject_return_value(volatile_data);
+        blocked_task.set_state(TaskState::Ready);
+
+        let priority = blocked_task.priority;
+        self.ready_queue.push_back(tid, priority);
     }
 }
@@ -0,0 +1,244 @@
+//! An interactive, busy-wait debug monitor on the kernel's console UART.
+//!
+//! The monitor is entered after the kernel panics (before returning to
+//! RedBoot), or when a break is received on COM2. While it's running, the
+//! kernel (and all tasks) are frozen, and their state can be inspected using a
+//! handful of simple commands (see `help`).
+
+use abi::Tid;
+
+use crate::{bwkprint, bwkprintln};
+
+use super::crash_dump::StateSummary;
+use super::{arch, stack, EventQueueItem, Kernel};
+
+/// Maximum length of a single command line.
+const MAX_LINE: usize = 64;
+
+/// Number of bytes dumped by `mem` if no length is given.
+const DEFAULT_MEM_LEN: usize = 64;
+
+/// Why the monitor was entered.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Entry {
+    /// The kernel panicked, and can't be resumed.
+    Panic,
+    /// A break was received on the console UART.
+    Break,
+}
+
+/// How the monitor was exited.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Exit {
+    /// Resume the kernel where it left off.
+    Resume,
+    /// Return to RedBoot.
+    RedBoot,
+}
+
+const HELP: &str = "\
+commands:
+  tasks              list tasks and their states
+  stack <slot>       dump a task's saved registers and backtrace
+  mem <addr> [len]   hex-dump memory
+  events             show tasks waiting for events, and unclaimed event data
+  log                print recent kernel log output
+  resume             resume the kernel (unavailable after a panic)
+  redboot            return to RedBoot";
+
+/// Busy-wait read a line from the console (echoing it back), returning the
+/// number of bytes read into `buf`.
+fn read_line(buf: &mut [u8; MAX_LINE]) -> usize {
+    let mut len = 0;
+    loop {
+        match crate::platform::bwkprint::read_byte() {
+            b'\r' | b'\n' => {
+                bwkprintln!();
+                return len;
+            }
+            // backspace / delete
+            0x08 | 0x7f => {
+                if len > 0 {
+                    len -= 1;
+                    bwkprint!("\x08 \x08");
+                }
+            }
+            b if (0x20..0x7f).contains(&b) && len < MAX_LINE => {
+                buf[len] = b;
+                len += 1;
+                bwkprint!("{}", b as char);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parse a decimal, or `0x`-prefixed hexadecimal, number.
+fn parse_usize(s: &str) -> Option<usize> {
+    if s.starts_with("0x") {
+        usize::from_str_radix(&s[2..], 16).ok()
+    } else {
+        s.parse().ok()
+    }
+}
+
+/// Hex-dump `len` bytes of memory starting at `addr`, 16 bytes per line.
+///
+/// NOTE: reading unmapped memory results in a data abort, which panics the
+/// kernel.
+fn dump_memory(addr: usize, len: usize) {
+    for line in (addr..addr.saturating_add(len)).step_by(16) {
+        let end = line.saturating_add(16).min(addr.saturating_add(len));
+
+        bwkprint!("{:#010x}: ", line);
+        for a in line..line.saturating_add(16) {
+            if a < end {
+                bwkprint!("{:02x} ", unsafe {
+                    core::ptr::read_volatile(a as *const u8)
+                });
+            } else {
+                bwkprint!("   ");
+            }
+        }
+        bwkprint!(" ");
+        for a in line..end {
+            match unsafe { core::ptr::read_volatile(a as *const u8) } {
+                b @ 0x20..=0x7e => {
+                    bwkprint!("{}", b as char);
+                }
+                _ => {
+                    bwkprint!(".");
+                }
+            }
+        }
+        bwkprintln!();
+    }
+}
+
+impl Kernel {
+    fn monitor_tasks(&self) {
+        for (slot, task) in self.tasks.iter().enumerate() {
+            let task = match task {
+                Some(task) => task,
+                None => continue,
+            };
+
+            let tid = Tid::new(slot, self.generations[slot] as usize);
+            bwkprintln!(
+                "  [{:>2}] {:?}{} priority={} parent={:?} state={}",
+                slot,
+                tid,
+                if self.current_tid == Some(tid) {
+                    "*"
+                } else {
+                    ""
+                },
+                task.priority,
+                task.parent_tid,
+                StateSummary(&task.state)
+            );
+        }
+    }
+
+    fn monitor_stack(&self, slot: usize) {
+        let task = match self.tasks.get(slot) {
+            Some(Some(task)) => task,
+            _ => return bwkprintln!("no task in slot {}", slot),
+        };
+
+        // the current task's saved state is only up-to-date while it's trapped
+        // in the kernel
+        let saved = if self.current_tid.map(|tid| tid.slot()) == Some(slot) {
+            self.trap_frame
+        } else {
+            Some(task.sp)
+        };
+
+        match saved {
+            // SAFETY: the saved state lives on the (live) task's stack
+            Some(saved) => arch::print_user_state(
+                unsafe { saved.as_ref() },
+                stack::base(slot)..stack::top(slot),
+            ),
+            None => bwkprintln!("  (registers unavailable)"),
+        }
+    }
+
+    fn monitor_events(&self) {
+        if self.event_queue.is_empty() {
+            return bwkprintln!("  (none)");
+        }
+
+        for (event_id, item) in self.event_queue.iter() {
+            match item {
+                EventQueueItem::BlockedTids(tids) => {
+                    bwkprintln!("  event_id {}: waiting {:?}", event_id, &tids[..])
+                }
+                EventQueueItem::VolatileData(pending) => bwkprintln!(
+                    "  event_id {}: {} unclaimed volatile data",
+                    event_id,
+                    pending.len()
+                ),
+            }
+        }
+    }
+
+    /// Run the monitor until the user asks to resume the kernel, or to return
+    /// to RedBoot.
+    ///
+    /// Never returns [`Exit::Resume`] when entered via [`Entry::Panic`].
+    pub fn monitor(&self, entry: Entry) -> Exit {
+        bwkprintln!("entered kernel monitor ({:?}), type `help` for help", entry);
+
+        let mut buf = [0; MAX_LINE];
+        loop {
+            bwkprint!("monitor> ");
+            let len = read_line(&mut buf);
+            // only printable ASCII is accepted by `read_line`
+            let line = core::str::from_utf8(&buf[..len]).unwrap_or("");
+
+            let mut args = line.split_whitespace();
+            match (args.next(), args.next(), args.next()) {
+                (None, _, _) => {}
+                (Some("help"), None, None) => bwkprintln!("{}", HELP),
+                (Some("tasks"), None, None) => self.monitor_tasks(),
+                (Some("stack"), Some(slot), None) => match parse_usize(slot) {
+                    Some(slot) => self.monitor_stack(slot),
+                    None => bwkprintln!("invalid slot: {}", slot),
+                },
+                (Some("mem"), Some(addr), len) => {
+                    match (
+                        parse_usize(addr),
+                        len.map_or(Some(DEFAULT_MEM_LEN), parse_usize),
+                    ) {
+                        (Some(addr), Some(len)) => dump_memory(addr, len),
+                        _ => bwkprintln!("usage: mem <addr> [len]"),
+                    }
+                }
+                (Some("events"), None, None) => self.monitor_events(),
+                (Some("log"), None, None) => crate::kernel_log::print_recent(),
+                (Some("resume"), None, None) => match entry {
+                    Entry::Panic => bwkprintln!("the kernel panicked, and can't be resumed"),
+                    Entry::Break => return Exit::Resume,
+                },
+                (Some("redboot"), None, None) => return Exit::RedBoot,
+                _ => bwkprintln!("unknown command (type `help` for help)"),
+            }
+        }
+    }
+}
+
+/// Run the monitor after a kernel panic (with or without an initialized global
+/// kernel), returning once the user asks to return to RedBoot.
+///
+/// Called from the platform's panic handler.
+pub fn enter_after_panic() {
+    unsafe {
+        match &crate::KERNEL {
+            Some(kernel) => {
+                kernel.monitor(Entry::Panic);
+            }
+            None => bwkprintln!("kernel wasn't initialized, skipping monitor"),
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! The scheduler's ready queue.
+
+use abi::{Tid, NUM_PRIORITIES};
+
+use super::MAX_TASKS;
+
+// each priority level gets a bit in `ReadyQueue::non_empty`
+const _: () = assert!(NUM_PRIORITIES <= 32);
+
+/// Priority queue of tasks ready to be scheduled.
+///
+/// Each priority level is an intrusive singly-linked FIFO list (threaded
+/// through the `next` array), and a bitmap tracks
+/// which levels are non-empty. As such, all operations are O(1), and tasks of
+/// equal priority are scheduled in strict FIFO order.
+pub struct ReadyQueue {
+    /// Bit `n` is set iff priority level `n` is non-empty.
+    non_empty: u32,
+    /// The (head, tail) of each priority level's list.
+    levels: [Option<(Tid, Tid)>; NUM_PRIORITIES],
+    /// The task following a given task in its priority level's list (indexed
+    /// by slot).
+    next: [Option<Tid>; MAX_TASKS],
+}
+
+impl ReadyQueue {
+    /// Create a new, empty ReadyQueue.
+    pub fn new() -> ReadyQueue {
+        ReadyQueue {
+            non_empty: 0,
+            levels: [None; NUM_PRIORITIES],
+            next: [None; MAX_TASKS],
+        }
+    }
+
+    fn level(priority: isize) -> usize {
+        assert!(
+            (0..NUM_PRIORITIES as isize).contains(&priority),
+            "invalid priority {}",
+            priority
+        );
+        priority as usize
+    }
+
+    /// Check if the task in `tid`'s slot is queued.
+    fn is_queued(&self, tid: Tid) -> bool {
+        self.next[tid.slot()].is_some()
+            || (self.levels.iter().flatten()).any(|(_, tail)| tail.slot() == tid.slot())
+    }
+
+    /// Add a task to the back of its priority level.
+    pub fn push_back(&mut self, tid: Tid, priority: isize) {
+        let level = Self::level(priority);
+        debug_assert!(!self.is_queued(tid), "{:?} is already queued", tid);
+
+        self.next[tid.slot()] = None;
+        self.levels[level] = match self.levels[level] {
+            None => Some((tid, tid)),
+            Some((head, tail)) => {
+                self.next[tail.slot()] = Some(tid);
+                Some((head, tid))
+            }
+        };
+        self.non_empty |= 1 << level;
+    }
+
+    /// Add a task to the front of its priority level.
+    pub fn push_front(&mut self, tid: Tid, priority: isize) {
+        let level = Self::level(priority);
+        debug_assert!(!self.is_queued(tid), "{:?} is already queued", tid);
+
+        self.levels[level] = match self.levels[level] {
+            None => {
+                self.next[tid.slot()] = None;
+                Some((tid, tid))
+            }
+            Some((head, tail)) => {
+                self.next[tid.slot()] = Some(head);
+                Some((tid, tail))
+            }
+        };
+        self.non_empty |= 1 << level;
+    }
+
+    /// Remove the highest priority task from the queue.
+    pub fn pop(&mut self) -> Option<Tid> {
+        if self.non_empty == 0 {
+            return None;
+        }
+
+        let level = 31 - self.non_empty.leading_zeros() as usize;
+        let (head, tail) = self.levels[level].expect("non-empty level has no head");
+
+        match self.next[head.slot()].take() {
+            Some(next) => self.levels[level] = Some((next, tail)),
+            None => {
+                self.levels[level] = None;
+                self.non_empty &= !(1 << level);
+            }
+        }
+
+        Some(head)
+    }
+
+    /// Remove a specific task from the queue, returning `false` if the task
+    /// wasn't in the queue.
+    ///
+    /// Unlike the other operations, this is O(n) in the number of tasks at the
+    /// given priority level.
+    pub fn remove(&mut self, tid: Tid, priority: isize) -> bool {
+        let level = Self::level(priority);
+
+        let (head, tail) = match self.levels[level] {
+            Some(ends) => ends,
+            None => return false,
+        };
+
+        let mut prev: Option<Tid> = None;
+        let mut cur = Some(head);
+        while let Some(c) = cur {
+            if c != tid {
+                prev = cur;
+                cur = self.next[c.slot()];
+                continue;
+            }
+
+            let next = self.next[c.slot()].take();
+            let new_head = match prev {
+                None => next,
+                Some(prev) => {
+                    self.next[prev.slot()] = next;
+                    Some(head)
+                }
+            };
+            let new_tail = if tail == c { prev } else { Some(tail) };
+
+            match (new_head, new_tail) {
+                (Some(head), Some(tail)) => self.levels[level] = Some((head, tail)),
+                _ => {
+                    self.levels[level] = None;
+                    self.non_empty &= !(1 << level);
+                }
+            }
+
+            return true;
+        }
+
+        false
+    }
+
+    /// Remove all tasks from the queue.
+    pub fn clear(&mut self) {
+        self.non_empty = 0;
+        self.levels = [None; NUM_PRIORITIES];
+        self.next = [None; MAX_TASKS];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::vec::Vec;
+
+    fn tid(slot: usize) -> Tid {
+        Tid::new(slot, 0)
+    }
+
+    /// Pop every task off the queue.
+    fn drain(queue: &mut ReadyQueue) -> Vec<Tid> {
+        std::iter::from_fn(|| queue.pop()).collect()
+    }
+
+    #[test]
+    fn fifo_within_level() {
+        let mut queue = ReadyQueue::new();
+        assert_eq!(queue.pop(), None);
+
+        for slot in 0..4 {
+            queue.push_back(tid(slot), 1);
+        }
+        assert_eq!(drain(&mut queue), [tid(0), tid(1), tid(2), tid(3)]);
+
+        // a task can be re-queued once it's been popped
+        queue.push_back(tid(0), 1);
+        queue.push_back(tid(1), 1);
+        assert_eq!(queue.pop(), Some(tid(0)));
+        queue.push_back(tid(0), 1);
+        assert_eq!(drain(&mut queue), [tid(1), tid(0)]);
+    }
+
+    #[test]
+    fn push_front() {
+        let mut queue = ReadyQueue::new();
+
+        // ...onto an empty level
+        queue.push_front(tid(0), 1);
+        assert_eq!(drain(&mut queue), [tid(0)]);
+
+        // ...onto a non-empty level, which keeps its tail
+        queue.push_back(tid(0), 1);
+        queue.push_back(tid(1), 1);
+        queue.push_front(tid(2), 1);
+        queue.push_back(tid(3), 1);
+        assert_eq!(drain(&mut queue), [tid(2), tid(0), tid(1), tid(3)]);
+    }
+
+    #[test]
+    fn remove() {
+        let mut queue = ReadyQueue::new();
+        for slot in 0..5 {
+            queue.push_back(tid(slot), 1);
+        }
+
+        // middle
+        assert!(queue.remove(tid(2), 1));
+        // head
+        assert!(queue.remove(tid(0), 1));
+        // tail
+        assert!(queue.remove(tid(4), 1));
+        // already removed, or at a different priority
+        assert!(!queue.remove(tid(2), 1));
+        assert!(!queue.remove(tid(1), 2));
+
+        // the level's tail was updated, so new tasks are still linked in
+        queue.push_back(tid(0), 1);
+        assert_eq!(drain(&mut queue), [tid(1), tid(3), tid(0)]);
+
+        // removing the last task empties the level
+        queue.push_back(tid(0), 1);
+        assert!(queue.remove(tid(0), 1));
+        assert_eq!(queue.pop(), None);
+        assert!(!queue.remove(tid(0), 1));
+    }
+
+    #[test]
+    fn highest_priority_first() {
+        let mut queue = ReadyQueue::new();
+        let top = NUM_PRIORITIES as isize - 1;
+
+        queue.push_back(tid(0), 0);
+        queue.push_back(tid(1), 3);
+        queue.push_back(tid(2), top);
+        queue.push_back(tid(3), 3);
+
+        assert_eq!(queue.pop(), Some(tid(2)));
+        // the top level drained, so the next non-empty level is picked...
+        assert_eq!(queue.pop(), Some(tid(1)));
+        // ...until a higher level is refilled
+        queue.push_back(tid(2), top);
+        assert_eq!(queue.pop(), Some(tid(2)));
+        // emptying a level using `remove` is also accounted for
+        assert!(queue.remove(tid(3), 3));
+        assert_eq!(drain(&mut queue), [tid(0)]);
+    }
+
+    #[test]
+    fn clear() {
+        let mut queue = ReadyQueue::new();
+        queue.push_back(tid(0), 0);
+        queue.push_back(tid(1), 0);
+        queue.push_back(tid(2), 5);
+
+        queue.clear();
+        assert_eq!(queue.pop(), None);
+
+        queue.push_back(tid(1), 0);
+        assert_eq!(drain(&mut queue), [tid(1)]);
+    }
+}
@@ -0,0 +1,160 @@
+//! Tearing down tasks.
+
+use abi::Tid;
+
+use super::task::TaskState;
+use super::{EventQueueItem, Kernel};
+
+impl Kernel {
+    /// Kill the currently running task (e.g: after it faults), reporting why.
+    #[cfg_attr(feature = "platform-host", allow(dead_code))]
+    pub(super) fn kill_current_task(&mut self, reason: core::fmt::Arguments) {
+        let tid = self.current_tid.expect("no task is running");
+        kprintln!("{:?} {}, killing it", tid, reason);
+        self.reap(tid);
+    }
+
+    /// Remove a task from every kernel data structure it may be part of,
+    /// unblock any tasks which are blocked on it, destroy any mailboxes it
+    /// owns, and free its slot (and stack) for reuse.
+    ///
+    /// Used by `Exit` (on the current task), `Destroy` (on any other task), and
+    /// when killing tasks which overflow their stack. `tid` must refer to a
+    /// live task.
+    pub(super) fn reap(&mut self, tid: Tid) {
+        use abi::syscall::error::Send as SendError;
+
+        super::trace::task_exit(tid);
+        self.timers.cancel(tid);
+
+        let task = self.tasks[tid.slot()].as_mut().unwrap();
+        let priority = task.priority;
+        let send_queue_head = task.send_queue_head.take();
+        task.send_queue_tail = None;
+
+        // unlink the task from whatever it's blocked on
+        match task.state {
+            // NOTE: the current task isn't in the ready queue while it's running
+            TaskState::Ready => {
+                self.ready_queue.remove(tid, priority);
+            }
+            TaskState::SendWait { receiver, .. } => self.unlink_sender(receiver, tid),
+            TaskState::EventWait => self.unlink_event_waiter(tid),
+            TaskState::MailboxWait { mailbox, .. } => self.unlink_mailbox_waiter(mailbox, tid),
+            // nothing refers to tasks in these states (besides the timer
+            // wheel, which was already taken care of)
+            TaskState::RecvWait { .. } | TaskState::ReplyWait { .. } | TaskState::DelayWait => {}
+        }
+
+        // fail any tasks waiting to send to the task...
+        let mut next_sender = send_queue_head;
+        while let Some(sender_tid) = next_sender {
+            let sender = self.tasks[sender_tid.slot()].as_mut().unwrap();
+            next_sender = match sender.state {
+                TaskState::SendWait { next, .. } => next,
+                _ => panic!("sender was not in SendWait state"),
+            };
+            self.fail_sender(sender_tid, SendError::CouldNotSSR);
+        }
+
+        // ...any tasks waiting on the task for a reply...
+        for slot in 0..self.tasks.len() {
+            let sender_tid = match &self.tasks[slot] {
+                Some(sender) => match sender.state {
+                    TaskState::ReplyWait { receiver, .. } if receiver == tid => {
+                        Tid::new(slot, self.generations[slot] as usize)
+                    }
+                    _ => continue,
+                },
+                None => continue,
+            };
+            self.fail_sender(sender_tid, SendError::ReceiverTerminated);
+        }
+
+        // ...and any tasks waiting on the task's mailboxes
+        self.destroy_owned_mailboxes(tid);
+
+        self.free_slot(tid);
+        if self.current_tid == Some(tid) {
+            self.current_tid = None;
+            self.trap_frame = None;
+        }
+    }
+
+    /// Unblock a task in the `SendWait` or `ReplyWait` state, returning
+    /// `error` from its `Send` call.
+    pub(super) fn fail_sender(&mut self, tid: Tid, error: abi::syscall::error::Send) {
+        let sender = self.tasks[tid.slot()].as_mut().unwrap();
+        sender.inject_return_value(error);
+        sender.set_state(TaskState::Ready);
+        let priority = sender.priority;
+        self.ready_queue.push_back(tid, priority);
+    }
+
+    /// Remove `sender` from `receiver`'s send queue.
+    pub(super) fn unlink_sender(&mut self, receiver: Tid, sender: Tid) {
+        macro_rules! next_of {
+            ($tid:expr) => {
+                match self.tasks[$tid.slot()].as_mut().unwrap().state {
+                    TaskState::SendWait { ref mut next, .. } => next,
+                    _ => panic!("sender was not in SendWait state"),
+                }
+            };
+        }
+
+        let sender_next = next_of!(sender).take();
+
+        // find the task before `sender` in the queue
+        let mut prev: Option<Tid> = None;
+        let mut cur = self.tasks[receiver.slot()]
+            .as_ref()
+            .unwrap()
+            .send_queue_head;
+        while let Some(c) = cur {
+            if c == sender {
+                break;
+            }
+            prev = cur;
+            cur = *next_of!(c);
+        }
+        assert!(cur.is_some(), "sender was not in receiver's send queue");
+
+        match prev {
+            Some(prev) => *next_of!(prev) = sender_next,
+            None => {
+                self.tasks[receiver.slot()]
+                    .as_mut()
+                    .unwrap()
+                    .send_queue_head = sender_next
+            }
+        }
+
+        let receiver = self.tasks[receiver.slot()].as_mut().unwrap();
+        if receiver.send_queue_tail == Some(sender) {
+            receiver.send_queue_tail = prev;
+        }
+    }
+
+    /// Remove `tid` from whichever event it's waiting on.
+    fn unlink_event_waiter(&mut self, tid: Tid) {
+        let event_id = self
+            .event_queue
+            .iter()
+            .find_map(|(&event_id, item)| match item {
+                EventQueueItem::BlockedTids(tids) if tids.contains(&tid) => Some(event_id),
+                _ => None,
+            });
+        let event_id = event_id.expect("task was not waiting on any event");
+
+        if let Some(EventQueueItem::BlockedTids(tids)) = self.event_queue.get_mut(&event_id) {
+            // preserve the order of the remaining waiters
+            let idx = tids.iter().position(|&t| t == tid).unwrap();
+            tids[idx..].rotate_left(1);
+            tids.pop();
+
+            if tids.is_empty() {
+                self.event_queue.remove(&event_id);
+            }
+        }
+    }
+}
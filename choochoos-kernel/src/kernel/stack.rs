@@ -0,0 +1,100 @@
+//! User stack layout, overflow detection, and usage tracking.
+//!
+//! Each task slot owns a fixed `USER_STACK_SIZE` region of the user stack
+//! area. The bottom of each region holds a canary, and the top
+//! `STACK_PAINT_SIZE` bytes of the region are "painted" with a known pattern
+//! when the task is created. Since stacks grow down, an overflowing task
+//! clobbers its canary before anything else, and the lowest un-painted word
+//! marks how deep the stack has ever grown.
+//!
+//! Only painting a bounded region keeps `Create` cheap, at the cost of
+//! high-water marks saturating at `STACK_PAINT_SIZE`.
+//!
+//! On `platform-host`, tasks run on their own thread's stack, and each slot's
+//! region of the user stack area only holds the task's `UserStack`. As such,
+//! there's no canary to check, and no stack usage to track.
+
+#[cfg(not(feature = "platform-host"))]
+use core::ptr;
+
+#[cfg(not(feature = "platform-host"))]
+use super::USER_STACK_SIZE;
+
+/// Size of the region of the user stack area owned by each task slot.
+#[cfg(not(feature = "platform-host"))]
+pub const SLOT_SIZE: usize = USER_STACK_SIZE;
+/// Size of the region of the user stack area owned by each task slot.
+#[cfg(feature = "platform-host")]
+pub const SLOT_SIZE: usize = core::mem::size_of::<super::UserStack>();
+
+/// Number of words at the bottom of each stack reserved for the canary.
+#[cfg(not(feature = "platform-host"))]
+const CANARY_WORDS: usize = 8;
+/// Value written to each canary word.
+#[cfg(not(feature = "platform-host"))]
+const CANARY: u32 = 0xdead_c0de;
+/// Value used to paint unused stack memory.
+#[cfg(not(feature = "platform-host"))]
+const PAINT: u32 = 0xc5c5_c5c5;
+
+#[cfg(not(feature = "platform-host"))]
+const WORD: usize = core::mem::size_of::<u32>();
+
+/// Index of the lowest painted word in each stack.
+#[cfg(not(feature = "platform-host"))]
+const FIRST_PAINTED: usize = {
+    let words = USER_STACK_SIZE / WORD;
+    let paint_words = super::STACK_PAINT_SIZE / WORD;
+    if paint_words + CANARY_WORDS < words {
+        words - paint_words
+    } else {
+        CANARY_WORDS
+    }
+};
+
+/// Returns the lowest address of the stack for the given task slot.
+pub fn base(slot: usize) -> usize {
+    crate::platform::user_stacks_start() + SLOT_SIZE * slot
+}
+
+/// Returns the initial stack pointer (i.e: highest address) of the stack for
+/// the given task slot.
+pub fn top(slot: usize) -> usize {
+    base(slot) + SLOT_SIZE
+}
+
+/// Write the canary and paint the stack for the given task slot.
+///
+/// # Safety
+///
+/// The stack must not be in use.
+#[cfg(not(feature = "platform-host"))]
+pub unsafe fn prepare(slot: usize) {
+    let base = base(slot) as *mut u32;
+    for i in 0..CANARY_WORDS {
+        ptr::write_volatile(base.add(i), CANARY);
+    }
+    for i in FIRST_PAINTED..(USER_STACK_SIZE / WORD) {
+        ptr::write_volatile(base.add(i), PAINT);
+    }
+}
+
+/// Check if the canary at the bottom of the stack for the given task slot is
+/// intact.
+#[cfg(not(feature = "platform-host"))]
+pub fn canary_intact(slot: usize) -> bool {
+    let base = base(slot) as *const u32;
+    (0..CANARY_WORDS).all(|i| unsafe { ptr::read_volatile(base.add(i)) } == CANARY)
+}
+
+/// Returns the maximum number of bytes the stack for the given task slot has
+/// ever used (i.e: its high-water mark), saturating at the size of the painted
+/// region.
+#[cfg(not(feature = "platform-host"))]
+pub fn high_water_mark(slot: usize) -> usize {
+    let base = base(slot) as *const u32;
+    let first_used = (FIRST_PAINTED..(USER_STACK_SIZE / WORD))
+        .find(|&i| unsafe { ptr::read_volatile(base.add(i)) } != PAINT)
+        .unwrap_or(USER_STACK_SIZE / WORD);
+    USER_STACK_SIZE - first_used * WORD
+}
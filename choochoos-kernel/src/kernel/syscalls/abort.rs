@@ -0,0 +1,12 @@
+use crate::kernel::Kernel;
+
+/// Syscall handler implementations.
+impl Kernel {
+    pub fn syscall_abort(&mut self) {
+        if let Some(tid) = self.current_tid {
+            kprintln!("{:?} aborted", tid);
+        }
+        self.crash_dump();
+        self.syscall_shutdown();
+    }
+}
@@ -1,3 +1,5 @@
+use heapless::Vec;
+
 use crate::kernel::task::TaskState;
 use crate::kernel::{EventQueueItem, Kernel};
 
@@ -15,40 +17,56 @@ impl Kernel {
 
         let current_tid =
             (self.current_tid).expect("called exec_syscall while `current_tid == None`");
-        let task = self.tasks[current_tid.into()].as_mut().unwrap();
-
-        if let Some(tid_or_volatile_data) = self.event_queue.remove(&event_id) {
-            match tid_or_volatile_data {
-                EventQueueItem::BlockedTid(tid) => {
-                    // TODO: support multiple tasks waiting on the same event?
-                    panic!(
-                        "AwaitEvent({}): {:?} is already waiting for this event",
-                        event_id, tid
-                    );
+        let task = self.tasks[current_tid.slot()].as_mut().unwrap();
+
+        match self.event_queue.get_mut(&event_id) {
+            Some(EventQueueItem::BlockedTids(tids)) => {
+                if tids.push(current_tid).is_err() {
+                    return Err(Error::TooManyWaiters);
                 }
-                EventQueueItem::VolatileData(data) => {
-                    kdebug!(
-                        "AwaitEvent({}): data already arrived {:#x?}",
-                        event_id,
-                        data
-                    );
-
-                    return Ok(Some(data));
+            }
+            Some(EventQueueItem::VolatileData(pending)) => {
+                // report any dropped data before delivering the rest
+                let overflowed = pending.take_overflowed();
+                let data = if overflowed { None } else { pending.pop() };
+                if pending.is_empty() {
+                    self.event_queue.remove(&event_id);
                 }
+
+                return match data {
+                    None => {
+                        log::debug!("AwaitEvent({}): volatile data was dropped", event_id);
+                        Err(Error::CorruptedVolatileData)
+                    }
+                    Some(data) => {
+                        log::trace!(
+                            "AwaitEvent({}): data already arrived {:#x?}",
+                            event_id,
+                            data
+                        );
+                        Ok(Some(data))
+                    }
+                };
+            }
+            None => {
+                let mut tids = Vec::new();
+                tids.push(current_tid).unwrap();
+                self.event_queue
+                    .insert(event_id, EventQueueItem::BlockedTids(tids))
+                    .expect("out of space on the event queue");
             }
         }
 
-        kdebug!(
+        log::trace!(
             "AwaitEvent({}): put {:?} on event_queue",
             event_id,
             current_tid
         );
-        self.event_queue
-            .insert(event_id, EventQueueItem::BlockedTid(current_tid))
-            .expect("out of space on the event queue");
+
+        crate::platform::interrupts::event_awaited(event_id);
 
         assert!(matches!(task.state, TaskState::Ready));
-        task.state = TaskState::EventWait;
+        task.set_state(TaskState::EventWait);
 
         Ok(None)
     }
@@ -1,7 +1,7 @@
 use abi::Tid;
 
 use crate::kernel::task::TaskDescriptor;
-use crate::kernel::{Kernel, ReadyQueueItem};
+use crate::kernel::{stack, trace, Kernel};
 
 /// Syscall handler implementations.
 impl Kernel {
@@ -18,44 +18,32 @@ impl Kernel {
             None => panic!("Cannot create task with null pointer"),
         };
 
-        // this is an artificial limitation, but it could come in handy in the future.
-        if priority < 0 {
+        // each priority level gets its own ready queue
+        if !(0..abi::NUM_PRIORITIES as isize).contains(&priority) {
             return Err(Error::InvalidPriority);
         }
 
         // find first available none slot
-        let tid = self
+        let slot = self
             .tasks
             .iter()
-            .enumerate()
-            .find(|(_, t)| t.is_none())
-            .map(|(i, _)| Tid::from(i))
+            .position(|t| t.is_none())
             .ok_or(Error::OutOfTaskDescriptors)?;
+        let tid = Tid::new(slot, self.generations[slot] as usize);
 
         // set up a fresh stack for the new task. This requires some unsafe,
         // arch-specific, low-level shenanigans.
-        // TODO: this should be platform specific code...
         let sp = unsafe {
-            // provided by the linker
-            extern "C" {
-                static __USER_STACKS_START__: core::ffi::c_void;
-            }
-
-            // TODO: find a smarter user stack size number
-            const USER_STACK_SIZE: usize = 0x40000;
-
-            let start_of_stack = (&__USER_STACKS_START__ as *const _ as usize)
-                + (USER_STACK_SIZE * (tid.into() + 1));
-
-            crate::kernel::arch::fresh_stack(start_of_stack, function)
+            #[cfg(not(feature = "platform-host"))]
+            stack::prepare(slot);
+            crate::kernel::arch::fresh_stack(stack::top(slot), function)
         };
 
         // create the new task descriptor
-        self.tasks[tid.into()] = Some(TaskDescriptor::new(priority, self.current_tid, sp));
+        self.tasks[slot] = Some(TaskDescriptor::new(priority, self.current_tid, sp));
+        trace::task_create(tid, self.current_tid, priority);
 
-        self.ready_queue
-            .push(ReadyQueueItem { tid, priority })
-            .expect("out of space on the ready queue");
+        self.ready_queue.push_back(tid, priority);
 
         Ok(tid)
     }
@@ -0,0 +1,52 @@
+use crate::kernel::task::TaskState;
+use crate::kernel::timeout::current_tick;
+use crate::kernel::Kernel;
+
+/// Syscall handler implementations.
+impl Kernel {
+    pub fn syscall_delay(
+        &mut self,
+        ticks: isize,
+    ) -> Result<Option<usize>, abi::syscall::error::Delay> {
+        if ticks < 0 {
+            return Err(abi::syscall::error::Delay::NegativeDelay);
+        }
+
+        self.delay_until(current_tick() + ticks as u64)
+    }
+
+    pub fn syscall_delay_until(
+        &mut self,
+        tick: usize,
+    ) -> Result<Option<usize>, abi::syscall::error::Delay> {
+        self.delay_until(tick as u64)
+    }
+
+    /// Common implementation of `Delay` and `DelayUntil`.
+    ///
+    /// Returns `Ok(None)` if the task blocked, in which case the current tick
+    /// is returned once the delay elapses.
+    fn delay_until(&mut self, deadline: u64) -> Result<Option<usize>, abi::syscall::error::Delay> {
+        use abi::syscall::error::Delay as Error;
+
+        // without a kernel clock, the task would never wake up
+        if !cfg!(feature = "kernel-clock") {
+            return Err(Error::ClockDisabled);
+        }
+
+        let now = current_tick();
+        if deadline <= now {
+            return Ok(Some(now as usize));
+        }
+
+        let tid = (self.current_tid).expect("called exec_syscall while `current_tid == None`");
+        log::trace!("{:?} is delaying until tick {}", tid, deadline);
+
+        let task = self.tasks[tid.slot()].as_mut().unwrap();
+        assert!(matches!(task.state, TaskState::Ready));
+        task.set_state(TaskState::DelayWait);
+        self.timers.insert(tid, deadline);
+
+        Ok(None)
+    }
+}
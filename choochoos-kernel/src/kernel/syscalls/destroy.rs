@@ -0,0 +1,22 @@
+use abi::Tid;
+
+use crate::kernel::Kernel;
+
+/// Syscall handler implementations.
+impl Kernel {
+    pub fn syscall_destroy(&mut self, tid: Tid) -> Result<(), abi::syscall::error::Destroy> {
+        use abi::syscall::error::Destroy as Error;
+
+        if self.task(tid).is_none() {
+            return Err(Error::TidDoesNotExist);
+        }
+
+        if self.current_tid == Some(tid) {
+            return Err(Error::TidIsCurrentTask);
+        }
+
+        self.reap(tid);
+
+        Ok(())
+    }
+}
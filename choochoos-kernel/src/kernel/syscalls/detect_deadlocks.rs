@@ -0,0 +1,26 @@
+use abi::Tid;
+
+use crate::kernel::Kernel;
+use crate::util::user_slice::UserSliceMut;
+
+/// Syscall handler implementations.
+impl Kernel {
+    pub fn syscall_detect_deadlocks(&mut self, tids: UserSliceMut<Tid>) -> usize {
+        let mut found = 0;
+        self.for_each_deadlock(|cycle| {
+            self.report_deadlock(cycle);
+            for &tid in cycle {
+                if found < tids.len() {
+                    unsafe { *tids.as_ptr().add(found) = tid };
+                }
+                found += 1;
+            }
+        });
+
+        if found == 0 {
+            kprintln!("no deadlocks detected");
+        }
+
+        found
+    }
+}
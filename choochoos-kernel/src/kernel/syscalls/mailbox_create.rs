@@ -0,0 +1,47 @@
+use abi::MailboxId;
+
+use crate::kernel::mailbox::Mailbox;
+use crate::kernel::Kernel;
+
+/// Syscall handler implementations.
+impl Kernel {
+    pub fn syscall_mailbox_create(
+        &mut self,
+        slot_size: usize,
+        count: usize,
+    ) -> Result<MailboxId, abi::syscall::error::MailboxCreate> {
+        use abi::syscall::error::MailboxCreate as Error;
+
+        if slot_size == 0 || count == 0 {
+            return Err(Error::InvalidSize);
+        }
+
+        let owner = (self.current_tid).expect("called exec_syscall while `current_tid == None`");
+
+        let slot = match self.mailboxes.iter().position(Option::is_none) {
+            Some(slot) => slot,
+            None => return Err(Error::OutOfMailboxes),
+        };
+
+        let base = match Mailbox::storage_size(slot_size, count)
+            .and_then(|size| self.alloc_mailbox_storage(size))
+        {
+            Some(base) => base,
+            None => {
+                log::debug!(
+                    "{:?} requested a {}x{} byte mailbox, but the mailbox pool is exhausted",
+                    owner,
+                    count,
+                    slot_size
+                );
+                return Err(Error::OutOfMemory);
+            }
+        };
+
+        self.mailboxes[slot] = Some(Mailbox::new(owner, base, slot_size, count));
+        let id = MailboxId::new(slot, self.mailbox_generations[slot] as usize);
+        log::trace!("{:?} created {:?}", owner, id);
+
+        Ok(id)
+    }
+}
@@ -0,0 +1,28 @@
+use abi::MailboxId;
+
+use crate::kernel::Kernel;
+
+/// Syscall handler implementations.
+impl Kernel {
+    pub fn syscall_mailbox_destroy(
+        &mut self,
+        id: MailboxId,
+    ) -> Result<(), abi::syscall::error::MailboxDestroy> {
+        use abi::syscall::error::MailboxDestroy as Error;
+
+        let tid = (self.current_tid).expect("called exec_syscall while `current_tid == None`");
+
+        let mailbox = match self.mailbox_mut(id) {
+            Some(mailbox) => mailbox,
+            None => return Err(Error::MailboxDoesNotExist),
+        };
+
+        if mailbox.owner != tid {
+            return Err(Error::NotOwner);
+        }
+
+        self.destroy_mailbox(id);
+
+        Ok(())
+    }
+}
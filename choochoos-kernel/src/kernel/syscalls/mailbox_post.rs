@@ -0,0 +1,62 @@
+use abi::MailboxId;
+
+use crate::util::user_slice::UserSlice;
+
+use crate::kernel::task::TaskState;
+use crate::kernel::Kernel;
+
+/// Syscall handler implementations.
+impl Kernel {
+    pub fn syscall_mailbox_post(
+        &mut self,
+        id: MailboxId,
+        msg: UserSlice<u8>,
+    ) -> Result<(), abi::syscall::error::MailboxPost> {
+        use abi::syscall::error::MailboxPost as Error;
+
+        let mailbox = match self.mailbox_mut(id) {
+            Some(mailbox) => mailbox,
+            None => return Err(Error::MailboxDoesNotExist),
+        };
+
+        if msg.len() > mailbox.slot_size {
+            return Err(Error::MessageTooLarge);
+        }
+
+        // tasks only wait on empty mailboxes, so the first waiter (if any) gets
+        // the message directly
+        let waiter_tid = match self.dequeue_mailbox_waiter(id) {
+            Some(tid) => tid,
+            None => {
+                let mailbox = self.mailboxes[id.slot()].as_mut().unwrap();
+                if mailbox.is_full() {
+                    return Err(Error::Full);
+                }
+                mailbox.push(msg);
+                return Ok(());
+            }
+        };
+
+        let waiter = self.tasks[waiter_tid.slot()].as_mut().unwrap();
+        let mut recv_dst = match waiter.state {
+            TaskState::MailboxWait { recv_dst, .. } => recv_dst,
+            _ => panic!("mailbox waiter was not in MailboxWait state"),
+        };
+
+        recv_dst.copy_from_slice_min(msg);
+        log::trace!(
+            "{:?} received {} bytes from {:?}",
+            waiter_tid,
+            msg.len(),
+            id
+        );
+
+        // like `Receive`, the message's original length is returned
+        waiter.inject_return_value(msg.len());
+        waiter.set_state(TaskState::Ready);
+        let priority = waiter.priority;
+        self.ready_queue.push_back(waiter_tid, priority);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use abi::MailboxId;
+
+use crate::util::user_slice::UserSliceMut;
+
+use crate::kernel::task::TaskState;
+use crate::kernel::Kernel;
+
+/// Syscall handler implementations.
+impl Kernel {
+    pub fn syscall_mailbox_receive(
+        &mut self,
+        id: MailboxId,
+        msg_dst: UserSliceMut<u8>,
+    ) -> Result<Option<usize>, abi::syscall::error::MailboxReceive> {
+        self.mailbox_receive(id, msg_dst, true)
+    }
+
+    pub fn syscall_mailbox_try_receive(
+        &mut self,
+        id: MailboxId,
+        msg_dst: UserSliceMut<u8>,
+    ) -> Result<usize, abi::syscall::error::MailboxReceive> {
+        match self.mailbox_receive(id, msg_dst, false)? {
+            Some(msg_len) => Ok(msg_len),
+            None => unreachable!("MailboxTryReceive() blocked"),
+        }
+    }
+
+    /// Common implementation of `MailboxReceive` and `MailboxTryReceive`,
+    /// where `block` determines whether to wait for a message if the mailbox is
+    /// empty.
+    ///
+    /// Returns `Ok(None)` if the task blocked, in which case the return value
+    /// is written later, as part of the `MailboxPost` (or `MailboxDestroy`)
+    /// syscall.
+    fn mailbox_receive(
+        &mut self,
+        id: MailboxId,
+        msg_dst: UserSliceMut<u8>,
+        block: bool,
+    ) -> Result<Option<usize>, abi::syscall::error::MailboxReceive> {
+        use abi::syscall::error::MailboxReceive as Error;
+
+        let tid = (self.current_tid).expect("called exec_syscall while `current_tid == None`");
+
+        let mailbox = match self.mailbox_mut(id) {
+            Some(mailbox) => mailbox,
+            None => return Err(Error::MailboxDoesNotExist),
+        };
+
+        if !mailbox.is_empty() {
+            let msg_len = mailbox.pop(msg_dst);
+            log::trace!("{:?} received {} bytes from {:?}", tid, msg_len, id);
+            return Ok(Some(msg_len));
+        }
+
+        if !block {
+            return Err(Error::Empty);
+        }
+
+        log::trace!("{:?} is waiting on {:?}", tid, id);
+        let task = self.tasks[tid.slot()].as_mut().unwrap();
+        assert!(matches!(task.state, TaskState::Ready));
+        task.set_state(TaskState::MailboxWait {
+            mailbox: id,
+            recv_dst: msg_dst,
+            next: None,
+        });
+        self.enqueue_mailbox_waiter(id, tid);
+
+        // return value written later, as part of the post syscall
+        Ok(None)
+    }
+}
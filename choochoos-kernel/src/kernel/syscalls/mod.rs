@@ -1,14 +1,28 @@
 //! Syscall handler implementations. See the [`Kernel`](crate::kernel::Kernel)
 //! docs.
 
+mod abort;
 mod await_event;
 mod create;
+mod delay;
+mod destroy;
+mod detect_deadlocks;
 mod exit;
+mod mailbox_create;
+mod mailbox_destroy;
+mod mailbox_post;
+mod mailbox_receive;
 mod my_parent_tid;
 mod my_tid;
 mod perf;
+mod perf_task;
+mod read_log;
+mod read_trace;
 mod receive;
 mod reply;
 mod send;
+mod set_log_level;
 mod shutdown;
+mod stack_usage;
+mod time;
 mod r#yield;
@@ -8,10 +8,19 @@ impl Kernel {
         let current_tid =
             (self.current_tid).expect("called exec_syscall while `current_tid == None`");
 
-        self.tasks[current_tid.into()]
+        use abi::syscall::error::MyParentTid as Error;
+
+        let parent_tid = self.tasks[current_tid.slot()]
             .as_ref()
             .unwrap()
             .parent_tid
-            .ok_or(abi::syscall::error::MyParentTid::NoParent)
+            .ok_or(Error::NoParent)?;
+
+        // the parent may have exited (and had its slot reused) since
+        if self.task(parent_tid).is_none() {
+            return Err(Error::TidDoesNotExist);
+        }
+
+        Ok(parent_tid)
     }
 }
@@ -8,7 +8,12 @@ impl Kernel {
         if let Some(mut perf_data) = perf_data {
             let perf_data = unsafe { perf_data.as_mut() };
 
-            perf_data.idle_time_pct = 0; // XXX: actually track idle time
+            let now = crate::platform::time::uptime();
+            perf_data.version = abi::PERF_VERSION;
+            perf_data.idle_time_pct = self.idle.since_boot_pct(now);
+            perf_data.idle_time_pct_window = self.idle.window_pct(now);
+            perf_data.dropped_events = self.dropped_events as u32;
+            perf_data.irq_time_us = self.irq_time.as_micros() as u64;
         }
     }
 }
@@ -0,0 +1,50 @@
+use core::ptr;
+use core::time::Duration;
+
+use abi::syscall::{MAX_SYSCALLS, NUM_SYSCALLS};
+use abi::Tid;
+
+use crate::kernel::Kernel;
+
+/// Syscall handler implementations.
+impl Kernel {
+    pub fn syscall_perf_task(
+        &mut self,
+        tid: Tid,
+        perf_data: Option<ptr::NonNull<abi::TaskPerfData>>,
+    ) -> Result<(), abi::syscall::error::PerfTask> {
+        use abi::syscall::error::PerfTask as Error;
+
+        let task = self.task(tid).ok_or(Error::TidDoesNotExist)?;
+
+        let mut perf_data = match perf_data {
+            Some(perf_data) => perf_data,
+            None => return Ok(()),
+        };
+        let perf_data = unsafe { perf_data.as_mut() };
+
+        // include time spent in the task's current state
+        let now = crate::platform::time::uptime();
+        let mut state_time = task.perf.state_time;
+        *state_time.of_mut(&task.state) += now.saturating_sub(task.perf.state_since);
+
+        let us = |d: Duration| d.as_micros() as u64;
+
+        perf_data.version = abi::PERF_VERSION;
+        perf_data.context_switches = task.perf.context_switches;
+        perf_data.num_syscalls = NUM_SYSCALLS as u32;
+        perf_data.syscalls = [0; MAX_SYSCALLS];
+        perf_data.syscalls[..NUM_SYSCALLS].copy_from_slice(&task.perf.syscalls);
+        perf_data.cpu_time_us = us(task.perf.cpu_time);
+        // tasks remain in the `Ready` state while they're running
+        perf_data.ready_time_us = us(state_time.ready.saturating_sub(task.perf.cpu_time));
+        perf_data.send_wait_time_us = us(state_time.send_wait);
+        perf_data.recv_wait_time_us = us(state_time.recv_wait);
+        perf_data.reply_wait_time_us = us(state_time.reply_wait);
+        perf_data.event_wait_time_us = us(state_time.event_wait);
+        perf_data.delay_wait_time_us = us(state_time.delay_wait);
+        perf_data.mailbox_wait_time_us = us(state_time.mailbox_wait);
+
+        Ok(())
+    }
+}
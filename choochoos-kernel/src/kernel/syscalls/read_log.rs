@@ -0,0 +1,9 @@
+use crate::kernel::Kernel;
+use crate::util::user_slice::UserSliceMut;
+
+/// Syscall handler implementations.
+impl Kernel {
+    pub fn syscall_read_log(&mut self, buf: UserSliceMut<u8>) -> usize {
+        crate::kernel_log::log_buffer().drain_into(buf)
+    }
+}
@@ -0,0 +1,17 @@
+use abi::trace::Record;
+
+use crate::kernel::Kernel;
+use crate::util::user_slice::UserSliceMut;
+
+/// Syscall handler implementations.
+impl Kernel {
+    #[cfg(feature = "trace")]
+    pub fn syscall_read_trace(&mut self, buf: UserSliceMut<Record>) -> usize {
+        crate::kernel::trace::trace_buffer().records.drain_into(buf)
+    }
+
+    #[cfg(not(feature = "trace"))]
+    pub fn syscall_read_trace(&mut self, _buf: UserSliceMut<Record>) -> usize {
+        0
+    }
+}
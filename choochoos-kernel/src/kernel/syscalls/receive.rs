@@ -12,11 +12,40 @@ impl Kernel {
     pub fn syscall_receive(
         &mut self,
         sender_tid_dst: Option<ptr::NonNull<Tid>>,
-        mut msg_dst: UserSliceMut<u8>,
+        msg_dst: UserSliceMut<u8>,
     ) -> Option<usize> {
+        match self.receive(sender_tid_dst, msg_dst, None) {
+            Ok(msg_len) => msg_len,
+            Err(_) => unreachable!("Receive() timed out without a timeout"),
+        }
+    }
+
+    pub fn syscall_receive_timeout(
+        &mut self,
+        sender_tid_dst: Option<ptr::NonNull<Tid>>,
+        msg_dst: UserSliceMut<u8>,
+        timeout: usize,
+    ) -> Result<Option<usize>, abi::syscall::error::Receive> {
+        let timeout = self.effective_timeout(timeout);
+        self.receive(sender_tid_dst, msg_dst, Some(timeout))
+    }
+
+    /// Common implementation of the `Receive` variants, where `timeout` is the
+    /// number of ticks to wait for a message (or `None` to wait forever).
+    ///
+    /// Returns `Ok(None)` if the task blocked, in which case the return value
+    /// is written later, as part of the send syscall.
+    fn receive(
+        &mut self,
+        sender_tid_dst: Option<ptr::NonNull<Tid>>,
+        mut msg_dst: UserSliceMut<u8>,
+        timeout: Option<usize>,
+    ) -> Result<Option<usize>, abi::syscall::error::Receive> {
+        use abi::syscall::error::Receive as Error;
+
         let receiver_tid =
             (self.current_tid).expect("called exec_syscall while `current_tid == None`");
-        let receiver = self.tasks[receiver_tid.into()].as_mut().unwrap();
+        let receiver = self.tasks[receiver_tid.slot()].as_mut().unwrap();
 
         if !matches!(receiver.state, TaskState::Ready) {
             panic!(
@@ -27,17 +56,25 @@ impl Kernel {
 
         let sender_tid = match receiver.send_queue_head {
             Some(tid) => tid,
+            None if timeout == Some(0) => {
+                log::trace!("{:?} timed out waiting to receive", receiver_tid);
+                return Err(Error::Timeout);
+            }
             None => {
-                receiver.state = TaskState::RecvWait {
+                log::trace!("{:?} is waiting to receive", receiver_tid);
+                receiver.set_state(TaskState::RecvWait {
                     sender_tid_dst,
                     recv_dst: msg_dst,
-                };
+                });
+                if let Some(ticks) = timeout {
+                    self.start_timeout(receiver_tid, ticks);
+                }
                 // return value written later, as part of the send syscall
-                return None;
+                return Ok(None);
             }
         };
 
-        let sender = self.tasks[sender_tid.into()]
+        let sender = self.tasks[sender_tid.slot()]
             .as_mut()
             .expect("sender was unexpectedly missing");
 
@@ -47,6 +84,12 @@ impl Kernel {
         };
 
         let msg_len = msg_dst.copy_from_slice_min(msg_src);
+        log::trace!(
+            "{:?} received {} bytes from {:?}",
+            receiver_tid,
+            msg_len,
+            sender_tid
+        );
 
         if let Some(mut sender_tid_dst) = sender_tid_dst {
             unsafe {
@@ -60,9 +103,15 @@ impl Kernel {
             } => (reply_dst, next),
             _ => panic!("sender was not in SendWait state"),
         };
-        sender.state = TaskState::ReplyWait { reply_dst };
+        sender.set_state(TaskState::ReplyWait {
+            receiver: receiver_tid,
+            reply_dst,
+        });
+        // the message has been received, so a sender's timeout no longer
+        // applies (i.e: it waits for the reply indefinitely)
+        self.timers.cancel(sender_tid);
 
-        let receiver = self.tasks[receiver_tid.into()].as_mut().unwrap();
+        let receiver = self.tasks[receiver_tid.slot()].as_mut().unwrap();
 
         receiver.send_queue_head = next;
         match receiver.send_queue_head {
@@ -70,6 +119,6 @@ impl Kernel {
             Some(_) => assert!(receiver.send_queue_tail.is_some()),
         }
 
-        Some(msg_len)
+        Ok(Some(msg_len))
     }
 }
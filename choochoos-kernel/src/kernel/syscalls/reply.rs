@@ -3,7 +3,7 @@ use abi::Tid;
 use crate::util::user_slice::UserSlice;
 
 use crate::kernel::task::TaskState;
-use crate::kernel::{Kernel, ReadyQueueItem};
+use crate::kernel::Kernel;
 
 /// Syscall handler implementations.
 impl Kernel {
@@ -14,19 +14,18 @@ impl Kernel {
     ) -> Result<usize, abi::syscall::error::Reply> {
         use abi::syscall::error::Reply as Error;
 
-        let receiver = self
-            .tasks
-            .get_mut(tid.into())
-            .ok_or(Error::TidDoesNotExist)?
-            .as_mut()
-            .ok_or(Error::TidDoesNotExist)?;
+        let receiver = self.task_mut(tid).ok_or(Error::TidDoesNotExist)?;
 
         let mut reply_dst = match receiver.state {
-            TaskState::ReplyWait { reply_dst } => reply_dst,
-            _ => return Err(Error::TidIsNotReplyBlocked),
+            TaskState::ReplyWait { reply_dst, .. } => reply_dst,
+            _ => {
+                log::debug!("Reply({:?}): task is {:?}", tid, receiver.state);
+                return Err(Error::TidIsNotReplyBlocked);
+            }
         };
 
         let msg_len = reply_dst.copy_from_slice_min(reply);
+        log::trace!("replied to {:?} with {} bytes", tid, msg_len);
 
         // Return the length of the reply to the original sender.
         //
@@ -35,13 +34,9 @@ impl Kernel {
         // the top of the stack represents the syscall return word, we
         // can write directly to the stack pointer.
         receiver.inject_return_value(msg_len);
-        receiver.state = TaskState::Ready;
-        self.ready_queue
-            .push(ReadyQueueItem {
-                tid,
-                priority: receiver.priority,
-            })
-            .expect("out of space on the ready queue");
+        receiver.set_state(TaskState::Ready);
+        let priority = receiver.priority;
+        self.ready_queue.push_back(tid, priority);
 
         Ok(msg_len)
     }
@@ -3,7 +3,7 @@ use abi::Tid;
 use crate::util::user_slice::{UserSlice, UserSliceMut};
 
 use crate::kernel::task::TaskState;
-use crate::kernel::{Kernel, ReadyQueueItem};
+use crate::kernel::Kernel;
 
 /// Syscall handler implementations.
 impl Kernel {
@@ -12,16 +12,44 @@ impl Kernel {
         receiver_tid: Tid,
         msg: UserSlice<u8>,
         reply: UserSliceMut<u8>,
+    ) -> Result<(), abi::syscall::error::Send> {
+        self.send(receiver_tid, msg, reply, None)
+    }
+
+    pub fn syscall_try_send(
+        &mut self,
+        receiver_tid: Tid,
+        msg: UserSlice<u8>,
+        reply: UserSliceMut<u8>,
+    ) -> Result<(), abi::syscall::error::Send> {
+        self.send(receiver_tid, msg, reply, Some(0))
+    }
+
+    pub fn syscall_send_timeout(
+        &mut self,
+        receiver_tid: Tid,
+        msg: UserSlice<u8>,
+        reply: UserSliceMut<u8>,
+        timeout: usize,
+    ) -> Result<(), abi::syscall::error::Send> {
+        let timeout = self.effective_timeout(timeout);
+        self.send(receiver_tid, msg, reply, Some(timeout))
+    }
+
+    /// Common implementation of the `Send` variants, where `timeout` is the
+    /// number of ticks to wait for the receiver to receive the message (or
+    /// `None` to wait forever).
+    fn send(
+        &mut self,
+        receiver_tid: Tid,
+        msg: UserSlice<u8>,
+        reply: UserSliceMut<u8>,
+        timeout: Option<usize>,
     ) -> Result<(), abi::syscall::error::Send> {
         use abi::syscall::error::Send as Error;
 
-        // ensure that the receiver exists
-        if !self
-            .tasks
-            .get(receiver_tid.into())
-            .map(Option::is_some)
-            .unwrap_or(false)
-        {
+        // ensure that the receiver exists (and isn't a stale Tid)
+        if self.task(receiver_tid).is_none() {
             return Err(Error::TidDoesNotExist);
         }
 
@@ -39,13 +67,13 @@ impl Kernel {
 
         macro_rules! receiver {
             () => {
-                self.tasks[receiver_tid.into()].as_mut().unwrap()
+                self.tasks[receiver_tid.slot()].as_mut().unwrap()
             };
         }
 
         macro_rules! sender {
             () => {
-                self.tasks[sender_tid.into()].as_mut().unwrap();
+                self.tasks[sender_tid.slot()].as_mut().unwrap();
             };
         }
 
@@ -61,18 +89,40 @@ impl Kernel {
                     unsafe { *sender_tid_dst.as_mut() = sender_tid };
                 }
 
+                log::trace!(
+                    "{:?} sent {} bytes to {:?} (receive-blocked)",
+                    sender_tid,
+                    msg_len,
+                    receiver_tid
+                );
+
                 receiver.inject_return_value(msg_len);
-                receiver.state = TaskState::Ready;
-                self.ready_queue
-                    .push(ReadyQueueItem {
-                        tid: receiver_tid,
-                        priority: receiver.priority,
-                    })
-                    .expect("out of space on the ready queue");
-
-                sender!().state = TaskState::ReplyWait { reply_dst: reply }
+                receiver.set_state(TaskState::Ready);
+                self.ready_queue.push_back(receiver_tid, receiver.priority);
+                self.timers.cancel(receiver_tid);
+
+                sender!().set_state(TaskState::ReplyWait {
+                    receiver: receiver_tid,
+                    reply_dst: reply,
+                })
+            }
+            _ if timeout == Some(0) => {
+                log::trace!(
+                    "{:?} timed out sending to {:?} ({:?})",
+                    sender_tid,
+                    receiver_tid,
+                    receiver.state
+                );
+                return Err(Error::Timeout);
             }
             _ => {
+                log::trace!(
+                    "{:?} queued to send to {:?} ({:?})",
+                    sender_tid,
+                    receiver_tid,
+                    receiver.state
+                );
+
                 match receiver.send_queue_head {
                     None => {
                         assert!(receiver.send_queue_tail.is_none());
@@ -82,7 +132,7 @@ impl Kernel {
                     Some(_) => {
                         assert!(receiver.send_queue_tail.is_some());
 
-                        let old_tail = self.tasks[receiver.send_queue_tail.unwrap().into()]
+                        let old_tail = self.tasks[receiver.send_queue_tail.unwrap().slot()]
                             .as_mut()
                             .unwrap();
                         match old_tail.state {
@@ -96,11 +146,16 @@ impl Kernel {
                 }
 
                 assert!(matches!(sender!().state, TaskState::Ready));
-                sender!().state = TaskState::SendWait {
+                sender!().set_state(TaskState::SendWait {
+                    receiver: receiver_tid,
                     msg_src: msg,
                     reply_dst: reply,
                     next: None,
-                };
+                });
+
+                if let Some(ticks) = timeout {
+                    self.start_timeout(sender_tid, ticks);
+                }
             }
         }
 
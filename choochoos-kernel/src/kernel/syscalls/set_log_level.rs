@@ -0,0 +1,38 @@
+use abi::LogLevel;
+use log::LevelFilter;
+
+use crate::kernel::Kernel;
+use crate::kernel_log::FilterError;
+use crate::util::user_slice::UserSlice;
+
+/// Syscall handler implementations.
+impl Kernel {
+    pub fn syscall_set_log_level(
+        &mut self,
+        module: UserSlice<u8>,
+        level: usize,
+    ) -> Result<(), abi::syscall::error::SetLogLevel> {
+        use abi::syscall::error::SetLogLevel as Error;
+
+        let level = match LogLevel::from_usize(level).ok_or(Error::InvalidLevel)? {
+            LogLevel::Off => LevelFilter::Off,
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        };
+
+        let module = unsafe { core::slice::from_raw_parts(module.as_ptr(), module.len()) };
+        let module = core::str::from_utf8(module).map_err(|_| Error::InvalidModule)?;
+
+        log::info!("setting log level of {:?} to {}", module, level);
+
+        crate::kernel_log::log_filters()
+            .set(module, level)
+            .map_err(|e| match e {
+                FilterError::TooManyFilters => Error::TooManyFilters,
+                FilterError::ModuleTooLong => Error::ModuleTooLong,
+            })
+    }
+}
@@ -0,0 +1,26 @@
+use abi::Tid;
+
+#[cfg(not(feature = "platform-host"))]
+use crate::kernel::stack;
+use crate::kernel::Kernel;
+
+/// Syscall handler implementations.
+impl Kernel {
+    pub fn syscall_stack_usage(
+        &mut self,
+        tid: Tid,
+    ) -> Result<usize, abi::syscall::error::StackUsage> {
+        use abi::syscall::error::StackUsage as Error;
+
+        if self.task(tid).is_none() {
+            return Err(Error::TidDoesNotExist);
+        }
+
+        // tasks run on their own thread's stack, which isn't tracked
+        #[cfg(feature = "platform-host")]
+        return Err(Error::Unsupported);
+
+        #[cfg(not(feature = "platform-host"))]
+        Ok(stack::high_water_mark(tid.slot()))
+    }
+}
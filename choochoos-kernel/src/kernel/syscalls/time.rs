@@ -0,0 +1,9 @@
+use crate::kernel::timeout::current_tick;
+use crate::kernel::Kernel;
+
+/// Syscall handler implementations.
+impl Kernel {
+    pub fn syscall_time(&mut self) -> usize {
+        current_tick() as usize
+    }
+}
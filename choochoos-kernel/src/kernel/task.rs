@@ -1,8 +1,10 @@
 //! Task descriptor data structures.
 
 use core::ptr;
+use core::time::Duration;
 
-use abi::Tid;
+use abi::syscall::NUM_SYSCALLS;
+use abi::{MailboxId, Tid};
 
 use crate::util::user_slice::{UserSlice, UserSliceMut};
 
@@ -15,6 +17,8 @@ pub enum TaskState {
     Ready,
     /// Blocked - waiting to send a message.
     SendWait {
+        /// The task being sent to.
+        receiver: Tid,
         /// The message being sent.
         msg_src: UserSlice<u8>,
         /// The reply buffer.
@@ -31,11 +35,67 @@ pub enum TaskState {
     },
     /// Blocked - waiting to receive a reply message.
     ReplyWait {
+        /// The task which received the message (and is expected to reply).
+        receiver: Tid,
         /// The reply buffer.
         reply_dst: UserSliceMut<u8>,
     },
     /// Blocked - waiting for an event to occur.
     EventWait,
+    /// Blocked - waiting for a `Delay` / `DelayUntil` to elapse.
+    DelayWait,
+    /// Blocked - waiting to receive a message from a mailbox.
+    MailboxWait {
+        /// The mailbox being received from.
+        mailbox: MailboxId,
+        /// The receive buffer.
+        recv_dst: UserSliceMut<u8>,
+        /// Pointer to the next task waiting on the mailbox.
+        next: Option<Tid>,
+    },
+}
+
+/// Total time spent in each [`TaskState`].
+#[derive(Debug, Default, Copy, Clone)]
+pub struct StateTimes {
+    pub ready: Duration,
+    pub send_wait: Duration,
+    pub recv_wait: Duration,
+    pub reply_wait: Duration,
+    pub event_wait: Duration,
+    pub delay_wait: Duration,
+    pub mailbox_wait: Duration,
+}
+
+impl StateTimes {
+    /// Return a mutable reference to the time spent in `state`.
+    pub fn of_mut(&mut self, state: &TaskState) -> &mut Duration {
+        match state {
+            TaskState::Ready => &mut self.ready,
+            TaskState::SendWait { .. } => &mut self.send_wait,
+            TaskState::RecvWait { .. } => &mut self.recv_wait,
+            TaskState::ReplyWait { .. } => &mut self.reply_wait,
+            TaskState::EventWait => &mut self.event_wait,
+            TaskState::DelayWait => &mut self.delay_wait,
+            TaskState::MailboxWait { .. } => &mut self.mailbox_wait,
+        }
+    }
+}
+
+/// Per-task performance counters.
+#[derive(Debug)]
+pub struct TaskPerf {
+    /// Number of times the task has been scheduled.
+    pub context_switches: u32,
+    /// Number of syscalls issued, indexed by `SyscallNo`.
+    pub syscalls: [u32; NUM_SYSCALLS],
+    /// Time spent running (excluding time spent handling interrupts).
+    pub cpu_time: Duration,
+    /// Time spent in each state. Does _not_ include time spent in the current
+    /// state.
+    pub state_time: StateTimes,
+    /// When the task entered its current state.
+    pub state_since: Duration,
 }
 
 /// Task descriptor.
@@ -50,10 +110,15 @@ pub struct TaskDescriptor {
     pub sp: ptr::NonNull<UserStack>,
 
     /// The Tasks's execution state + state-specific associated data.
+    ///
+    /// State transitions should go through [`TaskDescriptor::set_state`].
     pub state: TaskState,
 
     pub send_queue_head: Option<Tid>,
     pub send_queue_tail: Option<Tid>,
+
+    /// Performance counters.
+    pub perf: TaskPerf,
 }
 
 impl TaskDescriptor {
@@ -70,9 +135,25 @@ impl TaskDescriptor {
             state: TaskState::Ready,
             send_queue_head: None,
             send_queue_tail: None,
+            perf: TaskPerf {
+                context_switches: 0,
+                syscalls: [0; NUM_SYSCALLS],
+                cpu_time: Duration::new(0, 0),
+                state_time: StateTimes::default(),
+                state_since: crate::platform::time::uptime(),
+            },
         }
     }
 
+    /// Transition the task into a new state, accounting for the time spent in
+    /// the previous state.
+    pub fn set_state(&mut self, state: TaskState) {
+        let now = crate::platform::time::uptime();
+        *self.perf.state_time.of_mut(&self.state) += now.saturating_sub(self.perf.state_since);
+        self.perf.state_since = now;
+        self.state = state;
+    }
+
     /// Forcefully inject a return value into the task's stack.
     ///
     /// Currently only supports values with size equal to
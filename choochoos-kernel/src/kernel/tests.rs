@@ -0,0 +1,1483 @@
+//! Unit tests which drive the kernel through [`Kernel::step`], using scripted
+//! tasks (see `arch::fake`).
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, MutexGuard};
+use std::vec::Vec;
+
+use abi::syscall::SyscallNo;
+use abi::{MailboxId, Tid};
+
+use crate::platform::interrupts::STDIN_EVENT_ID;
+
+use super::*;
+
+/// Every kernel instance shares the same user stack area (and mailbox pool),
+/// so tests can't run concurrently.
+static KERNEL_LOCK: Mutex<()> = Mutex::new(());
+
+fn lock() -> MutexGuard<'static, ()> {
+    // a failing test shouldn't fail every subsequent test
+    KERNEL_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Never actually run (see `arch::fake`).
+unsafe extern "C" fn task() {}
+
+/// Activates tasks by having them make a pre-determined sequence of syscalls.
+#[derive(Default)]
+struct Scripted {
+    syscalls: HashMap<Tid, VecDeque<(SyscallNo, Vec<usize>)>>,
+}
+
+impl Scripted {
+    /// Queue up a syscall to be made the next time `tid` is activated.
+    fn push(&mut self, tid: Tid, no: SyscallNo, args: &[usize]) {
+        let syscalls = self.syscalls.entry(tid).or_default();
+        syscalls.push_back((no, args.to_vec()));
+    }
+}
+
+impl Activate for Scripted {
+    fn activate(
+        &mut self,
+        kernel: &mut Kernel,
+        tid: Tid,
+        mut sp: ptr::NonNull<UserStack>,
+    ) -> ptr::NonNull<UserStack> {
+        let (no, args) = self
+            .syscalls
+            .get_mut(&tid)
+            .and_then(VecDeque::pop_front)
+            .unwrap_or_else(|| panic!("{:?} was activated without a scripted syscall", tid));
+
+        let stack = unsafe { sp.as_mut() };
+        stack.syscall_no = no as u8;
+        stack.regs[..args.len()].copy_from_slice(&args);
+        dispatch::dispatch_syscall(kernel, no, stack);
+
+        sp
+    }
+}
+
+fn spawn(kernel: &mut Kernel, priority: isize) -> Tid {
+    kernel.syscall_create(priority, Some(task)).unwrap()
+}
+
+fn state(kernel: &Kernel, tid: Tid) -> &TaskState {
+    &kernel.task(tid).expect("task no longer exists").state
+}
+
+/// Returns the value in `tid`'s syscall return value register.
+fn ret(kernel: &Kernel, tid: Tid) -> usize {
+    unsafe { kernel.task(tid).unwrap().sp.as_ref().regs[0] }
+}
+
+#[test]
+fn send_before_receive() {
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let sender = spawn(&mut kernel, 1);
+    let receiver = spawn(&mut kernel, 1);
+
+    let msg = *b"ping";
+    let mut reply = [0u8; 8];
+    script.push(
+        sender,
+        SyscallNo::Send,
+        &[
+            receiver.into(),
+            msg.as_ptr() as usize,
+            msg.len(),
+            reply.as_mut_ptr() as usize,
+            reply.len(),
+        ],
+    );
+    assert_eq!(kernel.step(&mut script), Step::Activated(sender));
+    assert!(matches!(state(&kernel, sender), TaskState::SendWait { .. }));
+
+    let mut sender_tid = Tid::from(0);
+    let mut buf = [0u8; 8];
+    script.push(
+        receiver,
+        SyscallNo::Receive,
+        &[
+            &mut sender_tid as *mut Tid as usize,
+            buf.as_mut_ptr() as usize,
+            buf.len(),
+        ],
+    );
+    assert_eq!(kernel.step(&mut script), Step::Activated(receiver));
+    assert!(matches!(state(&kernel, receiver), TaskState::Ready));
+    assert!(
+        matches!(state(&kernel, sender), TaskState::ReplyWait { receiver: r, .. } if *r == receiver)
+    );
+    assert_eq!(ret(&kernel, receiver), msg.len());
+    assert_eq!(sender_tid, sender);
+    assert_eq!(&buf[..msg.len()], &msg);
+
+    let pong = *b"pong!";
+    script.push(
+        receiver,
+        SyscallNo::Reply,
+        &[sender.into(), pong.as_ptr() as usize, pong.len()],
+    );
+    assert_eq!(kernel.step(&mut script), Step::Activated(receiver));
+    assert!(matches!(state(&kernel, receiver), TaskState::Ready));
+    assert!(matches!(state(&kernel, sender), TaskState::Ready));
+    assert_eq!(ret(&kernel, receiver), pong.len());
+    assert_eq!(ret(&kernel, sender), pong.len());
+    assert_eq!(&reply[..pong.len()], &pong);
+
+    // the sender was unblocked before the receiver was re-queued
+    script.push(sender, SyscallNo::Exit, &[]);
+    script.push(receiver, SyscallNo::Exit, &[]);
+    assert_eq!(kernel.step(&mut script), Step::Activated(sender));
+    assert_eq!(kernel.step(&mut script), Step::Activated(receiver));
+    assert!(kernel.task(sender).is_none() && kernel.task(receiver).is_none());
+    assert_eq!(kernel.step(&mut script), Step::Done);
+}
+
+#[test]
+fn receive_before_send() {
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let receiver = spawn(&mut kernel, 2);
+    let sender = spawn(&mut kernel, 1);
+
+    let mut sender_tid = Tid::from(0);
+    let mut buf = [0u8; 2];
+    script.push(
+        receiver,
+        SyscallNo::Receive,
+        &[
+            &mut sender_tid as *mut Tid as usize,
+            buf.as_mut_ptr() as usize,
+            buf.len(),
+        ],
+    );
+    assert_eq!(kernel.step(&mut script), Step::Activated(receiver));
+    assert!(matches!(
+        state(&kernel, receiver),
+        TaskState::RecvWait { .. }
+    ));
+
+    // messages are truncated to fit the receive buffer
+    let msg = *b"hello";
+    let mut reply = [0u8; 1];
+    script.push(
+        sender,
+        SyscallNo::Send,
+        &[
+            receiver.into(),
+            msg.as_ptr() as usize,
+            msg.len(),
+            reply.as_mut_ptr() as usize,
+            reply.len(),
+        ],
+    );
+    assert_eq!(kernel.step(&mut script), Step::Activated(sender));
+    assert!(matches!(
+        state(&kernel, sender),
+        TaskState::ReplyWait { .. }
+    ));
+    assert!(matches!(state(&kernel, receiver), TaskState::Ready));
+    assert_eq!(ret(&kernel, receiver), buf.len());
+    assert_eq!(sender_tid, sender);
+    assert_eq!(buf, *b"he");
+
+    let ack = *b"k";
+    script.push(
+        receiver,
+        SyscallNo::Reply,
+        &[sender.into(), ack.as_ptr() as usize, ack.len()],
+    );
+    assert_eq!(kernel.step(&mut script), Step::Activated(receiver));
+    assert!(matches!(state(&kernel, sender), TaskState::Ready));
+    assert_eq!(ret(&kernel, sender), ack.len());
+    assert_eq!(reply, ack);
+}
+
+#[test]
+fn yield_round_robin() {
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let low = spawn(&mut kernel, 1);
+    let tids = [
+        spawn(&mut kernel, 2),
+        spawn(&mut kernel, 2),
+        spawn(&mut kernel, 2),
+    ];
+
+    // tasks of equal priority take turns, ahead of any lower priority tasks
+    for _ in 0..2 {
+        for &tid in &tids {
+            run(&mut kernel, &mut script, tid, SyscallNo::Yield, &[]);
+        }
+    }
+    for &tid in &tids {
+        run(&mut kernel, &mut script, tid, SyscallNo::Exit, &[]);
+    }
+    run(&mut kernel, &mut script, low, SyscallNo::Exit, &[]);
+    assert_eq!(kernel.step(&mut script), Step::Done);
+}
+
+#[test]
+fn await_event_blocks_until_event() {
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let tid = spawn(&mut kernel, 1);
+
+    script.push(tid, SyscallNo::AwaitEvent, &[STDIN_EVENT_ID]);
+    assert_eq!(kernel.step(&mut script), Step::Activated(tid));
+    assert!(matches!(state(&kernel, tid), TaskState::EventWait));
+
+    // the kernel idles (instead of exiting) while a task awaits an event
+    assert_eq!(kernel.step(&mut script), Step::Idle);
+
+    kernel.handle_event(STDIN_EVENT_ID, b'x' as usize);
+    assert!(matches!(state(&kernel, tid), TaskState::Ready));
+    assert_eq!(ret(&kernel, tid), b'x' as usize);
+
+    script.push(tid, SyscallNo::Exit, &[]);
+    assert_eq!(kernel.step(&mut script), Step::Activated(tid));
+    assert_eq!(kernel.step(&mut script), Step::Done);
+}
+
+#[test]
+fn await_event_returns_pending_data() {
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let tid = spawn(&mut kernel, 1);
+
+    // nobody is waiting yet, so the data is held onto
+    kernel.handle_event(STDIN_EVENT_ID, b'a' as usize);
+    kernel.handle_event(STDIN_EVENT_ID, b'b' as usize);
+
+    script.push(tid, SyscallNo::AwaitEvent, &[STDIN_EVENT_ID]);
+    assert_eq!(kernel.step(&mut script), Step::Activated(tid));
+    assert!(matches!(state(&kernel, tid), TaskState::Ready));
+    assert_eq!(ret(&kernel, tid), b'a' as usize);
+
+    script.push(tid, SyscallNo::AwaitEvent, &[STDIN_EVENT_ID]);
+    assert_eq!(kernel.step(&mut script), Step::Activated(tid));
+    assert!(matches!(state(&kernel, tid), TaskState::Ready));
+    assert_eq!(ret(&kernel, tid), b'b' as usize);
+
+    script.push(tid, SyscallNo::Exit, &[]);
+    assert_eq!(kernel.step(&mut script), Step::Activated(tid));
+    assert_eq!(kernel.step(&mut script), Step::Done);
+}
+
+#[test]
+fn await_event_reports_dropped_data_once() {
+    use abi::syscall::error::AwaitEvent as Error;
+
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let tid = spawn(&mut kernel, 1);
+
+    // the last two events don't fit
+    for data in 0..MAX_PENDING_EVENT_DATA + 2 {
+        kernel.handle_event(STDIN_EVENT_ID, data);
+    }
+    assert_eq!(kernel.dropped_events, 2);
+
+    run(
+        &mut kernel,
+        &mut script,
+        tid,
+        SyscallNo::AwaitEvent,
+        &[STDIN_EVENT_ID],
+    );
+    assert_eq!(
+        ret(&kernel, tid) as isize,
+        Error::CorruptedVolatileData as isize
+    );
+
+    // ...followed by the data which was queued, in order
+    for data in 0..MAX_PENDING_EVENT_DATA {
+        run(
+            &mut kernel,
+            &mut script,
+            tid,
+            SyscallNo::AwaitEvent,
+            &[STDIN_EVENT_ID],
+        );
+        assert!(matches!(state(&kernel, tid), TaskState::Ready));
+        assert_eq!(ret(&kernel, tid), data);
+    }
+
+    // ...after which the task waits for the next event
+    run(
+        &mut kernel,
+        &mut script,
+        tid,
+        SyscallNo::AwaitEvent,
+        &[STDIN_EVENT_ID],
+    );
+    assert!(matches!(state(&kernel, tid), TaskState::EventWait));
+    kernel.handle_event(STDIN_EVENT_ID, 42);
+    assert_eq!(ret(&kernel, tid), 42);
+    assert_eq!(kernel.dropped_events, 2);
+}
+
+#[test]
+fn await_event_rejects_invalid_event_id() {
+    use abi::syscall::error::AwaitEvent as Error;
+
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let tid = spawn(&mut kernel, 1);
+
+    script.push(tid, SyscallNo::AwaitEvent, &[usize::MAX]);
+    assert_eq!(kernel.step(&mut script), Step::Activated(tid));
+    assert!(matches!(state(&kernel, tid), TaskState::Ready));
+    assert_eq!(ret(&kernel, tid) as isize, Error::InvalidEventId as isize);
+}
+
+#[test]
+fn await_event_rejects_too_many_waiters() {
+    use abi::syscall::error::AwaitEvent as Error;
+
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let mut waiters = 0;
+    let rejected = loop {
+        assert!(waiters < MAX_TASKS, "every task was able to wait");
+
+        let tid = spawn(&mut kernel, 1);
+        script.push(tid, SyscallNo::AwaitEvent, &[STDIN_EVENT_ID]);
+        assert_eq!(kernel.step(&mut script), Step::Activated(tid));
+        match state(&kernel, tid) {
+            TaskState::EventWait => waiters += 1,
+            _ => break tid,
+        }
+    };
+
+    assert!(matches!(state(&kernel, rejected), TaskState::Ready));
+    assert_eq!(
+        ret(&kernel, rejected) as isize,
+        Error::TooManyWaiters as isize
+    );
+    match kernel.event_queue.get(&STDIN_EVENT_ID) {
+        Some(EventQueueItem::BlockedTids(tids)) => {
+            assert_eq!(tids.len(), waiters);
+            assert!(!tids.contains(&rejected));
+        }
+        _ => panic!("waiters are missing from the event queue"),
+    }
+}
+
+/// Queue up a syscall for `tid`, and check that it's the next task to run.
+fn run(kernel: &mut Kernel, script: &mut Scripted, tid: Tid, no: SyscallNo, args: &[usize]) {
+    script.push(tid, no, args);
+    assert_eq!(kernel.step(script), Step::Activated(tid));
+}
+
+/// Arguments to one of the `Send` variants (sans timeout).
+fn send_args(receiver: Tid, reply: &mut [u8]) -> Vec<usize> {
+    let msg = b"msg";
+    std::vec![
+        receiver.into(),
+        msg.as_ptr() as usize,
+        msg.len(),
+        reply.as_mut_ptr() as usize,
+        reply.len(),
+    ]
+}
+
+fn send(kernel: &mut Kernel, script: &mut Scripted, tid: Tid, receiver: Tid, reply: &mut [u8]) {
+    let args = send_args(receiver, reply);
+    run(kernel, script, tid, SyscallNo::Send, &args);
+}
+
+fn receive(kernel: &mut Kernel, script: &mut Scripted, tid: Tid, sender_tid: &mut Tid) {
+    let args = [sender_tid as *mut Tid as usize, 0, 0];
+    run(kernel, script, tid, SyscallNo::Receive, &args);
+}
+
+/// Block `tid` in the `DelayWait` state.
+fn delay(kernel: &mut Kernel, script: &mut Scripted, tid: Tid) {
+    #[cfg(feature = "kernel-clock")]
+    run(kernel, script, tid, SyscallNo::Delay, &[1000]);
+
+    // without a kernel clock `Delay` fails outright, so block the task by hand
+    #[cfg(not(feature = "kernel-clock"))]
+    {
+        let _ = script;
+        kernel.tasks[tid.slot()].as_mut().unwrap().state = TaskState::DelayWait;
+        kernel
+            .ready_queue
+            .remove(tid, kernel.task(tid).unwrap().priority);
+        kernel.timers.insert(tid, u64::MAX);
+    }
+
+    assert!(matches!(state(kernel, tid), TaskState::DelayWait));
+}
+
+fn mailbox_create(kernel: &mut Kernel, script: &mut Scripted, tid: Tid) -> MailboxId {
+    run(kernel, script, tid, SyscallNo::MailboxCreate, &[4, 4]);
+    MailboxId::from(ret(kernel, tid))
+}
+
+fn mailbox_receive(
+    kernel: &mut Kernel,
+    script: &mut Scripted,
+    tid: Tid,
+    mailbox: MailboxId,
+    buf: &mut [u8],
+) {
+    let args = [mailbox.into(), buf.as_mut_ptr() as usize, buf.len()];
+    run(kernel, script, tid, SyscallNo::MailboxReceive, &args);
+}
+
+/// The next task in `tid`'s queue (i.e: a send queue, or a mailbox's waiters).
+fn next_of(kernel: &Kernel, tid: Tid) -> Option<Tid> {
+    match *state(kernel, tid) {
+        TaskState::SendWait { next, .. } | TaskState::MailboxWait { next, .. } => next,
+        ref state => panic!("{:?} isn't queued ({:?})", tid, state),
+    }
+}
+
+#[test]
+fn stale_tid_after_slot_reuse() {
+    use abi::syscall::error::{Destroy, Reply, Send};
+
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let old = spawn(&mut kernel, 1);
+    run(&mut kernel, &mut script, old, SyscallNo::Exit, &[]);
+    let new = spawn(&mut kernel, 1);
+    assert_eq!(new.slot(), old.slot());
+    assert_ne!(new, old);
+    assert!(kernel.task(old).is_none());
+
+    let peer = spawn(&mut kernel, 2);
+    let mut reply = [0u8; 4];
+    send(&mut kernel, &mut script, peer, old, &mut reply);
+    assert_eq!(ret(&kernel, peer) as isize, Send::TidDoesNotExist as isize);
+    let args = [old.into(), reply.as_ptr() as usize, reply.len()];
+    run(&mut kernel, &mut script, peer, SyscallNo::Reply, &args);
+    assert_eq!(ret(&kernel, peer) as isize, Reply::TidDoesNotExist as isize);
+    run(
+        &mut kernel,
+        &mut script,
+        peer,
+        SyscallNo::Destroy,
+        &[old.into()],
+    );
+    assert_eq!(
+        ret(&kernel, peer) as isize,
+        Destroy::TidDoesNotExist as isize
+    );
+
+    // none of which affected the slot's new task...
+    assert!(matches!(state(&kernel, new), TaskState::Ready));
+
+    // ...which can still be referred to using its own Tid
+    let args = [new.into(), reply.as_ptr() as usize, reply.len()];
+    run(&mut kernel, &mut script, peer, SyscallNo::Reply, &args);
+    assert_eq!(
+        ret(&kernel, peer) as isize,
+        Reply::TidIsNotReplyBlocked as isize
+    );
+    run(
+        &mut kernel,
+        &mut script,
+        peer,
+        SyscallNo::Destroy,
+        &[new.into()],
+    );
+    assert_eq!(ret(&kernel, peer), 0);
+    assert!(kernel.task(new).is_none());
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Reap {
+    Exit,
+    Destroy,
+}
+
+/// Reap `target`, either by having it exit (in which case it must be the
+/// next task to run), or by having a short-lived high-priority task destroy
+/// it.
+fn reap(kernel: &mut Kernel, script: &mut Scripted, target: Tid, how: Reap) {
+    match how {
+        Reap::Exit => run(kernel, script, target, SyscallNo::Exit, &[]),
+        Reap::Destroy => {
+            let killer = spawn(kernel, abi::NUM_PRIORITIES as isize - 1);
+            run(kernel, script, killer, SyscallNo::Destroy, &[target.into()]);
+            assert_eq!(ret(kernel, killer), 0);
+            run(kernel, script, killer, SyscallNo::Exit, &[]);
+        }
+    }
+    assert!(kernel.task(target).is_none());
+}
+
+#[test]
+fn reap_ready() {
+    for &how in &[Reap::Exit, Reap::Destroy] {
+        let _lock = lock();
+        let mut kernel = Kernel::new();
+        let mut script = Scripted::default();
+
+        let target = spawn(&mut kernel, 1);
+        let peer = spawn(&mut kernel, 1);
+
+        reap(&mut kernel, &mut script, target, how);
+        assert!(!kernel.ready_queue.remove(target, 1), "{:?}", how);
+
+        // the peer is unaffected
+        assert!(matches!(state(&kernel, peer), TaskState::Ready));
+        run(&mut kernel, &mut script, peer, SyscallNo::Exit, &[]);
+        assert_eq!(kernel.step(&mut script), Step::Done);
+    }
+}
+
+#[test]
+fn destroy_send_wait() {
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    // the receiver never gets to run
+    let receiver = spawn(&mut kernel, 0);
+    let first = spawn(&mut kernel, 1);
+    let target = spawn(&mut kernel, 1);
+    let last = spawn(&mut kernel, 1);
+
+    let mut reply = [0u8; 4];
+    for &tid in &[first, target, last] {
+        send(&mut kernel, &mut script, tid, receiver, &mut reply);
+        assert!(matches!(state(&kernel, tid), TaskState::SendWait { .. }));
+    }
+
+    reap(&mut kernel, &mut script, target, Reap::Destroy);
+
+    let receiver_task = kernel.task(receiver).unwrap();
+    assert_eq!(receiver_task.send_queue_head, Some(first));
+    assert_eq!(receiver_task.send_queue_tail, Some(last));
+    assert_eq!(next_of(&kernel, first), Some(last));
+    assert_eq!(next_of(&kernel, last), None);
+
+    // ...and is still there once the rest of the queue is destroyed
+    reap(&mut kernel, &mut script, first, Reap::Destroy);
+    reap(&mut kernel, &mut script, last, Reap::Destroy);
+    let receiver_task = kernel.task(receiver).unwrap();
+    assert_eq!(receiver_task.send_queue_head, None);
+    assert_eq!(receiver_task.send_queue_tail, None);
+}
+
+#[test]
+fn destroy_recv_wait() {
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let target = spawn(&mut kernel, 1);
+    let mut sender_tid = Tid::from(0);
+    receive(&mut kernel, &mut script, target, &mut sender_tid);
+    assert!(matches!(state(&kernel, target), TaskState::RecvWait { .. }));
+
+    reap(&mut kernel, &mut script, target, Reap::Destroy);
+    assert_eq!(kernel.step(&mut script), Step::Done);
+}
+
+#[test]
+fn destroy_reply_wait() {
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let receiver = spawn(&mut kernel, 0);
+    let target = spawn(&mut kernel, 1);
+
+    let mut reply = [0u8; 4];
+    send(&mut kernel, &mut script, target, receiver, &mut reply);
+    let mut sender_tid = Tid::from(0);
+    receive(&mut kernel, &mut script, receiver, &mut sender_tid);
+    assert!(matches!(
+        state(&kernel, target),
+        TaskState::ReplyWait { .. }
+    ));
+
+    reap(&mut kernel, &mut script, target, Reap::Destroy);
+
+    // the receiver finds out when it tries to reply
+    let args = [target.into(), reply.as_ptr() as usize, reply.len()];
+    run(&mut kernel, &mut script, receiver, SyscallNo::Reply, &args);
+    assert_eq!(
+        ret(&kernel, receiver) as isize,
+        abi::syscall::error::Reply::TidDoesNotExist as isize
+    );
+}
+
+#[test]
+fn destroy_event_wait() {
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let first = spawn(&mut kernel, 1);
+    let target = spawn(&mut kernel, 1);
+    let last = spawn(&mut kernel, 1);
+    for &tid in &[first, target, last] {
+        run(
+            &mut kernel,
+            &mut script,
+            tid,
+            SyscallNo::AwaitEvent,
+            &[STDIN_EVENT_ID],
+        );
+    }
+
+    reap(&mut kernel, &mut script, target, Reap::Destroy);
+
+    match kernel.event_queue.get(&STDIN_EVENT_ID) {
+        Some(EventQueueItem::BlockedTids(tids)) => assert_eq!(&tids[..], &[first, last]),
+        _ => panic!("waiters are missing from the event queue"),
+    }
+
+    // once the last waiter is gone, so is the event's entry
+    reap(&mut kernel, &mut script, first, Reap::Destroy);
+    reap(&mut kernel, &mut script, last, Reap::Destroy);
+    assert!(kernel.event_queue.get(&STDIN_EVENT_ID).is_none());
+}
+
+#[test]
+fn destroy_delay_wait() {
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let target = spawn(&mut kernel, 1);
+    delay(&mut kernel, &mut script, target);
+    assert!(kernel.timers.contains(target));
+
+    reap(&mut kernel, &mut script, target, Reap::Destroy);
+    assert!(kernel.timers.is_empty());
+}
+
+#[test]
+fn destroy_mailbox_wait() {
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let owner = spawn(&mut kernel, 2);
+    let first = spawn(&mut kernel, 1);
+    let target = spawn(&mut kernel, 1);
+    let last = spawn(&mut kernel, 1);
+
+    let mailbox = mailbox_create(&mut kernel, &mut script, owner);
+    // keep the owner out of the way
+    delay(&mut kernel, &mut script, owner);
+
+    let mut buf = [0u8; 4];
+    for &tid in &[first, target, last] {
+        mailbox_receive(&mut kernel, &mut script, tid, mailbox, &mut buf);
+        assert!(matches!(state(&kernel, tid), TaskState::MailboxWait { .. }));
+    }
+
+    reap(&mut kernel, &mut script, target, Reap::Destroy);
+
+    let m = kernel.mailbox_mut(mailbox).unwrap();
+    assert_eq!((m.waiters_head, m.waiters_tail), (Some(first), Some(last)));
+    assert_eq!(next_of(&kernel, first), Some(last));
+    assert_eq!(next_of(&kernel, last), None);
+
+    reap(&mut kernel, &mut script, first, Reap::Destroy);
+    reap(&mut kernel, &mut script, last, Reap::Destroy);
+    let m = kernel.mailbox_mut(mailbox).unwrap();
+    assert_eq!((m.waiters_head, m.waiters_tail), (None, None));
+}
+
+#[test]
+fn reap_fails_blocked_senders() {
+    use abi::syscall::error::Send as Error;
+
+    for &how in &[Reap::Exit, Reap::Destroy] {
+        let _lock = lock();
+        let mut kernel = Kernel::new();
+        let mut script = Scripted::default();
+
+        let target = spawn(&mut kernel, 0);
+        let replier = spawn(&mut kernel, 1);
+        let sender = spawn(&mut kernel, 1);
+
+        let mut reply = [0u8; 4];
+        send(&mut kernel, &mut script, replier, target, &mut reply);
+        send(&mut kernel, &mut script, sender, target, &mut reply);
+
+        // `replier` is received, and waits for a reply, whereas `sender` is
+        // never received
+        let mut sender_tid = Tid::from(0);
+        receive(&mut kernel, &mut script, target, &mut sender_tid);
+        assert_eq!(sender_tid, replier);
+        assert!(matches!(
+            state(&kernel, replier),
+            TaskState::ReplyWait { .. }
+        ));
+        assert!(matches!(state(&kernel, sender), TaskState::SendWait { .. }));
+
+        reap(&mut kernel, &mut script, target, how);
+
+        assert!(matches!(state(&kernel, sender), TaskState::Ready));
+        assert_eq!(ret(&kernel, sender) as isize, Error::CouldNotSSR as isize);
+        assert!(matches!(state(&kernel, replier), TaskState::Ready));
+        assert_eq!(
+            ret(&kernel, replier) as isize,
+            Error::ReceiverTerminated as isize
+        );
+    }
+}
+
+#[test]
+fn reap_fails_mailbox_waiters() {
+    use abi::syscall::error::MailboxReceive as Error;
+
+    for &how in &[Reap::Exit, Reap::Destroy] {
+        let _lock = lock();
+        let mut kernel = Kernel::new();
+        let mut script = Scripted::default();
+
+        let target = spawn(&mut kernel, 1);
+        let mailbox = mailbox_create(&mut kernel, &mut script, target);
+
+        let waiter = spawn(&mut kernel, 2);
+        let mut buf = [0u8; 4];
+        mailbox_receive(&mut kernel, &mut script, waiter, mailbox, &mut buf);
+        assert!(matches!(
+            state(&kernel, waiter),
+            TaskState::MailboxWait { .. }
+        ));
+
+        reap(&mut kernel, &mut script, target, how);
+
+        assert!(matches!(state(&kernel, waiter), TaskState::Ready));
+        assert_eq!(
+            ret(&kernel, waiter) as isize,
+            Error::MailboxDestroyed as isize
+        );
+        assert!(kernel.mailbox_mut(mailbox).is_none());
+    }
+}
+
+/// Returns the number of deadlocks reported in the kernel log since the last
+/// call.
+fn deadlock_reports() -> usize {
+    let log = crate::kernel_log::log_buffer();
+    let (older, newer) = log.undrained();
+    let output = [older, newer].concat();
+    log.consume(usize::MAX);
+
+    let output = std::string::String::from_utf8_lossy(&output);
+    output.matches("deadlock detected").count()
+}
+
+#[test]
+fn deadlock_send_send() {
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+    deadlock_reports();
+
+    let a = spawn(&mut kernel, 1);
+    let b = spawn(&mut kernel, 1);
+    let mut reply = [0u8; 4];
+    send(&mut kernel, &mut script, a, b, &mut reply);
+    send(&mut kernel, &mut script, b, a, &mut reply);
+
+    // the cycle is reported once the ready queue empties...
+    assert_eq!(kernel.step(&mut script), Step::Done);
+    assert_eq!(deadlock_reports(), 1);
+    assert_eq!(kernel.deadlocked[a.slot()], Some(a));
+    assert_eq!(kernel.deadlocked[b.slot()], Some(b));
+
+    // ...but not every time it empties
+    assert_eq!(kernel.step(&mut script), Step::Done);
+    assert_eq!(deadlock_reports(), 0);
+
+    // a new cycle is reported alongside the old one
+    let c = spawn(&mut kernel, 1);
+    let d = spawn(&mut kernel, 1);
+    send(&mut kernel, &mut script, c, d, &mut reply);
+    send(&mut kernel, &mut script, d, c, &mut reply);
+    assert_eq!(kernel.step(&mut script), Step::Done);
+    assert_eq!(deadlock_reports(), 1);
+}
+
+#[test]
+fn deadlock_send_reply_wait() {
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+    deadlock_reports();
+
+    let b = spawn(&mut kernel, 1);
+    let a = spawn(&mut kernel, 1);
+    let bystander = spawn(&mut kernel, 1);
+    let detector = spawn(&mut kernel, 0);
+
+    // `a` waits for `b` to reply, while `b` waits for `a` to receive
+    let mut sender_tid = Tid::from(0);
+    let mut reply = [0u8; 4];
+    receive(&mut kernel, &mut script, b, &mut sender_tid);
+    send(&mut kernel, &mut script, a, b, &mut reply);
+    assert!(matches!(state(&kernel, a), TaskState::ReplyWait { .. }));
+    // ...and `bystander` is stuck behind the cycle, without being part of it
+    send(&mut kernel, &mut script, bystander, a, &mut reply);
+    send(&mut kernel, &mut script, b, a, &mut reply);
+    assert!(matches!(state(&kernel, b), TaskState::SendWait { .. }));
+
+    let mut tids = [Tid::from(usize::MAX); 3];
+    let args = [tids.as_mut_ptr() as usize, tids.len()];
+    run(
+        &mut kernel,
+        &mut script,
+        detector,
+        SyscallNo::DetectDeadlocks,
+        &args,
+    );
+    assert_eq!(ret(&kernel, detector), 2);
+    let mut found = [tids[0], tids[1]];
+    found.sort();
+    let mut expected = [a, b];
+    expected.sort();
+    assert_eq!(found, expected);
+    assert_eq!(tids[2], Tid::from(usize::MAX));
+    assert_eq!(deadlock_reports(), 1);
+
+    // the count includes any Tids which didn't fit
+    let mut tids = [Tid::from(usize::MAX); 1];
+    let args = [tids.as_mut_ptr() as usize, tids.len()];
+    run(
+        &mut kernel,
+        &mut script,
+        detector,
+        SyscallNo::DetectDeadlocks,
+        &args,
+    );
+    assert_eq!(ret(&kernel, detector), 2);
+    assert!(tids[0] == a || tids[0] == b);
+    deadlock_reports();
+
+    run(&mut kernel, &mut script, detector, SyscallNo::Exit, &[]);
+    assert_eq!(kernel.step(&mut script), Step::Done);
+    assert_eq!(deadlock_reports(), 1);
+    assert_eq!(kernel.deadlocked[bystander.slot()], None);
+}
+
+/// Advance the kernel clock by `ticks`, and deliver a single kernel clock tick
+/// (as if the kernel had been too busy to handle the ones in between).
+#[cfg(feature = "kernel-clock")]
+fn tick(kernel: &mut Kernel, ticks: u32) {
+    crate::platform::time::advance(TICK * ticks);
+    kernel.handle_event(crate::platform::interrupts::TIMER_EVENT_ID, 0);
+}
+
+fn send_timeout(
+    kernel: &mut Kernel,
+    script: &mut Scripted,
+    tid: Tid,
+    receiver: Tid,
+    reply: &mut [u8],
+    timeout: usize,
+) {
+    let mut args = send_args(receiver, reply);
+    args.push(timeout);
+    run(kernel, script, tid, SyscallNo::SendTimeout, &args);
+}
+
+fn receive_timeout(
+    kernel: &mut Kernel,
+    script: &mut Scripted,
+    tid: Tid,
+    sender_tid: &mut Tid,
+    timeout: usize,
+) {
+    let args = [sender_tid as *mut Tid as usize, 0, 0, timeout];
+    run(kernel, script, tid, SyscallNo::ReceiveTimeout, &args);
+}
+
+#[test]
+fn try_send_requires_waiting_receiver() {
+    use abi::syscall::error::Send as Error;
+
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let sender = spawn(&mut kernel, 1);
+    let receiver = spawn(&mut kernel, 1);
+
+    let mut reply = [0u8; 4];
+    let args = send_args(receiver, &mut reply);
+    run(&mut kernel, &mut script, sender, SyscallNo::TrySend, &args);
+    assert!(matches!(state(&kernel, sender), TaskState::Ready));
+    assert_eq!(ret(&kernel, sender) as isize, Error::Timeout as isize);
+    assert_eq!(kernel.task(receiver).unwrap().send_queue_head, None);
+    assert!(kernel.timers.is_empty());
+
+    let mut sender_tid = Tid::from(0);
+    receive(&mut kernel, &mut script, receiver, &mut sender_tid);
+    run(&mut kernel, &mut script, sender, SyscallNo::TrySend, &args);
+    assert!(matches!(
+        state(&kernel, sender),
+        TaskState::ReplyWait { .. }
+    ));
+    assert!(matches!(state(&kernel, receiver), TaskState::Ready));
+    assert_eq!(sender_tid, sender);
+}
+
+#[test]
+#[cfg(not(feature = "kernel-clock"))]
+fn timeouts_without_kernel_clock() {
+    use abi::syscall::error::{Receive, Send};
+
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let receiver = spawn(&mut kernel, 1);
+    let sender = spawn(&mut kernel, 1);
+
+    // the timeouts would never expire, so they're treated as 0
+    let mut sender_tid = Tid::from(0);
+    receive_timeout(&mut kernel, &mut script, receiver, &mut sender_tid, 10);
+    assert!(matches!(state(&kernel, receiver), TaskState::Ready));
+    assert_eq!(ret(&kernel, receiver) as isize, Receive::Timeout as isize);
+
+    let mut reply = [0u8; 4];
+    send_timeout(&mut kernel, &mut script, sender, receiver, &mut reply, 10);
+    assert!(matches!(state(&kernel, sender), TaskState::Ready));
+    assert_eq!(ret(&kernel, sender) as isize, Send::Timeout as isize);
+    assert!(kernel.timers.is_empty());
+}
+
+#[test]
+#[cfg(feature = "kernel-clock")]
+fn send_timeout_expires() {
+    use abi::syscall::error::Send as Error;
+
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    // the receiver never gets to run
+    let receiver = spawn(&mut kernel, 0);
+    let sender = spawn(&mut kernel, 1);
+    let other = spawn(&mut kernel, 1);
+
+    let mut reply = [0u8; 4];
+    send_timeout(&mut kernel, &mut script, sender, receiver, &mut reply, 5);
+    send(&mut kernel, &mut script, other, receiver, &mut reply);
+    assert!(matches!(state(&kernel, sender), TaskState::SendWait { .. }));
+    assert!(kernel.timers.contains(sender));
+
+    tick(&mut kernel, 4);
+    assert!(matches!(state(&kernel, sender), TaskState::SendWait { .. }));
+
+    tick(&mut kernel, 1);
+    assert!(matches!(state(&kernel, sender), TaskState::Ready));
+    assert_eq!(ret(&kernel, sender) as isize, Error::Timeout as isize);
+    assert!(kernel.timers.is_empty());
+
+    // ...leaving the rest of the send queue intact
+    let receiver_task = kernel.task(receiver).unwrap();
+    assert_eq!(receiver_task.send_queue_head, Some(other));
+    assert_eq!(receiver_task.send_queue_tail, Some(other));
+}
+
+#[test]
+#[cfg(feature = "kernel-clock")]
+fn send_timeout_satisfied() {
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let sender = spawn(&mut kernel, 2);
+    let receiver = spawn(&mut kernel, 1);
+
+    let mut reply = [0u8; 4];
+    send_timeout(&mut kernel, &mut script, sender, receiver, &mut reply, 5);
+    assert!(kernel.timers.contains(sender));
+
+    // receiving the message cancels the timeout...
+    let mut sender_tid = Tid::from(0);
+    receive(&mut kernel, &mut script, receiver, &mut sender_tid);
+    assert_eq!(sender_tid, sender);
+    assert!(!kernel.timers.contains(sender));
+
+    // ...so the sender waits for the reply for as long as it takes
+    tick(&mut kernel, 10);
+    assert!(matches!(
+        state(&kernel, sender),
+        TaskState::ReplyWait { .. }
+    ));
+
+    let args = [sender.into(), reply.as_ptr() as usize, 0];
+    run(&mut kernel, &mut script, receiver, SyscallNo::Reply, &args);
+    assert!(matches!(state(&kernel, sender), TaskState::Ready));
+    assert_eq!(ret(&kernel, sender), 0);
+}
+
+#[test]
+#[cfg(feature = "kernel-clock")]
+fn receive_timeout_expires() {
+    use abi::syscall::error::Receive as Error;
+
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let receiver = spawn(&mut kernel, 1);
+
+    let mut sender_tid = Tid::from(0);
+    receive_timeout(&mut kernel, &mut script, receiver, &mut sender_tid, 3);
+    assert!(matches!(
+        state(&kernel, receiver),
+        TaskState::RecvWait { .. }
+    ));
+    assert_eq!(kernel.step(&mut script), Step::Idle);
+
+    tick(&mut kernel, 2);
+    assert!(matches!(
+        state(&kernel, receiver),
+        TaskState::RecvWait { .. }
+    ));
+
+    tick(&mut kernel, 1);
+    assert!(matches!(state(&kernel, receiver), TaskState::Ready));
+    assert_eq!(ret(&kernel, receiver) as isize, Error::Timeout as isize);
+    assert!(kernel.timers.is_empty());
+}
+
+#[test]
+#[cfg(feature = "kernel-clock")]
+fn receive_timeout_satisfied() {
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let receiver = spawn(&mut kernel, 2);
+    let sender = spawn(&mut kernel, 1);
+
+    let mut sender_tid = Tid::from(0);
+    receive_timeout(&mut kernel, &mut script, receiver, &mut sender_tid, 3);
+    assert!(kernel.timers.contains(receiver));
+
+    let mut reply = [0u8; 4];
+    send(&mut kernel, &mut script, sender, receiver, &mut reply);
+    assert!(matches!(state(&kernel, receiver), TaskState::Ready));
+    assert_eq!(sender_tid, sender);
+    assert!(kernel.timers.is_empty());
+
+    // the receiver isn't woken up (again) once the deadline passes
+    tick(&mut kernel, 5);
+    assert!(matches!(state(&kernel, receiver), TaskState::Ready));
+    assert!(matches!(
+        state(&kernel, sender),
+        TaskState::ReplyWait { .. }
+    ));
+}
+
+#[test]
+fn timer_cancel() {
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let tid = spawn(&mut kernel, 1);
+    let other = spawn(&mut kernel, 1);
+    delay(&mut kernel, &mut script, tid);
+
+    // neither a task without a timer, nor a stale Tid of a task with one, can
+    // cancel anything
+    assert!(!kernel.timers.cancel(other));
+    let stale = Tid::new(tid.slot(), tid.generation() + 1);
+    assert!(!kernel.timers.cancel(stale));
+    assert!(kernel.timers.contains(tid));
+
+    assert!(kernel.timers.cancel(tid));
+    assert!(!kernel.timers.cancel(tid));
+    assert!(kernel.timers.is_empty());
+}
+
+#[test]
+#[cfg(feature = "kernel-clock")]
+fn timer_wheel_wraps() {
+    use timer_wheel::WHEEL_SLOTS;
+
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    // both timers land in the same bucket, a full revolution apart
+    let late = spawn(&mut kernel, 1);
+    let early = spawn(&mut kernel, 1);
+    let ticks = WHEEL_SLOTS + 2;
+    run(&mut kernel, &mut script, late, SyscallNo::Delay, &[ticks]);
+    run(&mut kernel, &mut script, early, SyscallNo::Delay, &[2]);
+
+    tick(&mut kernel, 1);
+    tick(&mut kernel, 1);
+    assert!(matches!(state(&kernel, early), TaskState::Ready));
+    assert!(matches!(state(&kernel, late), TaskState::DelayWait));
+
+    for _ in 0..WHEEL_SLOTS - 1 {
+        tick(&mut kernel, 1);
+    }
+    assert!(matches!(state(&kernel, late), TaskState::DelayWait));
+    tick(&mut kernel, 1);
+    assert!(matches!(state(&kernel, late), TaskState::Ready));
+    assert_eq!(ret(&kernel, late), ticks);
+}
+
+#[test]
+#[cfg(feature = "kernel-clock")]
+fn timer_wheel_catches_up() {
+    use timer_wheel::WHEEL_SLOTS;
+
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let tids = [
+        spawn(&mut kernel, 1),
+        spawn(&mut kernel, 1),
+        spawn(&mut kernel, 1),
+    ];
+    let gap = 10 * WHEEL_SLOTS;
+    for (&tid, &ticks) in tids.iter().zip(&[3, gap - 1, gap + 3]) {
+        run(&mut kernel, &mut script, tid, SyscallNo::Delay, &[ticks]);
+    }
+
+    // a single tick after a long gap expires everything that's due...
+    tick(&mut kernel, gap as u32);
+    assert!(matches!(state(&kernel, tids[0]), TaskState::Ready));
+    assert!(matches!(state(&kernel, tids[1]), TaskState::Ready));
+    assert_eq!(ret(&kernel, tids[0]), gap);
+    assert_eq!(ret(&kernel, tids[1]), gap);
+    assert!(matches!(state(&kernel, tids[2]), TaskState::DelayWait));
+
+    // ...and nothing else
+    tick(&mut kernel, 2);
+    assert!(matches!(state(&kernel, tids[2]), TaskState::DelayWait));
+    tick(&mut kernel, 1);
+    assert!(matches!(state(&kernel, tids[2]), TaskState::Ready));
+    assert!(kernel.timers.is_empty());
+}
+
+#[test]
+fn delay_rejects_negative_ticks() {
+    use abi::syscall::error::Delay as Error;
+
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let tid = spawn(&mut kernel, 1);
+    run(
+        &mut kernel,
+        &mut script,
+        tid,
+        SyscallNo::Delay,
+        &[-1isize as usize],
+    );
+    assert!(matches!(state(&kernel, tid), TaskState::Ready));
+    assert_eq!(ret(&kernel, tid) as isize, Error::NegativeDelay as isize);
+    assert!(kernel.timers.is_empty());
+}
+
+#[test]
+#[cfg(feature = "kernel-clock")]
+fn delay_without_blocking() {
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let tid = spawn(&mut kernel, 1);
+    tick(&mut kernel, 10);
+    run(&mut kernel, &mut script, tid, SyscallNo::Time, &[]);
+    assert_eq!(ret(&kernel, tid), 10);
+
+    // none of these block, and they all return the current tick
+    for &(no, arg) in &[
+        (SyscallNo::Delay, 0),
+        (SyscallNo::DelayUntil, 3),
+        (SyscallNo::DelayUntil, 10),
+    ] {
+        run(&mut kernel, &mut script, tid, no, &[arg]);
+        assert!(matches!(state(&kernel, tid), TaskState::Ready));
+        assert_eq!(ret(&kernel, tid), 10);
+        assert!(kernel.timers.is_empty());
+    }
+}
+
+#[test]
+#[cfg(feature = "kernel-clock")]
+fn delayed_tasks_wake_in_deadline_order() {
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let tids: Vec<Tid> = (0..4).map(|_| spawn(&mut kernel, 1)).collect();
+    let deadlines = [4, 1, 3, 2];
+    // at tick 0, `Delay` and `DelayUntil` are interchangeable
+    for (&tid, &deadline) in tids.iter().zip(&deadlines) {
+        if deadline % 2 == 0 {
+            run(&mut kernel, &mut script, tid, SyscallNo::Delay, &[deadline]);
+        } else {
+            run(
+                &mut kernel,
+                &mut script,
+                tid,
+                SyscallNo::DelayUntil,
+                &[deadline],
+            );
+        }
+    }
+    assert_eq!(kernel.step(&mut script), Step::Idle);
+
+    let by_deadline = |deadline| tids[deadlines.iter().position(|&d| d == deadline).unwrap()];
+
+    tick(&mut kernel, 1);
+    assert!(matches!(state(&kernel, by_deadline(1)), TaskState::Ready));
+    for &deadline in &[2, 3, 4] {
+        let tid = by_deadline(deadline);
+        assert!(matches!(state(&kernel, tid), TaskState::DelayWait));
+    }
+
+    // the rest expire at once, but are still woken up in order
+    tick(&mut kernel, 3);
+    for deadline in 1..=4 {
+        let tid = by_deadline(deadline);
+        assert_eq!(ret(&kernel, tid), if deadline == 1 { 1 } else { 4 });
+        run(&mut kernel, &mut script, tid, SyscallNo::Exit, &[]);
+    }
+    assert_eq!(kernel.step(&mut script), Step::Done);
+}
+
+fn mailbox_post(
+    kernel: &mut Kernel,
+    script: &mut Scripted,
+    tid: Tid,
+    mailbox: MailboxId,
+    msg: &[u8],
+) {
+    let args = [mailbox.into(), msg.as_ptr() as usize, msg.len()];
+    run(kernel, script, tid, SyscallNo::MailboxPost, &args);
+}
+
+fn mailbox_try_receive(
+    kernel: &mut Kernel,
+    script: &mut Scripted,
+    tid: Tid,
+    mailbox: MailboxId,
+    buf: &mut [u8],
+) {
+    let args = [mailbox.into(), buf.as_mut_ptr() as usize, buf.len()];
+    run(kernel, script, tid, SyscallNo::MailboxTryReceive, &args);
+}
+
+#[test]
+fn mailbox_post_then_receive() {
+    use abi::syscall::error::MailboxReceive as Error;
+
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let tid = spawn(&mut kernel, 1);
+    let mailbox = mailbox_create(&mut kernel, &mut script, tid);
+
+    for &msg in &[&b"ab"[..], b"cde"] {
+        mailbox_post(&mut kernel, &mut script, tid, mailbox, msg);
+        assert_eq!(ret(&kernel, tid), 0);
+    }
+
+    // messages are received in the order they were posted, without blocking
+    let mut buf = [0u8; 4];
+    mailbox_receive(&mut kernel, &mut script, tid, mailbox, &mut buf);
+    assert!(matches!(state(&kernel, tid), TaskState::Ready));
+    assert_eq!(ret(&kernel, tid), 2);
+    assert_eq!(&buf[..2], b"ab");
+
+    mailbox_try_receive(&mut kernel, &mut script, tid, mailbox, &mut buf);
+    assert_eq!(ret(&kernel, tid), 3);
+    assert_eq!(&buf[..3], b"cde");
+
+    mailbox_try_receive(&mut kernel, &mut script, tid, mailbox, &mut buf);
+    assert_eq!(ret(&kernel, tid) as isize, Error::Empty as isize);
+}
+
+#[test]
+fn mailbox_post_errors() {
+    use abi::syscall::error::MailboxPost as Error;
+
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let tid = spawn(&mut kernel, 1);
+    let mailbox = mailbox_create(&mut kernel, &mut script, tid);
+
+    mailbox_post(&mut kernel, &mut script, tid, mailbox, b"abcde");
+    assert_eq!(ret(&kernel, tid) as isize, Error::MessageTooLarge as isize);
+
+    for i in 0..4 {
+        mailbox_post(&mut kernel, &mut script, tid, mailbox, &[i]);
+        assert_eq!(ret(&kernel, tid), 0);
+    }
+    mailbox_post(&mut kernel, &mut script, tid, mailbox, b"full");
+    assert_eq!(ret(&kernel, tid) as isize, Error::Full as isize);
+
+    // neither failed post was queued
+    let mut buf = [0u8; 4];
+    for i in 0..4 {
+        mailbox_try_receive(&mut kernel, &mut script, tid, mailbox, &mut buf);
+        assert_eq!(ret(&kernel, tid), 1);
+        assert_eq!(buf[0], i);
+    }
+    assert!(kernel.mailbox_mut(mailbox).unwrap().is_empty());
+}
+
+#[test]
+fn mailbox_pool_exhaustion() {
+    use abi::syscall::error::MailboxCreate as Error;
+
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let tid = spawn(&mut kernel, 1);
+    let create = |kernel: &mut Kernel, script: &mut Scripted, slot_size, count| {
+        run(
+            kernel,
+            script,
+            tid,
+            SyscallNo::MailboxCreate,
+            &[slot_size, count],
+        );
+        ret(kernel, tid) as isize
+    };
+
+    // leave just enough room for a single 8 byte message
+    let small = Mailbox::storage_size(8, 1).unwrap();
+    let big_size = MAILBOX_POOL_SIZE - small;
+    // (each slot is prefixed with the length of its message)
+    let slot_size = big_size - core::mem::size_of::<usize>();
+    assert_eq!(Mailbox::storage_size(slot_size, 1), Some(big_size));
+    let big = create(&mut kernel, &mut script, slot_size, 1);
+    assert_eq!(kernel.alloc_mailbox_storage(small), Some(big_size));
+    assert_eq!(kernel.alloc_mailbox_storage(small + 1), None);
+
+    let res = create(&mut kernel, &mut script, 8, 2);
+    assert_eq!(res, Error::OutOfMemory as isize);
+    let rest = create(&mut kernel, &mut script, 8, 1);
+    assert!(rest >= 0);
+    assert_eq!(kernel.alloc_mailbox_storage(1), None);
+
+    // destroying a mailbox returns its storage to the pool
+    run(
+        &mut kernel,
+        &mut script,
+        tid,
+        SyscallNo::MailboxDestroy,
+        &[big as usize],
+    );
+    assert_eq!(ret(&kernel, tid), 0);
+    assert_eq!(kernel.alloc_mailbox_storage(big_size), Some(0));
+    assert_eq!(kernel.alloc_mailbox_storage(big_size + 1), None);
+    let reused = create(&mut kernel, &mut script, 8, 2);
+    assert!(reused >= 0);
+
+    for &id in &[rest, reused] {
+        run(
+            &mut kernel,
+            &mut script,
+            tid,
+            SyscallNo::MailboxDestroy,
+            &[id as usize],
+        );
+    }
+    assert_eq!(kernel.alloc_mailbox_storage(MAILBOX_POOL_SIZE), Some(0));
+
+    // mailbox slots run out independently of the pool
+    for _ in 0..MAX_MAILBOXES {
+        assert!(create(&mut kernel, &mut script, 8, 1) >= 0);
+    }
+    let res = create(&mut kernel, &mut script, 8, 1);
+    assert_eq!(res, Error::OutOfMailboxes as isize);
+}
+
+#[test]
+fn stale_mailbox_id() {
+    use abi::syscall::error::{MailboxDestroy, MailboxPost, MailboxReceive};
+
+    let _lock = lock();
+    let mut kernel = Kernel::new();
+    let mut script = Scripted::default();
+
+    let tid = spawn(&mut kernel, 1);
+    let old = mailbox_create(&mut kernel, &mut script, tid);
+    run(
+        &mut kernel,
+        &mut script,
+        tid,
+        SyscallNo::MailboxDestroy,
+        &[old.into()],
+    );
+    let new = mailbox_create(&mut kernel, &mut script, tid);
+    assert_eq!(new.slot(), old.slot());
+    assert_ne!(new, old);
+
+    mailbox_post(&mut kernel, &mut script, tid, old, b"old");
+    assert_eq!(
+        ret(&kernel, tid) as isize,
+        MailboxPost::MailboxDoesNotExist as isize
+    );
+    let mut buf = [0u8; 4];
+    mailbox_receive(&mut kernel, &mut script, tid, old, &mut buf);
+    assert!(matches!(state(&kernel, tid), TaskState::Ready));
+    assert_eq!(
+        ret(&kernel, tid) as isize,
+        MailboxReceive::MailboxDoesNotExist as isize
+    );
+    run(
+        &mut kernel,
+        &mut script,
+        tid,
+        SyscallNo::MailboxDestroy,
+        &[old.into()],
+    );
+    assert_eq!(
+        ret(&kernel, tid) as isize,
+        MailboxDestroy::MailboxDoesNotExist as isize
+    );
+
+    // ...leaving the new mailbox untouched
+    assert!(kernel.mailbox_mut(new).unwrap().is_empty());
+    mailbox_post(&mut kernel, &mut script, tid, new, b"new");
+    assert_eq!(ret(&kernel, tid), 0);
+}
@@ -0,0 +1,99 @@
+//! Timeouts for blocking syscalls (e.g: `SendTimeout` and `ReceiveTimeout`),
+//! and the kernel clock's tick counter (see `Time`, `Delay`, and
+//! `DelayUntil`).
+//!
+//! Timeouts are measured in kernel clock ticks, which are only delivered when
+//! the kernel is built with the `kernel-clock` feature. Without it, timers
+//! would never expire, so every timeout is treated as 0 instead (i.e: the
+//! syscall fails immediately if it would otherwise block), and delays aren't
+//! supported at all.
+
+use core::time::Duration;
+
+use abi::Tid;
+
+use super::task::TaskState;
+use super::Kernel;
+
+/// Length of a kernel clock tick.
+pub const TICK: Duration = Duration::from_millis(10);
+
+/// Returns the current kernel clock tick (i.e: the number of ticks since the
+/// platform was initialized).
+///
+/// Derived from the platform's uptime, and therefore available with or without
+/// the `kernel-clock` feature.
+pub(super) fn current_tick() -> u64 {
+    (crate::platform::time::uptime().as_micros() / TICK.as_micros()) as u64
+}
+
+impl Kernel {
+    /// Clamp a syscall's timeout (in ticks) to something which the kernel can
+    /// actually wait for.
+    pub(super) fn effective_timeout(&self, ticks: usize) -> usize {
+        #[cfg(feature = "kernel-clock")]
+        {
+            ticks
+        }
+
+        #[cfg(not(feature = "kernel-clock"))]
+        {
+            if ticks != 0 {
+                log::debug!(
+                    "{:?} requested a timeout of {} ticks, but the kernel clock is disabled",
+                    self.current_tid,
+                    ticks
+                );
+            }
+            0
+        }
+    }
+
+    /// Start a timeout for `tid`, which has just blocked, and must be unblocked
+    /// if it's still blocked `ticks` ticks from now.
+    ///
+    /// `ticks` must have been clamped by [`Kernel::effective_timeout`].
+    pub(super) fn start_timeout(&mut self, tid: Tid, ticks: usize) {
+        self.timers.insert(tid, current_tick() + ticks as u64);
+    }
+
+    /// Unblock any tasks whose timeouts (or delays) have expired.
+    ///
+    /// Called on every kernel clock tick.
+    #[cfg(feature = "kernel-clock")]
+    pub(super) fn expire_timeouts(&mut self) {
+        let now = current_tick();
+        while let Some(tid) = self.timers.pop_expired(now) {
+            self.timeout_expired(tid);
+        }
+    }
+
+    #[cfg_attr(not(feature = "kernel-clock"), allow(dead_code))]
+    fn timeout_expired(&mut self, tid: Tid) {
+        use abi::syscall::error::{Receive as ReceiveError, Send as SendError};
+
+        let task = self.tasks[tid.slot()].as_mut().unwrap();
+        match task.state {
+            TaskState::SendWait { receiver, .. } => {
+                log::trace!("{:?} timed out sending to {:?}", tid, receiver);
+                self.unlink_sender(receiver, tid);
+                self.fail_sender(tid, SendError::Timeout);
+            }
+            TaskState::RecvWait { .. } => {
+                log::trace!("{:?} timed out waiting to receive", tid);
+                task.inject_return_value(ReceiveError::Timeout);
+                task.set_state(TaskState::Ready);
+                let priority = task.priority;
+                self.ready_queue.push_back(tid, priority);
+            }
+            TaskState::DelayWait => {
+                log::trace!("{:?} finished its delay", tid);
+                task.inject_return_value(current_tick() as usize);
+                task.set_state(TaskState::Ready);
+                let priority = task.priority;
+                self.ready_queue.push_back(tid, priority);
+            }
+            _ => panic!("timeout expired for {:?} in state {:?}", tid, task.state),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! The kernel's timer wheel, used to implement timeouts and delays.
+
+use abi::Tid;
+
+use super::MAX_TASKS;
+
+/// Number of buckets in the wheel.
+pub(super) const WHEEL_SLOTS: usize = 64;
+
+#[derive(Debug, Copy, Clone)]
+struct Timer {
+    tid: Tid,
+    /// The tick at which the timer expires.
+    #[cfg_attr(not(feature = "kernel-clock"), allow(dead_code))]
+    deadline: u64,
+    /// The bucket containing the timer.
+    bucket: usize,
+    /// The previous timer in the timer's bucket (indexed by slot).
+    prev: Option<usize>,
+    /// The next timer in the timer's bucket (indexed by slot).
+    next: Option<usize>,
+}
+
+/// Hashed timer wheel, holding at most one timer per task.
+///
+/// Each timer is placed in the bucket corresponding to its deadline (modulo
+/// the size of the wheel), where each bucket is an intrusive doubly-linked list
+/// (threaded through the `timers` array). As such, inserting and cancelling
+/// timers is O(1), and each tick only has to look at a single bucket.
+pub struct TimerWheel {
+    /// The most recent tick whose bucket has been checked for expired timers.
+    now: u64,
+    /// The head of each bucket's list.
+    buckets: [Option<usize>; WHEEL_SLOTS],
+    /// Each task's pending timer (indexed by slot).
+    timers: [Option<Timer>; MAX_TASKS],
+    /// Number of pending timers.
+    len: usize,
+}
+
+impl TimerWheel {
+    /// Create a new, empty TimerWheel.
+    pub fn new() -> TimerWheel {
+        TimerWheel {
+            now: 0,
+            buckets: [None; WHEEL_SLOTS],
+            timers: [None; MAX_TASKS],
+            len: 0,
+        }
+    }
+
+    fn bucket(tick: u64) -> usize {
+        (tick % WHEEL_SLOTS as u64) as usize
+    }
+
+    /// Check if there are no pending timers.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Check if `tid` has a pending timer.
+    pub fn contains(&self, tid: Tid) -> bool {
+        matches!(self.timers[tid.slot()], Some(timer) if timer.tid == tid)
+    }
+
+    /// Start a timer for `tid` which expires at the `deadline` tick, replacing
+    /// the task's existing timer (if any).
+    ///
+    /// Timers with deadlines that have already passed expire on the next call
+    /// to [`TimerWheel::pop_expired`].
+    pub fn insert(&mut self, tid: Tid, deadline: u64) {
+        self.remove(tid.slot());
+
+        let bucket = Self::bucket(deadline.max(self.now + 1));
+        let next = self.buckets[bucket];
+        if let Some(next) = next {
+            self.timers[next].as_mut().unwrap().prev = Some(tid.slot());
+        }
+        self.buckets[bucket] = Some(tid.slot());
+        self.timers[tid.slot()] = Some(Timer {
+            tid,
+            deadline,
+            bucket,
+            prev: None,
+            next,
+        });
+        self.len += 1;
+    }
+
+    /// Cancel `tid`'s pending timer, returning `true` if it had one.
+    pub fn cancel(&mut self, tid: Tid) -> bool {
+        if !self.contains(tid) {
+            return false;
+        }
+        self.remove(tid.slot());
+        true
+    }
+
+    fn remove(&mut self, slot: usize) {
+        let timer = match self.timers[slot].take() {
+            Some(timer) => timer,
+            None => return,
+        };
+
+        match timer.prev {
+            Some(prev) => self.timers[prev].as_mut().unwrap().next = timer.next,
+            None => self.buckets[timer.bucket] = timer.next,
+        }
+        if let Some(next) = timer.next {
+            self.timers[next].as_mut().unwrap().prev = timer.prev;
+        }
+        self.len -= 1;
+    }
+
+    /// Advance the wheel to the `now` tick, removing and returning one of the
+    /// timers which expired along the way (if any).
+    ///
+    /// Should be called repeatedly until it returns `None`.
+    #[cfg_attr(not(feature = "kernel-clock"), allow(dead_code))]
+    pub fn pop_expired(&mut self, now: u64) -> Option<Tid> {
+        // every bucket is checked once per revolution, so when catching up on
+        // lots of missed ticks, only the last revolution needs to be checked.
+        if now > self.now + WHEEL_SLOTS as u64 {
+            self.now = now - WHEEL_SLOTS as u64;
+        }
+
+        while self.now < now {
+            let tick = self.now + 1;
+
+            let mut cur = self.buckets[Self::bucket(tick)];
+            while let Some(slot) = cur {
+                let timer = self.timers[slot].unwrap();
+                if timer.deadline <= now {
+                    self.remove(slot);
+                    return Some(timer.tid);
+                }
+                cur = timer.next;
+            }
+
+            self.now = tick;
+        }
+
+        None
+    }
+}
@@ -0,0 +1,177 @@
+//! Binary tracing of scheduling, syscall, and interrupt events.
+//!
+//! When the `trace` feature is enabled, each event is recorded as a fixed-size
+//! [`Record`] in a ring buffer, which can be drained by userspace using the
+//! `ReadTrace` syscall. Whatever hasn't been drained is dumped when the kernel
+//! shuts down, and can be converted into a Chrome / Perfetto trace using the
+//! `choochoos-trace` tool.
+//!
+//! Without the `trace` feature, the recording functions are no-ops.
+
+use abi::syscall::SyscallNo;
+use abi::trace::{EventKind, State, NO_TID};
+use abi::Tid;
+
+use super::arch::UserStack;
+use super::task::TaskState;
+
+fn raw_tid(tid: Option<Tid>) -> u32 {
+    tid.map_or(NO_TID, |tid| tid.into() as u32)
+}
+
+#[cfg(feature = "trace")]
+mod buffer {
+    use abi::trace::Record;
+
+    use crate::kernel::{MAX_TASKS, TRACE_RECORDS};
+    use crate::util::ring::Ring;
+
+    /// The most recent trace records, along with the state needed to record
+    /// syscall returns.
+    pub struct TraceBuffer {
+        pub records: Ring<Record, TRACE_RECORDS>,
+        /// Syscall each task is blocked in (if any), indexed by slot.
+        pub(super) pending_syscall: [Option<u8>; MAX_TASKS],
+    }
+
+    static mut TRACE_BUFFER: TraceBuffer = TraceBuffer {
+        records: Ring::new(Record::EMPTY),
+        pending_syscall: [None; MAX_TASKS],
+    };
+
+    /// Returns the kernel trace buffer.
+    pub fn trace_buffer() -> &'static mut TraceBuffer {
+        unsafe { &mut TRACE_BUFFER }
+    }
+}
+
+#[cfg(feature = "trace")]
+pub use buffer::trace_buffer;
+
+/// Append a record to the trace buffer.
+#[inline]
+fn record(kind: EventKind, tid: Option<Tid>, syscall_no: u8, data: [u32; 4]) {
+    #[cfg(feature = "trace")]
+    {
+        trace_buffer().records.push(abi::trace::Record {
+            timestamp_us: crate::platform::time::uptime().as_micros() as u64,
+            tid: raw_tid(tid),
+            kind: kind as u8,
+            syscall_no,
+            reserved: 0,
+            data,
+        });
+    }
+
+    #[cfg(not(feature = "trace"))]
+    {
+        let _ = (kind, tid, syscall_no, data);
+    }
+}
+
+/// Record a task being activated, along with the return value of the syscall
+/// it's resuming from (if any).
+pub fn switch_in(tid: Tid, stack: &UserStack) {
+    #[cfg(feature = "trace")]
+    {
+        if let Some(no) = trace_buffer().pending_syscall[tid.slot()].take() {
+            let ret = stack.regs[0] as u32;
+            record(EventKind::SyscallReturn, Some(tid), no, [ret, 0, 0, 0]);
+        }
+    }
+
+    #[cfg(not(feature = "trace"))]
+    {
+        let _ = stack;
+    }
+
+    record(EventKind::SwitchIn, Some(tid), 0, [0; 4]);
+}
+
+/// Record a task no longer running, and the state it was left in (`None` if
+/// the task exited).
+pub fn switch_out(tid: Tid, state: Option<&TaskState>) {
+    let (state, blocked_on) = match state {
+        None => (State::Exited, None),
+        Some(TaskState::Ready) => (State::Ready, None),
+        Some(TaskState::SendWait { receiver, .. }) => (State::SendWait, Some(*receiver)),
+        Some(TaskState::RecvWait { .. }) => (State::RecvWait, None),
+        Some(TaskState::ReplyWait { receiver, .. }) => (State::ReplyWait, Some(*receiver)),
+        Some(TaskState::EventWait) => (State::EventWait, None),
+        Some(TaskState::DelayWait) => (State::DelayWait, None),
+        Some(TaskState::MailboxWait { .. }) => (State::MailboxWait, None),
+    };
+    let data = [state as u32, raw_tid(blocked_on), 0, 0];
+    record(EventKind::SwitchOut, Some(tid), 0, data);
+}
+
+/// Record a syscall (and its first four arguments).
+pub fn syscall(tid: Tid, syscall_no: SyscallNo, stack: &mut UserStack) {
+    #[cfg(feature = "trace")]
+    {
+        let mut args = stack.args();
+        let mut arg = || unsafe { args.extract::<usize>() as u32 };
+        let data = [arg(), arg(), arg(), arg()];
+        record(EventKind::Syscall, Some(tid), syscall_no as u8, data);
+
+        trace_buffer().pending_syscall[tid.slot()] = Some(syscall_no as u8);
+    }
+
+    #[cfg(not(feature = "trace"))]
+    {
+        let _ = (tid, syscall_no, stack);
+    }
+}
+
+/// Record an interrupt (and the task it interrupted, if any).
+pub fn irq(tid: Option<Tid>, event_id: usize, volatile_data: usize) {
+    let data = [event_id as u32, volatile_data as u32, 0, 0];
+    record(EventKind::Irq, tid, 0, data);
+}
+
+/// Record a task being created.
+pub fn task_create(tid: Tid, parent_tid: Option<Tid>, priority: isize) {
+    let data = [raw_tid(parent_tid), priority as u32, 0, 0];
+    record(EventKind::TaskCreate, Some(tid), 0, data);
+}
+
+/// Record a task exiting (or being destroyed / killed).
+pub fn task_exit(tid: Tid) {
+    #[cfg(feature = "trace")]
+    {
+        trace_buffer().pending_syscall[tid.slot()] = None;
+    }
+
+    record(EventKind::TaskExit, Some(tid), 0, [0; 4]);
+}
+
+/// Busy-wait print (and drain) any undrained trace records, as hex.
+pub fn dump() {
+    #[cfg(feature = "trace")]
+    {
+        use crate::bwkprintln;
+
+        let records = &mut trace_buffer().records;
+        let (older, newer) = records.undrained();
+        let len = older.len() + newer.len();
+
+        bwkprintln!("{} ({} records)", abi::trace::DUMP_BEGIN, len);
+        for record in older.iter().chain(newer.iter()) {
+            bwkprintln!("{}", Hex(&record.to_bytes()));
+        }
+        bwkprintln!("{}", abi::trace::DUMP_END);
+
+        records.consume(len);
+    }
+}
+
+/// Formats a byte slice as lowercase hex.
+#[cfg(feature = "trace")]
+struct Hex<'a>(&'a [u8]);
+
+#[cfg(feature = "trace")]
+impl core::fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{:02x}", b))
+    }
+}
@@ -0,0 +1,69 @@
+//! Bounded queue of undelivered volatile data.
+
+use super::MAX_PENDING_EVENT_DATA;
+
+/// Ring buffer of volatile data from interrupts that no task was waiting for,
+/// in the order the interrupts occurred.
+///
+/// If the ring fills up, subsequent data is dropped, and the queue is marked
+/// as having overflowed.
+#[derive(Debug)]
+pub struct VolatileDataQueue {
+    buf: [usize; MAX_PENDING_EVENT_DATA],
+    head: usize,
+    len: usize,
+    overflowed: bool,
+}
+
+impl VolatileDataQueue {
+    /// Create a new, empty VolatileDataQueue.
+    pub fn new() -> VolatileDataQueue {
+        VolatileDataQueue {
+            buf: [0; MAX_PENDING_EVENT_DATA],
+            head: 0,
+            len: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Enqueue some volatile data, returning `false` (and discarding the data)
+    /// if the queue is full.
+    pub fn push(&mut self, data: usize) -> bool {
+        if self.len == MAX_PENDING_EVENT_DATA {
+            self.overflowed = true;
+            return false;
+        }
+
+        self.buf[(self.head + self.len) % MAX_PENDING_EVENT_DATA] = data;
+        self.len += 1;
+        true
+    }
+
+    /// Dequeue the oldest volatile data.
+    pub fn pop(&mut self) -> Option<usize> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let data = self.buf[self.head];
+        self.head = (self.head + 1) % MAX_PENDING_EVENT_DATA;
+        self.len -= 1;
+        Some(data)
+    }
+
+    /// Return the number of queued items.
+    #[cfg_attr(not(feature = "monitor"), allow(dead_code))]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if the queue is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Check (and clear) whether any data has been dropped since the last call.
+    pub fn take_overflowed(&mut self) -> bool {
+        core::mem::replace(&mut self.overflowed, false)
+    }
+}
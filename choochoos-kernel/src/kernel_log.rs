@@ -1,65 +1,266 @@
-//! Kernel logging macros.
+//! Kernel logging macros, and the kernel's [`log`] backend.
 //!
-//! TODO: use an in-memory buffer for the kernel log instead of immediately
-//! dumping via the BusyWaitLogger
+//! Log messages are appended to an in-memory ring buffer (instead of being
+//! immediately busy-wait printed), which can be drained by a userspace logging
+//! task using the `ReadLog` syscall. Whatever hasn't been drained is printed
+//! when the kernel shuts down, and the most recent log output is replayed as
+//! part of a crash dump.
+//!
+//! The `kernel-log-busy-wait` feature additionally busy-wait prints each
+//! message as it's logged (at the expense of real-time behavior).
+//!
+//! Diagnostic messages should be logged using the [`log`] crate's macros (e.g:
+//! `log::trace!`), which are filtered on a per-module basis. Initial filters
+//! are set at build time via `CHOOCHOOS_LOG` (see `build.rs`), and can be
+//! adjusted at runtime using the `SetLogLevel` syscall. `kprintln!` is reserved
+//! for messages which should always be logged.
 
-// TODO: hook into `log` crate
-/// Logs messages when the `kdebug` feature is enabled.
-#[doc(cfg(feature = "kdebug"))]
-#[macro_export]
-macro_rules! kdebug {
-    () => { kdebug!("") };
-    ($fmt:literal) => { kdebug!($fmt,) };
-    ($fmt:literal, $($arg:tt)*) => {
-        #[cfg(feature = "kdebug")]
-        #[allow(unused_unsafe)]
-        {
-            $crate::bwkprintln!(
-                "{} {}",
-                format_args!(
-                    // foreground color = yellow
-                    concat!("\x1b[33m", "[kdebug][tid={}][{}:{}]", "\x1b[0m"),
-                    unsafe {
-                        match $crate::KERNEL {
-                            Some(ref kernel) => {
-                                kernel.current_tid()
-                                    .map(|tid| tid.into() as isize)
-                                    .unwrap_or(-1)
-                            }
-                            None => core::hint::unreachable_unchecked(),
-                        }
-                    },
-                    file!(),
-                    line!(),
-                ),
-                format_args!($fmt, $($arg)*)
-            );
+use core::fmt::{self, Write};
+
+use heapless::{String, Vec};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::kernel::{
+    KERNEL_LOG_SIZE, LOG_LEVEL, LOG_MODULE_LEVELS, MAX_LOG_FILTERS, MAX_LOG_MODULE_LEN,
+};
+use crate::util::ring::Ring;
+
+/// Ring buffer containing the most recent kernel log output.
+pub type LogBuffer = Ring<u8, KERNEL_LOG_SIZE>;
+
+static mut LOG_BUFFER: LogBuffer = LogBuffer::new(0);
+
+/// Returns the kernel log buffer.
+pub fn log_buffer() -> &'static mut LogBuffer {
+    unsafe { &mut LOG_BUFFER }
+}
+
+/// Busy-wait print raw log output (translating newlines as appropriate).
+fn bwprint_output((older, newer): (&[u8], &[u8])) {
+    use bstr::ByteSlice;
+
+    for chunk in &[older, newer] {
+        let mut lines = chunk.split(|&c| c == b'\n').peekable();
+        while let Some(line) = lines.next() {
+            if lines.peek().is_some() {
+                crate::bwkprintln!("{}", line.as_bstr());
+            } else {
+                crate::bwkprint!("{}", line.as_bstr());
+            }
+        }
+    }
+}
+
+/// Busy-wait print the most recent log output (regardless of whether or not
+/// it's been drained).
+pub fn print_recent() {
+    bwprint_output(log_buffer().recent())
+}
+
+/// Busy-wait print (and drain) any undrained log output.
+pub fn flush() {
+    let log = log_buffer();
+    let (older, newer) = log.undrained();
+    let len = older.len() + newer.len();
+    bwprint_output((older, newer));
+    log.consume(len);
+}
+
+/// Append a log message to the kernel log buffer. Used by the logging macros.
+#[doc(hidden)]
+pub fn log(args: fmt::Arguments, newline: bool) {
+    let log = log_buffer();
+    let _ = log.write_fmt(args);
+    if newline {
+        let _ = log.write_str("\n");
+    }
+
+    // output which has already been printed doesn't need to be drained
+    #[cfg(feature = "kernel-log-busy-wait")]
+    {
+        if newline {
+            crate::bwkprintln!("{}", args);
+        } else {
+            crate::bwkprint!("{}", args);
+        }
+        log.consume(usize::MAX);
+    }
+}
+
+/// Errors which may occur when setting a module's log level.
+#[derive(Debug)]
+pub enum FilterError {
+    /// There's no more room for per-module filters.
+    TooManyFilters,
+    /// The module path is too long.
+    ModuleTooLong,
+}
+
+/// A log level which applies to a module (and its children).
+struct ModuleFilter {
+    /// Module path, relative to the crate root (e.g: `kernel::syscalls::send`).
+    module: String<MAX_LOG_MODULE_LEN>,
+    level: LevelFilter,
+}
+
+/// Per-module log level filters.
+pub struct LogFilters {
+    /// Level of modules without a more specific filter.
+    default: LevelFilter,
+    modules: Vec<ModuleFilter, MAX_LOG_FILTERS>,
+}
+
+/// Strip the crate name from a module path (e.g: `choochoos_kernel::kernel`
+/// becomes `kernel`).
+fn relative_path(path: &str) -> &str {
+    match path.find("::") {
+        Some(i) => &path[i + 2..],
+        None => "",
+    }
+}
+
+impl LogFilters {
+    /// Returns the log level of the given module path, i.e: the level of the
+    /// most specific filter which applies to it.
+    pub fn level(&self, path: &str) -> LevelFilter {
+        let path = relative_path(path);
+        let applies = |module: &str| {
+            path.starts_with(module)
+                && (path.len() == module.len() || path[module.len()..].starts_with("::"))
+        };
+
+        self.modules
+            .iter()
+            .filter(|filter| applies(&filter.module))
+            .max_by_key(|filter| filter.module.len())
+            .map_or(self.default, |filter| filter.level)
+    }
+
+    /// Set the log level of a module (and its children). An empty module path
+    /// sets the level of every module without a more specific filter.
+    pub fn set(&mut self, module: &str, level: LevelFilter) -> Result<(), FilterError> {
+        if module.is_empty() {
+            self.default = level;
+        } else if let Some(filter) = self.modules.iter_mut().find(|f| f.module == module) {
+            filter.level = level;
+        } else {
+            let mut filter = ModuleFilter {
+                module: String::new(),
+                level,
+            };
+            (filter.module.push_str(module)).map_err(|_| FilterError::ModuleTooLong)?;
+            (self.modules.push(filter)).map_err(|_| FilterError::TooManyFilters)?;
         }
-        #[cfg(not(feature = "kdebug"))]
-        {
-            let _ = ($fmt, $($arg)*);
+
+        self.update_max_level();
+        Ok(())
+    }
+
+    /// Update the `log` crate's global max level, which allows it to skip
+    /// messages which no filter would let through.
+    fn update_max_level(&self) {
+        let max_level = self
+            .modules
+            .iter()
+            .map(|f| f.level)
+            .fold(self.default, Ord::max);
+        log::set_max_level(max_level);
+    }
+}
+
+static mut LOG_FILTERS: Option<LogFilters> = None;
+
+/// Returns the kernel's log filters.
+///
+/// # Panics
+///
+/// Panics if [`init`] hasn't been called.
+pub fn log_filters() -> &'static mut LogFilters {
+    unsafe { LOG_FILTERS.as_mut() }.expect("kernel logger was not initialized")
+}
+
+/// [`log`] backend which appends messages to the kernel log.
+struct KernelLogger;
+
+impl Log for KernelLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log_filters().level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
         }
+
+        let color = match record.level() {
+            Level::Error => "\x1b[31m", // red
+            Level::Warn => "\x1b[33m",  // yellow
+            Level::Info => "\x1b[32m",  // green
+            Level::Debug => "\x1b[36m", // cyan
+            Level::Trace => "\x1b[90m", // grey
+        };
+
+        let tid = match unsafe { &crate::KERNEL } {
+            Some(kernel) => kernel.current_tid().map_or(-1, |tid| tid.into() as isize),
+            None => unsafe { core::hint::unreachable_unchecked() },
+        };
+
+        log(
+            format_args!(
+                "{}[{}][tid={}][{}]\x1b[0m {}",
+                color,
+                record.level(),
+                tid,
+                relative_path(record.target()),
+                record.args()
+            ),
+            true,
+        );
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: KernelLogger = KernelLogger;
+
+/// Install the kernel's [`log`] backend, using the log filters set at build
+/// time.
+///
+/// # Safety
+///
+/// Must only be called once, before any messages are logged.
+pub unsafe fn init() {
+    let mut filters = LogFilters {
+        default: LOG_LEVEL,
+        modules: Vec::new(),
     };
+    for &(module, level) in LOG_MODULE_LEVELS {
+        // build.rs validates the filters' sizes
+        filters.set(module, level).unwrap();
+    }
+    filters.update_max_level();
+    LOG_FILTERS = Some(filters);
+
+    // armv4t doesn't support atomic compare-and-swap
+    log::set_logger_racy(&LOGGER).expect("kernel logger was already set");
 }
 
 /// General Purpose kernel logging mechanism. Appends "\n\r" to the output.
-// TODO: hook this up to an internal log buffer instead of directly to bwprint
 #[macro_export]
 macro_rules! kprintln {
     () => { kprintln!("") };
     ($fmt:literal) => { kprintln!($fmt,) };
     ($fmt:literal, $($arg:tt)*) => {{
-        $crate::bwkprintln!($fmt, $($arg)*)
+        $crate::kernel_log::log(format_args!($fmt, $($arg)*), true)
     }};
 }
 
 /// General Purpose kernel logging mechanism.
-// TODO: hook this up to an internal log buffer instead of directly to bwprint
 #[macro_export]
 macro_rules! kprint {
     () => { kprint!("") };
     ($fmt:literal) => { kprint!($fmt,) };
-    ($fmt:literal, $($arg:tt)*) => {
-        $crate::bwkprint!($fmt, $($arg)*)
-    };
+    ($fmt:literal, $($arg:tt)*) => {{
+        $crate::kernel_log::log(format_args!($fmt, $($arg)*), false)
+    }};
 }
@@ -0,0 +1,45 @@
+//! The core choochoos kernel.
+//!
+//! The kernel is built as a library (linked into a thin executable, see
+//! `main.rs`), so that its unit tests can be run on the host (using the
+//! `platform-host` feature).
+
+#![cfg_attr(not(feature = "platform-host"), no_std)]
+#![feature(asm, naked_functions)]
+#![feature(doc_cfg)]
+#![cfg_attr(feature = "heap", feature(alloc_error_handler))]
+// unit tests don't use the kernel's entry point (or anything only reachable
+// from it)
+#![cfg_attr(test, allow(dead_code))]
+
+#[cfg(feature = "heap")]
+#[macro_use]
+extern crate alloc;
+
+#[macro_use]
+mod kernel_log;
+mod util;
+
+#[doc(cfg(feature = "heap"))]
+#[cfg(feature = "heap")]
+mod heap;
+
+/// Platform-independent core Kernel code.
+mod kernel;
+/// Platform-specific Kernel code (e.g: hardware initialization / handling)
+mod platform;
+
+/// There can be only one kernel.
+///
+/// We use an Option to represent the initial "uninitialized" state, which is
+/// later initialized via the `Kernel::init` method.
+///
+/// This could potentially be replaced with a UnsafeCell<MaybeUninit<Kernel>>,
+/// but this approach is fine for now.
+static mut KERNEL: Option<kernel::Kernel> = None;
+
+/// Called from `_start`. See `src/platform/<platform>/rust_runtime/crt0.rs`
+fn main() -> isize {
+    unsafe { kernel::Kernel::init() }.run();
+    0
+}
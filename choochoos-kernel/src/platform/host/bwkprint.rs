@@ -0,0 +1,23 @@
+//! Busy-wait logging primitives.
+
+/// "Busy-wait" Kernel printing over the host's stdout.
+#[macro_export]
+macro_rules! bwkprintln {
+    () => { bwkprintln!("") };
+    ($fmt:literal) => { bwkprintln!($fmt,) };
+    ($fmt:literal, $($arg:tt)*) => {{
+        std::println!($fmt, $($arg)*);
+    }};
+}
+
+/// "Busy-wait" Kernel printing over the host's stdout.
+#[macro_export]
+macro_rules! bwkprint {
+    () => { bwkprint!("") };
+    ($fmt:literal) => { bwkprint!($fmt,) };
+    ($fmt:literal, $($arg:tt)*) => {{
+        use std::io::Write;
+        std::print!($fmt, $($arg)*);
+        let _ = std::io::stdout().flush();
+    }};
+}
@@ -0,0 +1,14 @@
+//! Periodic kernel clock tick, used to expire syscall timeouts and delays.
+//!
+//! Uses the simulated periodic timer interrupt (which is otherwise left to
+//! userspace clock servers), whose period matches the kernel's
+//! [`TICK`](crate::kernel::TICK).
+
+use super::interrupts::{TIMER_EVENT_ID, TIMER_PERIOD};
+
+const _: () = assert!(TIMER_PERIOD.as_nanos() == crate::kernel::TICK.as_nanos());
+
+/// Check if the `event_id` corresponds to a kernel clock tick.
+pub fn is_tick(event_id: usize) -> bool {
+    event_id == TIMER_EVENT_ID
+}
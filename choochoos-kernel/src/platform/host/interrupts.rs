@@ -0,0 +1,195 @@
+//! Simulated interrupt sources.
+//!
+//! The host platform exposes two event sources:
+//!
+//! - [`TIMER_EVENT_ID`]: fires every [`TIMER_PERIOD`], with no associated
+//!   volatile data. The timer is only started once a task first waits on it
+//!   (much like the TS-7200's timers, which are started by userspace), as ticks
+//!   which nobody is waiting for would otherwise keep the kernel running
+//!   forever.
+//! - [`STDIN_EVENT_ID`]: fires whenever a byte is read from stdin, with the
+//!   byte itself as volatile data.
+//!
+//! With the `preemption` feature enabled, the end of each scheduling quantum is
+//! also delivered as an interrupt (see [`super::preemption`]).
+//!
+//! With the `kernel-clock` feature enabled, the periodic timer interrupt is
+//! reserved for the kernel (see [`super::clock`]).
+
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::kernel::WakePolicy;
+
+/// EventId of the periodic timer interrupt.
+pub const TIMER_EVENT_ID: usize = 1;
+/// EventId of the stdin "UART" receive interrupt.
+pub const STDIN_EVENT_ID: usize = 2;
+
+/// Period of the simulated timer interrupt.
+pub const TIMER_PERIOD: Duration = Duration::from_millis(10);
+
+pub(super) struct InterruptState {
+    /// When the next timer tick occurs (if the timer has been started).
+    next_tick: Option<Instant>,
+    /// When the currently running task's scheduling quantum expires.
+    #[cfg(feature = "preemption")]
+    pub(super) quantum_deadline: Instant,
+    stdin_rx: mpsc::Receiver<u8>,
+    /// Byte which has been read from `stdin_rx`, but hasn't been delivered.
+    pending_byte: Option<u8>,
+}
+
+static mut STATE: Option<InterruptState> = None;
+
+pub(super) unsafe fn state() -> &'static mut InterruptState {
+    match &mut STATE {
+        Some(state) => state,
+        None => panic!("platform was not initialized"),
+    }
+}
+
+pub(super) unsafe fn init() {
+    let (stdin_tx, stdin_rx) = mpsc::sync_channel(64);
+    std::thread::spawn(move || {
+        use std::io::Read;
+        for b in std::io::stdin().lock().bytes() {
+            match b {
+                Ok(b) => {
+                    if stdin_tx.send(b).is_err() {
+                        break;
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+    });
+
+    STATE = Some(InterruptState {
+        // the kernel clock is always running
+        next_tick: if cfg!(feature = "kernel-clock") {
+            Some(Instant::now() + TIMER_PERIOD)
+        } else {
+            None
+        },
+        #[cfg(feature = "preemption")]
+        quantum_deadline: Instant::now() + super::preemption::QUANTUM,
+        stdin_rx,
+        pending_byte: None,
+    });
+}
+
+impl InterruptState {
+    /// Block until the next simulated interrupt occurs.
+    pub(super) fn wait_for_irq(&mut self) {
+        if self.pending_byte.is_some() {
+            return;
+        }
+
+        self.pending_byte = match self.next_tick {
+            Some(next_tick) => {
+                let timeout = next_tick.saturating_duration_since(Instant::now());
+                self.stdin_rx.recv_timeout(timeout).ok()
+            }
+            None => match self.stdin_rx.recv() {
+                Ok(b) => Some(b),
+                Err(_) => panic!("idle, but no more interrupts can occur (stdin was closed)"),
+            },
+        };
+    }
+
+    fn poll_stdin(&mut self) -> Option<u8> {
+        self.pending_byte
+            .take()
+            .or_else(|| self.stdin_rx.try_recv().ok())
+    }
+}
+
+// ---------------------------- public interface ---------------------------- //
+
+/// Check if the `event_id` corresponds to a valid host interrupt.
+pub fn validate_eventid(event_id: usize) -> bool {
+    // the timer is reserved for the kernel clock
+    #[cfg(feature = "kernel-clock")]
+    {
+        if super::clock::is_tick(event_id) {
+            return false;
+        }
+    }
+
+    matches!(event_id, TIMER_EVENT_ID | STDIN_EVENT_ID)
+}
+
+/// Called whenever a task waits on `event_id`, starting the timer the first
+/// time it's waited on.
+pub fn event_awaited(event_id: usize) {
+    if event_id != TIMER_EVENT_ID {
+        return;
+    }
+
+    let state = unsafe { state() };
+    if state.next_tick.is_none() {
+        state.next_tick = Some(Instant::now() + TIMER_PERIOD);
+    }
+}
+
+/// Determine which of the tasks waiting on `event_id` are woken when the event
+/// occurs.
+///
+/// Each simulated interrupt is delivered to a single task (e.g: a byte read
+/// from stdin should only be consumed once).
+pub fn wake_policy(_event_id: usize) -> WakePolicy {
+    WakePolicy::WakeHighestPriority
+}
+
+/// Check if there are any pending interrupts.
+pub fn irq_pending() -> bool {
+    let state = unsafe { state() };
+
+    if state.pending_byte.is_none() {
+        state.pending_byte = state.stdin_rx.try_recv().ok();
+    }
+
+    let now = Instant::now();
+
+    #[cfg(feature = "preemption")]
+    {
+        if state.quantum_deadline <= now {
+            return true;
+        }
+    }
+
+    state.pending_byte.is_some() || state.next_tick.is_some_and(|tick| tick <= now)
+}
+
+/// Handle any pending interrupts.
+///
+/// Called from [`Kernel::handle_irq`](crate::kernel::Kernel::handle_irq).
+pub unsafe fn handle_irq(
+    mut interrupt_handled: impl FnMut(/* event_id: */ usize, /* volatile_data: */ usize),
+) {
+    let state = state();
+
+    // missed ticks are coalesced, just like a real timer would
+    let now = Instant::now();
+    if let Some(next_tick) = &mut state.next_tick {
+        if *next_tick <= now {
+            while *next_tick <= now {
+                *next_tick += TIMER_PERIOD;
+            }
+            interrupt_handled(TIMER_EVENT_ID, 0);
+        }
+    }
+
+    #[cfg(feature = "preemption")]
+    {
+        if state.quantum_deadline <= now {
+            state.quantum_deadline = now + super::preemption::QUANTUM;
+            interrupt_handled(super::preemption::QUANTUM_EVENT_ID, 0);
+        }
+    }
+
+    while let Some(b) = state.poll_stdin() {
+        interrupt_handled(STDIN_EVENT_ID, b as usize);
+    }
+}
@@ -0,0 +1,58 @@
+//! Platform specific support for running the kernel as a regular process on a
+//! hosted (i.e: `std` enabled) operating system, such as Linux.
+//!
+//! Tasks are run as green threads (see `kernel::arch::host`), and hardware
+//! interrupts are simulated using host timers and stdin/stdout.
+
+mod rust_runtime;
+
+pub mod bwkprint;
+#[cfg(feature = "kernel-clock")]
+pub mod clock;
+pub mod interrupts;
+#[cfg(feature = "preemption")]
+pub mod preemption;
+pub mod time;
+
+use core::mem::MaybeUninit;
+use std::time::{Duration, Instant};
+
+use crate::kernel::{UserStack, MAX_TASKS};
+
+const NO_USER_STACK: MaybeUninit<UserStack> = MaybeUninit::uninit();
+
+/// Backing memory for user stacks.
+///
+/// Tasks run on their own thread's stack, so each task slot's region of the
+/// user stack area only needs to hold the task's `UserStack`.
+static mut USER_STACKS: [MaybeUninit<UserStack>; MAX_TASKS] = [NO_USER_STACK; MAX_TASKS];
+
+pub unsafe fn initialize() {
+    time::init();
+    interrupts::init();
+}
+
+pub unsafe fn teardown() {
+    use std::io::Write;
+    let _ = std::io::stdout().flush();
+}
+
+pub unsafe fn idle_task() -> Duration {
+    let start = Instant::now();
+    interrupts::state().wait_for_irq();
+    let time_asleep = start.elapsed();
+
+    let kernel = match &mut crate::KERNEL {
+        Some(kernel) => kernel,
+        None => core::hint::unreachable_unchecked(),
+    };
+
+    kernel.handle_irq();
+
+    time_asleep
+}
+
+/// Returns the start address of the memory region reserved for user stacks.
+pub fn user_stacks_start() -> usize {
+    unsafe { USER_STACKS.as_ptr() as usize }
+}
@@ -0,0 +1,23 @@
+//! Simulated scheduling quantum timer.
+//!
+//! Host interrupts are only checked when a task traps into the kernel, so
+//! unlike on real hardware, a task spinning in a tight loop without making any
+//! syscalls can't be preempted.
+
+use std::time::{Duration, Instant};
+
+/// EventId of the (kernel-internal) scheduling quantum interrupt.
+pub const QUANTUM_EVENT_ID: usize = 3;
+
+/// Length of a scheduling quantum.
+pub const QUANTUM: Duration = Duration::from_millis(10);
+
+/// Restart the scheduling quantum from zero.
+pub unsafe fn reset_quantum() {
+    super::interrupts::state().quantum_deadline = Instant::now() + QUANTUM;
+}
+
+/// Check if the `event_id` corresponds to the end of a scheduling quantum.
+pub fn is_tick(event_id: usize) -> bool {
+    event_id == QUANTUM_EVENT_ID
+}
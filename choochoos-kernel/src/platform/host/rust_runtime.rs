@@ -0,0 +1,20 @@
+//! Set up the minimal Rust runtime.
+//!
+//! Unlike bare-metal platforms, the host's C runtime takes care of clearing
+//! bss, setting up the stack, etc..., and `std` provides the panic handler.
+
+/// The first function called by the host's C runtime.
+///
+/// Omitted from the kernel's unit tests, which use the test harness's `main`.
+#[cfg(not(test))]
+#[no_mangle]
+extern "C" fn main(_argc: i32, _argv: *const *const u8) -> i32 {
+    // follow up std's panic message with a crash dump
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        crate::kernel::crash_dump();
+    }));
+
+    crate::main() as i32
+}
@@ -0,0 +1,42 @@
+//! Monotonic time since boot.
+//!
+//! In the kernel's unit tests, time only passes when a test calls [`advance`],
+//! which keeps kernel clock ticks deterministic.
+
+#[cfg(not(test))]
+use std::sync::OnceLock;
+use std::time::Duration;
+#[cfg(not(test))]
+use std::time::Instant;
+
+#[cfg(not(test))]
+static BOOT: OnceLock<Instant> = OnceLock::new();
+
+#[cfg(test)]
+std::thread_local! {
+    static UPTIME: core::cell::Cell<Duration> = const { core::cell::Cell::new(Duration::ZERO) };
+}
+
+pub(super) fn init() {
+    #[cfg(not(test))]
+    BOOT.get_or_init(Instant::now);
+}
+
+/// Returns the amount of time elapsed since the platform was initialized.
+pub fn uptime() -> Duration {
+    #[cfg(not(test))]
+    {
+        BOOT.get_or_init(Instant::now).elapsed()
+    }
+
+    #[cfg(test)]
+    {
+        UPTIME.with(|uptime| uptime.get())
+    }
+}
+
+/// Advance the current thread's uptime by `by`.
+#[cfg(test)]
+pub fn advance(by: Duration) {
+    UPTIME.with(|uptime| uptime.set(uptime.get() + by));
+}
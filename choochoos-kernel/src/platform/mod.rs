@@ -1,4 +1,24 @@
+#[cfg(all(feature = "platform-ts7200", feature = "platform-host"))]
+compile_error!("only a single `platform-` feature can be enabled at a time");
+
+#[cfg(all(feature = "mmu", feature = "platform-host"))]
+compile_error!("the `mmu` feature is only supported on `platform-ts7200`");
+
+#[cfg(all(feature = "monitor", feature = "platform-host"))]
+compile_error!("the `monitor` feature is only supported on `platform-ts7200`");
+
+#[cfg(all(feature = "gdb", feature = "platform-host"))]
+compile_error!("the `gdb` feature is only supported on `platform-ts7200`");
+
+#[cfg(all(feature = "mmu", feature = "legacy-implicit-exit"))]
+compile_error!("`legacy-implicit-exit` returns into kernel code, which the `mmu` feature protects");
+
 #[cfg(feature = "platform-ts7200")]
 mod ts7200;
 #[cfg(feature = "platform-ts7200")]
 pub use self::ts7200::*;
+
+#[cfg(feature = "platform-host")]
+mod host;
+#[cfg(feature = "platform-host")]
+pub use self::host::*;
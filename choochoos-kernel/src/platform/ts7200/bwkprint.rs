@@ -14,6 +14,20 @@ macro_rules! bwkprintln {
     }};
 }
 
+/// Busy-wait read a single byte from the TS-7200's COM2.
+#[cfg(feature = "monitor")]
+pub fn read_byte() -> u8 {
+    use ts7200::hw::uart::{Channel, Uart};
+
+    #[cfg(not(feature = "platform-ts7200-emulated"))]
+    let channel = Channel::COM2;
+    #[cfg(feature = "platform-ts7200-emulated")]
+    let channel = Channel::COM3;
+
+    // SAFETY: the kernel only reads from the UART while it's busy-waiting
+    unsafe { Uart::new(channel) }.read_byte_blocking()
+}
+
 /// Bust-wait Kernel printing over the TS-7200's COM2.
 #[macro_export]
 macro_rules! bwkprint {
@@ -0,0 +1,28 @@
+//! Periodic kernel clock tick, used to expire syscall timeouts and delays.
+//!
+//! Uses TIMER2 (which is otherwise left to userspace clock servers), running
+//! in periodic mode off the 508KHz clock.
+
+use core::ptr;
+
+use choochoos_platform_ts7200::{EventId, Interrupt};
+
+/// Length of a kernel clock tick, in 508KHz timer ticks.
+const TICK_TIMER_TICKS: u32 = (crate::kernel::TICK.as_micros() * 508 / 1000) as u32;
+
+pub(super) unsafe fn init() {
+    use ts7200::constants::timer::*;
+
+    // timer2 interrupts are always enabled in the VIC
+    ptr::write_volatile((TIMER2_BASE + CTRL_OFFSET) as *mut u32, 0);
+    ptr::write_volatile((TIMER2_BASE + LDR_OFFSET) as *mut u32, TICK_TIMER_TICKS);
+    ptr::write_volatile(
+        (TIMER2_BASE + CTRL_OFFSET) as *mut u32,
+        ENABLE_MASK | MODE_MASK | CLKSEL_MASK,
+    );
+}
+
+/// Check if the `event_id` corresponds to a kernel clock tick.
+pub fn is_tick(event_id: usize) -> bool {
+    event_id == EventId::from_interrupt(Interrupt::Tc2Ui).raw()
+}
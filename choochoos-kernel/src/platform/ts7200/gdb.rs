@@ -0,0 +1,77 @@
+//! The serial connection used by the GDB stub.
+//!
+//! GDB is attached to COM1 (which is configured for 115200 baud, 8N1), or to
+//! the emulator's virtual UART3 when using `platform-ts7200-emulated`.
+
+use core::convert::Infallible;
+
+use gdbstub::Connection;
+use ts7200::hw::uart::{Channel, Uart};
+
+/// A busy-wait GDB connection over a UART.
+pub struct GdbConnection {
+    uart: Uart,
+    /// A byte which was received while checking for an interrupt.
+    peeked: Option<u8>,
+}
+
+impl Connection for GdbConnection {
+    type Error = Infallible;
+
+    fn read(&mut self) -> Result<u8, Infallible> {
+        match self.peeked.take() {
+            Some(b) => Ok(b),
+            None => Ok(self.uart.read_byte_blocking()),
+        }
+    }
+
+    fn write(&mut self, byte: u8) -> Result<(), Infallible> {
+        self.uart.write_byte_blocking(byte);
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>, Infallible> {
+        if self.peeked.is_none() {
+            self.peeked = self.uart.try_read_byte();
+        }
+        Ok(self.peeked)
+    }
+
+    fn flush(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// Configure the GDB UART, and return a connection over it.
+///
+/// # Safety
+///
+/// Must only be called once, as nothing else may use the GDB UART.
+pub unsafe fn connection() -> GdbConnection {
+    #[cfg(not(feature = "platform-ts7200-emulated"))]
+    {
+        use core::ptr;
+        use ts7200::constants::uart::*;
+
+        // the UART clock runs at 7.3728MHz
+        const UART_CLK: u32 = 7_372_800;
+        const BAUD_RATE: u32 = 115_200;
+
+        let divisor = UART_CLK / (16 * BAUD_RATE) - 1;
+        ptr::write_volatile((UART1_BASE + LCRM_OFFSET) as *mut u32, divisor >> 8);
+        ptr::write_volatile((UART1_BASE + LCRL_OFFSET) as *mut u32, divisor & 0xff);
+        // writing LCRH latches the baud rate divisor, so it must be written last
+        ptr::write_volatile((UART1_BASE + LCRH_OFFSET) as *mut u32, WLEN_MASK as u32);
+        ptr::write_volatile((UART1_BASE + CTLR_OFFSET) as *mut u32, UARTEN_MASK as u32);
+    }
+
+    #[cfg(not(feature = "platform-ts7200-emulated"))]
+    let channel = Channel::COM1;
+    #[cfg(feature = "platform-ts7200-emulated")]
+    let channel = Channel::COM3;
+
+    GdbConnection {
+        uart: Uart::new(channel),
+        peeked: None,
+    }
+}
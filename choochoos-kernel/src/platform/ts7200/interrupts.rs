@@ -6,6 +6,8 @@ use bit_field::BitField;
 
 use choochoos_platform_ts7200::{EventId, Interrupt};
 
+use crate::kernel::WakePolicy;
+
 unsafe fn service_timer(base_addr: u32) -> usize {
     use ts7200::constants::timer;
 
@@ -70,6 +72,50 @@ unsafe fn service_uart(base_addr: u32) -> usize {
     ret as _
 }
 
+/// Set when a break is received on COM2 (or COM3, when using
+/// `platform-ts7200-emulated`).
+#[cfg(feature = "monitor")]
+static mut BREAK_RECEIVED: bool = false;
+
+/// Read the byte which raised a receive interrupt on the UART at `base`,
+/// checking whether it's a break (which enters the kernel monitor). Any other
+/// byte is handed to userspace via the interrupt's volatile data (see
+/// [`console_rx_byte`](choochoos_platform_ts7200::console_rx_byte)).
+///
+/// The receive interrupt is left enabled, so that breaks are detected even if
+/// userspace never reads from the UART.
+#[cfg(feature = "monitor")]
+unsafe fn service_console_rx(base: u32, volatile_data: usize) -> usize {
+    use choochoos_platform_ts7200::{CONSOLE_RX_SHIFT, CONSOLE_RX_VALID};
+    use ts7200::constants::uart;
+
+    if volatile_data as u32 & uart::INTR_RX == 0 {
+        return volatile_data;
+    }
+
+    let flags = ptr::read_volatile((base + uart::FLAG_OFFSET) as *const u32);
+    let volatile_data = if flags & uart::RXFE_MASK as u32 != 0 {
+        volatile_data
+    } else {
+        // the receive status corresponds to the most recently read byte
+        let byte = ptr::read_volatile((base + uart::DATA_OFFSET) as *const u32) & uart::DATA_MASK;
+        let rsr = (base + uart::RSR_OFFSET) as *mut u32;
+        if ptr::read_volatile(rsr) & uart::BE_MASK as u32 != 0 {
+            // any write clears the error flags
+            ptr::write_volatile(rsr, 0);
+            BREAK_RECEIVED = true;
+            volatile_data & !(uart::INTR_RX as usize)
+        } else {
+            volatile_data | CONSOLE_RX_VALID | (byte as usize) << CONSOLE_RX_SHIFT
+        }
+    };
+
+    let ctlr = (base + uart::CTLR_OFFSET) as *mut u32;
+    ptr::write_volatile(ctlr, ptr::read_volatile(ctlr) | uart::RIEN_MASK as u32);
+
+    volatile_data
+}
+
 /// Service a pending interrupt and return it's corresponding EventId and
 /// volatile data.
 unsafe fn service_interrupt(vic_idx: u8) -> (EventId, usize) {
@@ -83,9 +129,22 @@ unsafe fn service_interrupt(vic_idx: u8) -> (EventId, usize) {
         Interrupt::Tc2Ui => service_timer(ts7200::constants::timer::TIMER2_BASE),
         Interrupt::Tc3Ui => service_timer(ts7200::constants::timer::TIMER3_BASE),
         Interrupt::IntUart1 => service_uart(ts7200::constants::uart::UART1_BASE),
+        #[cfg(not(feature = "monitor"))]
         Interrupt::IntUart2 => service_uart(ts7200::constants::uart::UART2_BASE),
+        #[cfg(feature = "monitor")]
+        Interrupt::IntUart2 => {
+            let base = ts7200::constants::uart::UART2_BASE;
+            service_console_rx(base, service_uart(base))
+        }
+        // the monitor reads from COM3 in the emulator
+        #[cfg(all(feature = "monitor", feature = "platform-ts7200-emulated"))]
+        Interrupt::IntUart3 => {
+            let base = ts7200::constants::uart::UART3_BASE;
+            service_console_rx(base, service_uart(base))
+        }
         _ => unimplemented!("unimplemented interrupt source: {:?}", interrupt),
     };
+    log::trace!("serviced {:?}, data {:#x?}", interrupt, volatile_data);
 
     (EventId::from_interrupt(interrupt), volatile_data)
 }
@@ -94,9 +153,65 @@ unsafe fn service_interrupt(vic_idx: u8) -> (EventId, usize) {
 
 /// Check if the `event_id` corresponds to a valid TS-7200 interrupt.
 pub fn validate_eventid(event_id: usize) -> bool {
+    // the scheduling quantum timer is reserved for the kernel
+    #[cfg(feature = "preemption")]
+    {
+        if super::preemption::is_tick(event_id) {
+            return false;
+        }
+    }
+
+    // ...as is the kernel clock timer
+    #[cfg(feature = "kernel-clock")]
+    {
+        if super::clock::is_tick(event_id) {
+            return false;
+        }
+    }
+
+    // ...as is the emulator's monitor UART
+    #[cfg(all(feature = "monitor", feature = "platform-ts7200-emulated"))]
+    {
+        if event_id == EventId::from_interrupt(Interrupt::IntUart3).raw() {
+            return false;
+        }
+    }
+
     EventId::from_raw(event_id).is_some()
 }
 
+/// Called whenever a task waits on `event_id`.
+///
+/// Userspace is responsible for enabling the TS-7200's interrupt sources (e.g:
+/// starting a timer), so there's nothing to do.
+pub fn event_awaited(_event_id: usize) {}
+
+/// Determine which of the tasks waiting on `event_id` are woken when the event
+/// occurs.
+///
+/// UART1 and UART2 each raise a single combined interrupt for RX, TX, and modem
+/// status changes, which is typically waited on by separate RX and TX
+/// notifiers. Since the volatile data is the UART's interrupt status register,
+/// every waiter is woken, and can check whether the interrupt is relevant to
+/// it.
+///
+/// All other interrupts are delivered to a single task (as they'd otherwise be
+/// "handled" multiple times).
+pub fn wake_policy(event_id: usize) -> WakePolicy {
+    match EventId::from_raw(event_id) {
+        Some(e) if e == EventId::from_interrupt(Interrupt::IntUart1) => WakePolicy::WakeAll,
+        Some(e) if e == EventId::from_interrupt(Interrupt::IntUart2) => WakePolicy::WakeAll,
+        _ => WakePolicy::WakeHighestPriority,
+    }
+}
+
+/// Check (and clear) whether a break has been received on COM2 (or COM3, when
+/// using `platform-ts7200-emulated`) since the last call.
+#[cfg(feature = "monitor")]
+pub fn take_break_received() -> bool {
+    unsafe { core::mem::replace(&mut BREAK_RECEIVED, false) }
+}
+
 /// Handle any pending interrupts.
 ///
 /// Called from [`Kernel::handle_irq`](crate::kernel::Kernel::handle_irq).
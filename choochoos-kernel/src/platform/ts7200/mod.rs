@@ -3,8 +3,17 @@
 
 mod rust_runtime;
 
+pub use rust_runtime::exit_to_redboot;
+
 pub mod bwkprint;
+#[cfg(feature = "kernel-clock")]
+pub mod clock;
+#[cfg(feature = "gdb")]
+pub mod gdb;
 pub mod interrupts;
+#[cfg(feature = "preemption")]
+pub mod preemption;
+pub mod time;
 
 use core::ptr;
 use core::time::Duration;
@@ -37,10 +46,31 @@ pub unsafe fn initialize() {
             1 << Interrupt::Tc2Ui.to_vic_idx(),
         );
         // enable uart1 and uart2 combined interrupt
-        ptr::write_volatile(
-            (VIC2_BASE + INT_ENABLE_OFFSET) as *mut u32,
-            (1 << Interrupt::IntUart1.to_vic_idx()) | (1 << Interrupt::IntUart2.to_vic_idx()),
-        );
+        #[allow(unused_mut)]
+        let mut vic2_enable =
+            (1 << Interrupt::IntUart1.to_vic_idx()) | (1 << Interrupt::IntUart2.to_vic_idx());
+        // ...and uart3's, which the kernel monitor reads from in the emulator
+        #[cfg(all(feature = "monitor", feature = "platform-ts7200-emulated"))]
+        {
+            vic2_enable |= 1 << Interrupt::IntUart3.to_vic_idx();
+        }
+        ptr::write_volatile((VIC2_BASE + INT_ENABLE_OFFSET) as *mut u32, vic2_enable);
+    }
+
+    // the kernel reads COM2 input itself, in order to detect breaks (as well as
+    // COM3's, where the monitor runs in the emulator)
+    #[cfg(feature = "monitor")]
+    {
+        use ts7200::constants::uart::*;
+
+        let ctlr = (UART2_BASE + CTLR_OFFSET) as *mut u32;
+        ptr::write_volatile(ctlr, ptr::read_volatile(ctlr) | RIEN_MASK as u32);
+
+        #[cfg(feature = "platform-ts7200-emulated")]
+        {
+            let ctlr = (UART3_BASE + CTLR_OFFSET) as *mut u32;
+            ptr::write_volatile(ctlr, ptr::read_volatile(ctlr) | RIEN_MASK as u32);
+        }
     }
 
     {
@@ -54,6 +84,14 @@ pub unsafe fn initialize() {
             ENABLE_MASK | CLKSEL_MASK,
         );
     }
+
+    time::init();
+
+    #[cfg(feature = "kernel-clock")]
+    clock::init();
+
+    #[cfg(feature = "preemption")]
+    preemption::init();
 }
 
 pub unsafe fn teardown() {
@@ -83,10 +121,9 @@ pub unsafe fn idle_task() -> Duration {
     // tasks.
 
     use ts7200::constants::syscon;
+    let start = time::uptime();
     ptr::read_volatile(syscon::HALT as *mut u32);
-
-    // XXX: actually track time asleep
-    let time_asleep = Duration::new(0, 0);
+    let time_asleep = time::uptime() - start;
 
     let kernel = match &mut crate::KERNEL {
         Some(kernel) => kernel,
@@ -97,3 +134,23 @@ pub unsafe fn idle_task() -> Duration {
 
     time_asleep
 }
+
+/// Returns the start address of the memory region reserved for user stacks.
+pub fn user_stacks_start() -> usize {
+    // provided by the linker
+    extern "C" {
+        static __USER_STACKS_START__: core::ffi::c_void;
+    }
+
+    unsafe { &__USER_STACKS_START__ as *const _ as usize }
+}
+
+/// Returns the end of RAM (i.e: the end of the kernel's stack).
+pub fn ram_end() -> usize {
+    // provided by the linker
+    extern "C" {
+        static __RAM_END__: core::ffi::c_void;
+    }
+
+    unsafe { &__RAM_END__ as *const _ as usize }
+}
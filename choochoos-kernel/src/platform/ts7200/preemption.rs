@@ -0,0 +1,44 @@
+//! Scheduling quantum timer, used to preempt long-running tasks.
+//!
+//! Uses TIMER1 (which is otherwise unused), running in periodic mode off the
+//! 508KHz clock.
+
+use core::ptr;
+
+use choochoos_platform_ts7200::{EventId, Interrupt};
+
+/// Length of a scheduling quantum, in 508KHz timer ticks (~10ms).
+///
+/// TIMER1 is only 16 bits wide, which caps the quantum at ~129ms.
+const QUANTUM_TICKS: u32 = 5080;
+
+pub(super) unsafe fn init() {
+    use ts7200::constants::vic::*;
+
+    reset_quantum();
+
+    // enable timer1 interrupts
+    ptr::write_volatile(
+        (VIC1_BASE + INT_ENABLE_OFFSET) as *mut u32,
+        1 << Interrupt::Tc1Ui.to_vic_idx(),
+    );
+}
+
+/// Restart the scheduling quantum from zero.
+pub unsafe fn reset_quantum() {
+    use ts7200::constants::timer::*;
+
+    ptr::write_volatile((TIMER1_BASE + CTRL_OFFSET) as *mut u32, 0);
+    ptr::write_volatile((TIMER1_BASE + LDR_OFFSET) as *mut u32, QUANTUM_TICKS);
+    // discard any expiry of the previous quantum which hasn't been handled yet
+    ptr::write_volatile((TIMER1_BASE + CLR_OFFSET) as *mut u32, 1);
+    ptr::write_volatile(
+        (TIMER1_BASE + CTRL_OFFSET) as *mut u32,
+        ENABLE_MASK | MODE_MASK | CLKSEL_MASK,
+    );
+}
+
+/// Check if the `event_id` corresponds to the end of a scheduling quantum.
+pub fn is_tick(event_id: usize) -> bool {
+    event_id == EventId::from_interrupt(Interrupt::Tc1Ui).raw()
+}
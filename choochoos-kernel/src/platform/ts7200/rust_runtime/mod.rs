@@ -12,3 +12,15 @@ mod panic;
 /// A static variable containing the value of the link-register provided by
 /// Redboot when `_start` is called.
 static mut REDBOOT_RETURN_ADDRESS: *const core::ffi::c_void = core::ptr::null_mut();
+
+/// Yield control back to RedBoot, as though `_start` had returned.
+#[allow(clippy::empty_loop)]
+pub unsafe fn exit_to_redboot() -> ! {
+    asm!(
+        "mov pc, {}",
+        in(reg) REDBOOT_RETURN_ADDRESS,
+    );
+
+    // unreachable
+    loop {}
+}
@@ -5,31 +5,24 @@ static mut RECURSIVE_PANIC: bool = false;
 
 /// Busy-wait prints the error message, and then yields control back to Redboot.
 #[cfg_attr(not(test), panic_handler)]
-#[allow(dead_code, clippy::empty_loop)]
+#[allow(dead_code)]
 fn panic(info: &core::panic::PanicInfo) -> ! {
     // prints "kernel panicked at ..."
     ts7200::bwprintln!(COM2, "{}{}", "kernel ".red(), info.red());
 
-    // TODO?: flush kernel logs
-    // TODO?: manual backtrace / crash dump
-
-    // try and clean up, while making sure that if the teardown routine panics,
-    // there isn't an infinite loop.
+    // dump the kernel's state (including recent kernel logs) and try and clean up,
+    // while making sure that if either of those panic, there isn't an infinite
+    // loop.
     unsafe {
         if !RECURSIVE_PANIC {
             RECURSIVE_PANIC = true;
+            crate::kernel::crash_dump();
+            #[cfg(feature = "monitor")]
+            crate::kernel::monitor::enter_after_panic();
             super::super::teardown();
         }
     }
 
     // exit to RedBoot
-    unsafe {
-        asm!(
-            "mov pc, {}",
-            in(reg) super::REDBOOT_RETURN_ADDRESS,
-        );
-    }
-
-    // unreachable
-    loop {}
+    unsafe { super::exit_to_redboot() }
 }
@@ -0,0 +1,45 @@
+//! Monotonic time since boot, derived from TIMER3.
+//!
+//! TIMER3 is configured as a free-running 32 bit down-counter at 508KHz, which
+//! wraps around roughly every 2.3 hours. Wraps are accounted for by
+//! accumulating elapsed ticks each time the timer is read, so [`uptime`] must
+//! be called at least once per wrap-around period (which the kernel does
+//! whenever it idles, or handles a `Perf` syscall).
+
+use core::ptr;
+use core::time::Duration;
+
+/// TIMER3 ticks per second.
+const TIMER3_HZ: u64 = 508_000;
+
+/// Total TIMER3 ticks elapsed since boot.
+static mut UPTIME_TICKS: u64 = 0;
+/// TIMER3's value as of the last call to `uptime`.
+static mut LAST_TIMER3_VAL: u32 = core::u32::MAX;
+
+fn timer3_val() -> u32 {
+    use ts7200::constants::timer::*;
+    unsafe { ptr::read_volatile((TIMER3_BASE + VAL_OFFSET) as *const u32) }
+}
+
+fn ticks_to_duration(ticks: u64) -> Duration {
+    Duration::from_secs(ticks / TIMER3_HZ)
+        + Duration::from_nanos((ticks % TIMER3_HZ) * 1_000_000_000 / TIMER3_HZ)
+}
+
+/// Must be called after TIMER3 has been enabled.
+pub(super) unsafe fn init() {
+    UPTIME_TICKS = 0;
+    LAST_TIMER3_VAL = timer3_val();
+}
+
+/// Returns the amount of time elapsed since the platform was initialized.
+pub fn uptime() -> Duration {
+    let now = timer3_val();
+    unsafe {
+        // TIMER3 counts down
+        UPTIME_TICKS += LAST_TIMER3_VAL.wrapping_sub(now) as u64;
+        LAST_TIMER3_VAL = now;
+        ticks_to_duration(UPTIME_TICKS)
+    }
+}
@@ -1,3 +1,4 @@
 //! Shared utilities.
 
+pub mod ring;
 pub mod user_slice;
@@ -0,0 +1,154 @@
+//! Fixed-size ring buffers which are drained by userspace.
+
+use core::fmt::{self, Write};
+use core::ptr;
+
+use super::user_slice::UserSliceMut;
+
+/// Fixed-size ring buffer containing the `N` most recently pushed items.
+///
+/// If the buffer fills up before being drained, the oldest undrained items are
+/// overwritten.
+pub struct Ring<T, const N: usize> {
+    buf: [T; N],
+    /// Total number of items ever pushed (wrapping).
+    written: usize,
+    /// Total number of items ever drained (wrapping).
+    drained: usize,
+}
+
+impl<T: Copy, const N: usize> Ring<T, N> {
+    /// Create a new, empty Ring, with every slot initialized to `fill`.
+    pub const fn new(fill: T) -> Ring<T, N> {
+        // ensures positions remain consistent when the counters wrap
+        assert!(N.is_power_of_two(), "ring size must be a power of two");
+
+        Ring {
+            buf: [fill; N],
+            written: 0,
+            drained: 0,
+        }
+    }
+
+    /// Append an item, overwriting the oldest undrained item if the buffer is
+    /// full.
+    pub fn push(&mut self, item: T) {
+        self.buf[self.written % N] = item;
+        self.written = self.written.wrapping_add(1);
+
+        if self.written.wrapping_sub(self.drained) > N {
+            self.drained = self.written.wrapping_sub(N);
+        }
+    }
+
+    /// Split the `len` items preceding `end` into two slices (oldest items
+    /// first).
+    fn slices_before(&self, end: usize, len: usize) -> (&[T], &[T]) {
+        let start = end.wrapping_sub(len) % N;
+        if start + len <= N {
+            (&self.buf[start..start + len], &[])
+        } else {
+            let split = N - start;
+            (&self.buf[start..], &self.buf[..len - split])
+        }
+    }
+
+    /// Returns the most recent items (regardless of whether or not they've
+    /// been drained) as two slices (oldest items first).
+    pub fn recent(&self) -> (&[T], &[T]) {
+        self.slices_before(self.written, self.written.min(N))
+    }
+
+    /// Returns the undrained items as two slices (oldest items first).
+    pub fn undrained(&self) -> (&[T], &[T]) {
+        self.slices_before(self.written, self.written.wrapping_sub(self.drained))
+    }
+
+    /// Mark the oldest `len` undrained items as drained.
+    pub fn consume(&mut self, len: usize) {
+        let len = len.min(self.written.wrapping_sub(self.drained));
+        self.drained = self.drained.wrapping_add(len);
+    }
+
+    /// Drain as many of the oldest undrained items as fit into `buf`,
+    /// returning the number of items copied.
+    pub fn drain_into(&mut self, buf: UserSliceMut<T>) -> usize {
+        let (older, newer) = self.undrained();
+
+        let mut read = 0;
+        for chunk in &[older, newer] {
+            let len = chunk.len().min(buf.len() - read);
+            unsafe { ptr::copy_nonoverlapping(chunk.as_ptr(), buf.as_ptr().add(read), len) };
+            read += len;
+        }
+
+        self.consume(read);
+        read
+    }
+}
+
+impl<const N: usize> Write for Ring<u8, N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for &b in s.as_bytes() {
+            self.push(b);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::vec::Vec;
+
+    fn concat((older, newer): (&[u32], &[u32])) -> Vec<u32> {
+        older.iter().chain(newer).copied().collect()
+    }
+
+    #[test]
+    fn overwrites_oldest_undrained() {
+        let mut ring = Ring::<u32, 4>::new(0);
+        assert_eq!(concat(ring.undrained()), []);
+
+        for i in 0..3 {
+            ring.push(i);
+        }
+        ring.consume(1);
+        assert_eq!(concat(ring.undrained()), [1, 2]);
+        assert_eq!(concat(ring.recent()), [0, 1, 2]);
+
+        // wraps around, overwriting 0 (drained) and 1 (undrained)
+        for i in 3..6 {
+            ring.push(i);
+        }
+        assert_eq!(ring.undrained(), (&[2, 3][..], &[4, 5][..]));
+        assert_eq!(concat(ring.recent()), [2, 3, 4, 5]);
+
+        ring.consume(usize::MAX);
+        assert_eq!(concat(ring.undrained()), []);
+        assert_eq!(concat(ring.recent()), [2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn drain_into_user_buffer() {
+        let mut ring = Ring::<u32, 4>::new(0);
+        for i in 0..6 {
+            ring.push(i);
+        }
+
+        // the undrained items are split across the end of the ring
+        let mut buf = [0; 3];
+        let user = unsafe {
+            crate::util::user_slice::from_raw_parts_mut(
+                ptr::NonNull::new(buf.as_mut_ptr()).unwrap(),
+                buf.len(),
+            )
+        };
+        assert_eq!(ring.drain_into(user), 3);
+        assert_eq!(buf, [2, 3, 4]);
+        assert_eq!(ring.drain_into(user), 1);
+        assert_eq!(buf[0], 5);
+        assert_eq!(ring.drain_into(user), 0);
+    }
+}
@@ -38,3 +38,26 @@ impl EventId {
         Some(EventId::from_interrupt(interrupt))
     }
 }
+
+/// When the kernel is built with its `monitor` feature, it reads each byte
+/// received on COM2 itself (in order to detect the break which enters the
+/// monitor), and hands the byte to userspace via the volatile data of the
+/// `IntUart2` event instead.
+///
+/// Returns the received byte (if any) encoded in an `IntUart2` event's
+/// volatile data.
+pub fn console_rx_byte(volatile_data: usize) -> Option<u8> {
+    if volatile_data & CONSOLE_RX_VALID != 0 {
+        Some((volatile_data >> CONSOLE_RX_SHIFT) as u8)
+    } else {
+        None
+    }
+}
+
+/// Set in an `IntUart2` event's volatile data when it includes a received
+/// byte. See [`console_rx_byte`].
+pub const CONSOLE_RX_VALID: usize = 1 << 16;
+
+/// Offset of the received byte within an `IntUart2` event's volatile data.
+/// See [`console_rx_byte`].
+pub const CONSOLE_RX_SHIFT: usize = 8;
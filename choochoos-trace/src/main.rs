@@ -0,0 +1,398 @@
+//! Converts a choochoos kernel trace into
+//! [Chrome trace-event](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU)
+//! JSON, which can be viewed using `chrome://tracing` or
+//! [Perfetto](https://ui.perfetto.dev).
+//!
+//! The input is either a capture of the kernel's serial output which contains
+//! a trace dump (printed when a kernel built with the `trace` feature shuts
+//! down), or a file of raw records (as returned by the `ReadTrace` syscall).
+//!
+//! ```bash
+//! cargo run -p choochoos-trace -- capture.txt > trace.json
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+
+use abi::syscall::SyscallNo;
+use abi::trace::{EventKind, Record, State, DUMP_BEGIN, DUMP_END, NO_TID};
+use abi::Tid;
+
+/// Extract the records from every trace dump in a serial output capture.
+fn parse_dumps(input: &str) -> Result<Vec<Record>, String> {
+    let mut records = Vec::new();
+    let mut in_dump = false;
+    for (i, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with(DUMP_BEGIN) {
+            in_dump = true;
+        } else if line.starts_with(DUMP_END) {
+            in_dump = false;
+        } else if in_dump {
+            let mut buf = [0; Record::SIZE];
+            if !line.is_ascii() || line.len() != Record::SIZE * 2 {
+                return Err(format!("line {}: malformed trace record", i + 1));
+            }
+            for (j, b) in buf.iter_mut().enumerate() {
+                *b = u8::from_str_radix(&line[j * 2..j * 2 + 2], 16)
+                    .map_err(|e| format!("line {}: malformed trace record ({})", i + 1, e))?;
+            }
+            records.push(Record::from_bytes(&buf));
+        }
+    }
+    Ok(records)
+}
+
+/// Parse a file of raw records.
+fn parse_raw(input: &[u8]) -> Result<Vec<Record>, String> {
+    if input.len() % Record::SIZE != 0 {
+        return Err(format!(
+            "input isn't a trace dump, or a multiple of {} bytes long",
+            Record::SIZE
+        ));
+    }
+
+    let records = input
+        .chunks_exact(Record::SIZE)
+        .map(|chunk| {
+            let mut buf = [0; Record::SIZE];
+            buf.copy_from_slice(chunk);
+            Record::from_bytes(&buf)
+        })
+        .collect();
+    Ok(records)
+}
+
+/// Names of each syscall's arguments (as recorded in the trace).
+fn arg_names(no: SyscallNo) -> &'static [&'static str] {
+    match no {
+        SyscallNo::Create => &["priority", "function"],
+        SyscallNo::Send | SyscallNo::TrySend | SyscallNo::SendTimeout => {
+            &["tid", "msg", "msglen", "reply"]
+        }
+        SyscallNo::Receive => &["tid", "msg", "msglen"],
+        SyscallNo::ReceiveTimeout => &["tid", "msg", "msglen", "timeout"],
+        SyscallNo::Reply => &["tid", "reply", "rplen"],
+        SyscallNo::AwaitEvent => &["event_id"],
+        SyscallNo::Perf => &["perf"],
+        SyscallNo::PerfTask => &["tid", "perf"],
+        SyscallNo::Destroy | SyscallNo::StackUsage => &["tid"],
+        SyscallNo::ReadLog | SyscallNo::ReadTrace => &["buf", "buflen"],
+        SyscallNo::DetectDeadlocks => &["tids", "len"],
+        SyscallNo::SetLogLevel => &["module", "module_len", "level"],
+        SyscallNo::Delay => &["ticks"],
+        SyscallNo::DelayUntil => &["tick"],
+        SyscallNo::MailboxCreate => &["slot_size", "count"],
+        SyscallNo::MailboxPost => &["mailbox", "msg", "msglen"],
+        SyscallNo::MailboxReceive | SyscallNo::MailboxTryReceive => &["mailbox", "msg", "msglen"],
+        SyscallNo::MailboxDestroy => &["mailbox"],
+        SyscallNo::Yield
+        | SyscallNo::Exit
+        | SyscallNo::MyParentTid
+        | SyscallNo::MyTid
+        | SyscallNo::Time
+        | SyscallNo::Shutdown
+        | SyscallNo::Abort => &[],
+    }
+}
+
+/// Format a raw Tid for display.
+fn tid_name(raw: u32) -> String {
+    if raw == NO_TID {
+        return "none".to_string();
+    }
+    let tid = Tid::from(raw as usize);
+    format!("{:#x} (slot {}, gen {})", raw, tid.slot(), tid.generation())
+}
+
+/// Builder for a single trace event (i.e: a JSON object).
+struct Event(String);
+
+impl Event {
+    fn new(ph: &str, name: &str, tid: u32, ts: u64) -> Event {
+        Event(format!(
+            r#"{{"ph":"{}","name":"{}","pid":0,"tid":{},"ts":{}"#,
+            ph, name, tid, ts
+        ))
+    }
+
+    fn field(mut self, key: &str, val: &str) -> Event {
+        write!(self.0, r#","{}":{}"#, key, val).unwrap();
+        self
+    }
+
+    fn args(self, args: &[(&str, String)]) -> Event {
+        let args = args
+            .iter()
+            .map(|(k, v)| format!(r#""{}":"{}""#, k, v))
+            .collect::<Vec<_>>()
+            .join(",");
+        self.field("args", &format!("{{{}}}", args))
+    }
+
+    fn finish(mut self) -> String {
+        self.0.push('}');
+        self.0
+    }
+}
+
+/// Convert trace records into Chrome trace events.
+///
+/// Each task is displayed as a separate thread, with "running" slices for the
+/// time it spent scheduled, and async slices spanning each of its syscalls
+/// (from invocation until it resumes with the return value). Interrupts are
+/// displayed as instant events on a separate "interrupts" thread.
+fn to_chrome_events(records: &[Record]) -> Vec<String> {
+    let mut events = Vec::new();
+    let mut tids = Vec::new();
+    // syscalls which haven't returned yet, by raw tid
+    let mut open_syscalls: HashMap<u32, SyscallNo> = HashMap::new();
+
+    for r in records {
+        let kind = match EventKind::from_u8(r.kind) {
+            Some(kind) => kind,
+            None => {
+                eprintln!("skipping record with unknown kind {}", r.kind);
+                continue;
+            }
+        };
+        let ts = r.timestamp_us;
+
+        // interrupts are displayed on their own thread, not the interrupted task's
+        let thread = match kind {
+            EventKind::Irq => NO_TID,
+            _ => r.tid,
+        };
+        if !tids.contains(&thread) {
+            tids.push(thread);
+        }
+
+        let event = match kind {
+            EventKind::SwitchIn => Event::new("B", "running", r.tid, ts),
+            EventKind::SwitchOut => {
+                let state = match State::from_u32(r.data[0]) {
+                    Some(state) => format!("{:?}", state),
+                    None => format!("unknown ({})", r.data[0]),
+                };
+                let mut args = vec![("state", state)];
+                if r.data[1] != NO_TID {
+                    args.push(("blocked_on", tid_name(r.data[1])));
+                }
+                Event::new("E", "running", r.tid, ts).args(&args)
+            }
+            EventKind::Syscall => {
+                let no = match SyscallNo::from_u8(r.syscall_no) {
+                    Some(no) => no,
+                    None => {
+                        eprintln!("skipping unknown syscall {}", r.syscall_no);
+                        continue;
+                    }
+                };
+                let args = arg_names(no)
+                    .iter()
+                    .zip(r.data.iter())
+                    .map(|(&name, val)| (name, format!("{:#x}", val)))
+                    .collect::<Vec<_>>();
+                open_syscalls.insert(r.tid, no);
+                Event::new("b", &format!("{:?}", no), r.tid, ts)
+                    .field("cat", r#""syscall""#)
+                    .field("id", &r.tid.to_string())
+                    .args(&args)
+            }
+            EventKind::SyscallReturn => match open_syscalls.remove(&r.tid) {
+                // the syscall was made before the start of the trace
+                None => continue,
+                Some(no) => Event::new("e", &format!("{:?}", no), r.tid, ts)
+                    .field("cat", r#""syscall""#)
+                    .field("id", &r.tid.to_string())
+                    .args(&[("ret", (r.data[0] as i32).to_string())]),
+            },
+            EventKind::Irq => Event::new("i", &format!("event {}", r.data[0]), NO_TID, ts)
+                .field("s", r#""t""#)
+                .args(&[
+                    ("volatile_data", format!("{:#x}", r.data[1])),
+                    ("interrupted", tid_name(r.tid)),
+                ]),
+            EventKind::TaskCreate => Event::new("i", "created", r.tid, ts)
+                .field("s", r#""t""#)
+                .args(&[
+                    ("parent", tid_name(r.data[0])),
+                    ("priority", (r.data[1] as i32).to_string()),
+                ]),
+            EventKind::TaskExit => {
+                // syscalls like `Exit` never return
+                if let Some(no) = open_syscalls.remove(&r.tid) {
+                    let event = Event::new("e", &format!("{:?}", no), r.tid, ts)
+                        .field("cat", r#""syscall""#)
+                        .field("id", &r.tid.to_string());
+                    events.push(event.finish());
+                }
+                Event::new("i", "exited", r.tid, ts).field("s", r#""t""#)
+            }
+        };
+        events.push(event.finish());
+    }
+
+    for tid in tids {
+        let name = match tid {
+            NO_TID => "interrupts".to_string(),
+            tid => format!("task {}", tid_name(tid)),
+        };
+        let event = Event::new("M", "thread_name", tid, 0).args(&[("name", name)]);
+        events.push(event.finish());
+    }
+
+    events
+}
+
+fn main() -> Result<(), String> {
+    let mut input = Vec::new();
+    match std::env::args().nth(1) {
+        Some(path) => {
+            input = std::fs::read(&path).map_err(|e| format!("could not read {}: {}", path, e))?
+        }
+        None => {
+            io::stdin()
+                .read_to_end(&mut input)
+                .map_err(|e| format!("could not read stdin: {}", e))?;
+        }
+    }
+
+    let text = String::from_utf8_lossy(&input);
+    let records = if text.contains(DUMP_BEGIN) {
+        parse_dumps(&text)?
+    } else {
+        parse_raw(&input)?
+    };
+
+    let events = to_chrome_events(&records);
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let write = |out: &mut io::StdoutLock| -> io::Result<()> {
+        writeln!(out, "{{\"traceEvents\":[")?;
+        for (i, event) in events.iter().enumerate() {
+            let sep = if i + 1 == events.len() { "" } else { "," };
+            writeln!(out, "{}{}", event, sep)?;
+        }
+        writeln!(out, "]}}")
+    };
+    write(&mut out).map_err(|e| format!("could not write output: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(ts: u64, tid: u32, kind: EventKind, syscall_no: u8, data: [u32; 4]) -> Record {
+        Record {
+            timestamp_us: ts,
+            tid,
+            kind: kind as u8,
+            syscall_no,
+            reserved: 0,
+            data,
+        }
+    }
+
+    /// A task being created, delaying (and blocking), an interrupt waking it
+    /// up, and the task exiting.
+    fn stream() -> Vec<Record> {
+        let delay = SyscallNo::Delay as u8;
+        vec![
+            record(10, 1, EventKind::TaskCreate, 0, [0, 3, 0, 0]),
+            record(20, 1, EventKind::SwitchIn, 0, [0; 4]),
+            record(30, 1, EventKind::Syscall, delay, [5, 0, 0, 0]),
+            record(
+                40,
+                1,
+                EventKind::SwitchOut,
+                0,
+                [State::DelayWait as u32, NO_TID, 0, 0],
+            ),
+            record(50, 1, EventKind::Irq, 0, [4, 0xab, 0, 0]),
+            record(60, 1, EventKind::SwitchIn, 0, [0; 4]),
+            record(70, 1, EventKind::SyscallReturn, 0, [0, 0, 0, 0]),
+            record(80, 1, EventKind::TaskExit, 0, [0; 4]),
+        ]
+    }
+
+    #[test]
+    fn parse_raw_records() {
+        let records = stream();
+        let input = records
+            .iter()
+            .flat_map(|r| r.to_bytes())
+            .collect::<Vec<_>>();
+        assert_eq!(parse_raw(&input), Ok(records));
+    }
+
+    #[test]
+    fn parse_raw_rejects_partial_records() {
+        let input = record(10, 1, EventKind::SwitchIn, 0, [0; 4]).to_bytes();
+        assert!(parse_raw(&input[..Record::SIZE - 1]).is_err());
+        assert!(parse_raw(&[input, input].concat()[..Record::SIZE + 1]).is_err());
+        assert_eq!(parse_raw(&[]), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn parse_dumps_skips_other_output() {
+        let records = stream();
+        let mut input = String::from("booting...\r\n");
+        input.push_str(DUMP_BEGIN);
+        input.push_str("\r\n");
+        for r in &records {
+            for b in r.to_bytes().iter() {
+                write!(input, "{:02x}", b).unwrap();
+            }
+            input.push_str("\r\n");
+        }
+        input.push_str(DUMP_END);
+        input.push_str("\r\nshutting down\r\n");
+        assert_eq!(parse_dumps(&input), Ok(records));
+
+        let malformed = format!(
+            "{}\n{}\n{}\n",
+            DUMP_BEGIN,
+            "zz".repeat(Record::SIZE),
+            DUMP_END
+        );
+        assert!(parse_dumps(&malformed).is_err());
+    }
+
+    #[test]
+    fn chrome_events() {
+        let events = to_chrome_events(&stream());
+        let expected = [
+            r#"{"ph":"i","name":"created","pid":0,"tid":1,"ts":10,"s":"t","args":{"parent":"0x0 (slot 0, gen 0)","priority":"3"}}"#,
+            r#"{"ph":"B","name":"running","pid":0,"tid":1,"ts":20}"#,
+            r#"{"ph":"b","name":"Delay","pid":0,"tid":1,"ts":30,"cat":"syscall","id":1,"args":{"ticks":"0x5"}}"#,
+            r#"{"ph":"E","name":"running","pid":0,"tid":1,"ts":40,"args":{"state":"DelayWait"}}"#,
+            r#"{"ph":"i","name":"event 4","pid":0,"tid":4294967295,"ts":50,"s":"t","args":{"volatile_data":"0xab","interrupted":"0x1 (slot 1, gen 0)"}}"#,
+            r#"{"ph":"B","name":"running","pid":0,"tid":1,"ts":60}"#,
+            r#"{"ph":"e","name":"Delay","pid":0,"tid":1,"ts":70,"cat":"syscall","id":1,"args":{"ret":"0"}}"#,
+            r#"{"ph":"i","name":"exited","pid":0,"tid":1,"ts":80,"s":"t"}"#,
+            r#"{"ph":"M","name":"thread_name","pid":0,"tid":1,"ts":0,"args":{"name":"task 0x1 (slot 1, gen 0)"}}"#,
+            r#"{"ph":"M","name":"thread_name","pid":0,"tid":4294967295,"ts":0,"args":{"name":"interrupts"}}"#,
+        ];
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn chrome_events_close_unreturned_syscalls() {
+        let exit = SyscallNo::Exit as u8;
+        let events = to_chrome_events(&[
+            record(10, 1, EventKind::SyscallReturn, 0, [0; 4]),
+            record(20, 1, EventKind::Syscall, exit, [0; 4]),
+            record(30, 1, EventKind::TaskExit, 0, [0; 4]),
+        ]);
+        let expected = [
+            r#"{"ph":"b","name":"Exit","pid":0,"tid":1,"ts":20,"cat":"syscall","id":1,"args":{}}"#,
+            r#"{"ph":"e","name":"Exit","pid":0,"tid":1,"ts":30,"cat":"syscall","id":1}"#,
+            r#"{"ph":"i","name":"exited","pid":0,"tid":1,"ts":30,"s":"t"}"#,
+            r#"{"ph":"M","name":"thread_name","pid":0,"tid":1,"ts":0,"args":{"name":"task 0x1 (slot 1, gen 0)"}}"#,
+        ];
+        assert_eq!(events, expected);
+    }
+}
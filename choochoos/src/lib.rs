@@ -10,6 +10,8 @@
 pub use nameserver as _;
 
 mod panic;
+#[doc(hidden)]
+pub mod print;
 
 /// (re-export of [`nameserver`])
 /// The choochoos nameserver API.
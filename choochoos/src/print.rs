@@ -0,0 +1,73 @@
+//! Platform-agnostic busy-wait printing.
+//!
+//! On the TS-7200, output is busy-wait written to the requested COM channel.
+//! When running on the host (see the kernel's `platform-host` feature), every
+//! COM channel is mapped to the host process's stdout.
+
+#[cfg(target_arch = "arm")]
+#[doc(hidden)]
+pub use ts7200;
+
+/// Debug macro to output data over a COM channel using busy waiting.
+/// Appends a newline to the output.
+///
+/// e.g:
+///
+/// ```rust
+/// bwprintln!(COM2, "Hello {}!", "World");
+/// ```
+#[cfg(target_arch = "arm")]
+#[macro_export]
+macro_rules! bwprintln {
+    ($($arg:tt)*) => {
+        $crate::print::ts7200::bwprintln!($($arg)*)
+    };
+}
+
+/// Debug macro to output data over a COM channel using busy waiting.
+/// Appends a newline to the output.
+///
+/// e.g:
+///
+/// ```rust
+/// bwprintln!(COM2, "Hello {}!", "World");
+/// ```
+#[cfg(not(target_arch = "arm"))]
+#[macro_export]
+macro_rules! bwprintln {
+    ($com:ident) => { $crate::bwprintln!($com, "") };
+    ($com:ident, $fmt:literal) => { $crate::bwprintln!($com, $fmt,) };
+    ($com:ident, $fmt:literal, $($arg:tt)*) => {
+        $crate::print::host_print(format_args!(concat!($fmt, "\n"), $($arg)*))
+    };
+}
+
+/// Write formatted output to the host process's stdout.
+#[cfg(not(target_arch = "arm"))]
+#[doc(hidden)]
+pub fn host_print(args: core::fmt::Arguments) {
+    use core::fmt::Write;
+
+    // provided by the host's C library, which the (hosted) kernel links with
+    extern "C" {
+        fn write(fd: i32, buf: *const u8, count: usize) -> isize;
+    }
+
+    struct Stdout;
+
+    impl Write for Stdout {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            let mut buf = s.as_bytes();
+            while !buf.is_empty() {
+                let n = unsafe { write(1, buf.as_ptr(), buf.len()) };
+                if n < 0 {
+                    return Err(core::fmt::Error);
+                }
+                buf = &buf[n as usize..];
+            }
+            Ok(())
+        }
+    }
+
+    Stdout.write_fmt(args).unwrap();
+}
@@ -2,12 +2,13 @@
 
 #![deny(missing_docs)]
 #![feature(asm, naked_functions)]
+#![cfg_attr(not(target_arch = "arm"), feature(never_type))]
 #![no_std]
 
 use core::num::NonZeroUsize;
 
 pub use abi;
-pub use abi::{PerfData, Tid};
+pub use abi::{LogLevel, MailboxId, PerfData, Tid};
 
 /// C-FII exposing the interface outlined in the
 /// [CS 452 Kernel Description](https://student.cs.uwaterloo.ca/~cs452/W20/assignments/kernel.html).
@@ -15,18 +16,19 @@ pub use abi::{PerfData, Tid};
 #[allow(missing_docs, clippy::missing_safety_doc)]
 pub mod ffi {
     use abi::syscall::{signature, SyscallNo};
-    use abi::{PerfData, Tid};
+    use abi::{MailboxId, PerfData, TaskPerfData, Tid};
 
     macro_rules! sys {
         (
             $(#[$meta:meta])*
-            fn $name:ident $($sig:tt)*
+            fn $name:ident ($($arg:ident: $arg_ty:ty),* $(,)?) $(-> $ret:ty)?
         ) => {
             $(#[$meta])*
+            #[cfg(target_arch = "arm")]
             #[naked]
             #[no_mangle]
             #[inline(never)] // very important - puts parameters in the right place
-            pub unsafe extern "C" fn $name $($sig)* {
+            pub unsafe extern "C" fn $name($($arg: $arg_ty),*) $(-> $ret)? {
                 asm! {
                     "swi {no}",
                     "bx lr",
@@ -35,11 +37,60 @@ pub mod ffi {
                 loop {}
             }
 
+            $(#[$meta])*
+            #[cfg(not(target_arch = "arm"))]
+            #[no_mangle]
+            pub unsafe extern "C" fn $name($($arg: $arg_ty),*) $(-> $ret)? {
+                let args: &[usize] = &[$(host::to_word($arg)),*];
+                host::FromWord::from_word(host::__choochoos_syscall(
+                    SyscallNo::$name as u8,
+                    args.as_ptr(),
+                    args.len(),
+                ))
+            }
+
             // Ensure that the function signature matches the one defined in `choochoos_abi`
             const _: signature::$name = $name;
         };
     }
 
+    /// Support code for running userspace on the kernel's `platform-host`,
+    /// where syscalls are implemented as plain function calls into the kernel.
+    #[cfg(not(target_arch = "arm"))]
+    mod host {
+        extern "C" {
+            /// Provided by the kernel's host "architecture".
+            pub fn __choochoos_syscall(no: u8, args: *const usize, nargs: usize) -> usize;
+        }
+
+        /// Reinterpret a word-sized syscall argument as a `usize`.
+        pub fn to_word<T: Copy>(val: T) -> usize {
+            assert_eq!(core::mem::size_of::<T>(), core::mem::size_of::<usize>());
+            unsafe { core::mem::transmute_copy(&val) }
+        }
+
+        /// Types which can be returned from a syscall.
+        pub trait FromWord {
+            fn from_word(word: usize) -> Self;
+        }
+
+        impl FromWord for () {
+            fn from_word(_word: usize) {}
+        }
+
+        impl FromWord for isize {
+            fn from_word(word: usize) -> isize {
+                word as isize
+            }
+        }
+
+        impl FromWord for ! {
+            fn from_word(_word: usize) -> ! {
+                unreachable!("non-returning syscall returned")
+            }
+        }
+    }
+
     sys! { fn Yield() }
     sys! { fn Exit() -> ! }
     sys! { fn MyParentTid() -> isize }
@@ -58,6 +109,98 @@ pub mod ffi {
         /// Custom - Terminate the kernel.
         fn Shutdown() -> !
     }
+    sys! {
+        /// Custom - Obtain per-task [`TaskPerfData`]
+        fn PerfTask(tid: Tid, perf: *mut TaskPerfData) -> isize
+    }
+    sys! {
+        /// Custom - Forcefully terminate a task.
+        fn Destroy(tid: Tid) -> isize
+    }
+    sys! {
+        /// Custom - Query a task's stack high-water mark (in bytes).
+        fn StackUsage(tid: Tid) -> isize
+    }
+    sys! {
+        /// Custom - Terminate the kernel, printing a crash dump.
+        fn Abort() -> !
+    }
+    sys! {
+        /// Custom - Drain output from the kernel log.
+        fn ReadLog(buf: *mut u8, buflen: usize) -> isize
+    }
+    sys! {
+        /// Custom - Set the kernel log level of a module (and its children).
+        fn SetLogLevel(module: *const u8, module_len: usize, level: usize) -> isize
+    }
+    sys! {
+        /// Custom - Drain records from the kernel trace buffer.
+        fn ReadTrace(buf: *mut abi::trace::Record, buflen: usize) -> isize
+    }
+    sys! {
+        /// Custom - Report any Send-Receive-Reply deadlocks to the kernel log,
+        /// and return the Tids of the deadlocked tasks.
+        fn DetectDeadlocks(tids: *mut Tid, len: usize) -> isize
+    }
+    sys! {
+        /// Custom - `Send`, failing immediately if the receiver isn't
+        /// receive-blocked.
+        fn TrySend(tid: Tid, msg: *const u8, msglen: usize, reply: *mut u8, rplen: usize) -> isize
+    }
+    sys! {
+        /// Custom - `Send`, failing if the message isn't received within
+        /// `timeout` ticks.
+        fn SendTimeout(
+            tid: Tid,
+            msg: *const u8,
+            msglen: usize,
+            reply: *mut u8,
+            rplen: usize,
+            timeout: usize,
+        ) -> isize
+    }
+    sys! {
+        /// Custom - `Receive`, failing if no message is received within
+        /// `timeout` ticks.
+        fn ReceiveTimeout(tid: *mut Tid, msg: *mut u8, msglen: usize, timeout: usize) -> isize
+    }
+    sys! {
+        /// Custom - Query the number of kernel clock ticks since boot.
+        fn Time() -> isize
+    }
+    sys! {
+        /// Custom - Block for `ticks` kernel clock ticks, returning the
+        /// current tick.
+        fn Delay(ticks: isize) -> isize
+    }
+    sys! {
+        /// Custom - Block until the given kernel clock tick, returning the
+        /// current tick.
+        fn DelayUntil(tick: usize) -> isize
+    }
+    sys! {
+        /// Custom - Create a mailbox holding up to `count` messages of up to
+        /// `slot_size` bytes each, owned by the calling task.
+        fn MailboxCreate(slot_size: usize, count: usize) -> isize
+    }
+    sys! {
+        /// Custom - Post a message to a mailbox (without blocking).
+        fn MailboxPost(mailbox: MailboxId, msg: *const u8, msglen: usize) -> isize
+    }
+    sys! {
+        /// Custom - Receive the oldest message in a mailbox, blocking until
+        /// one is posted if the mailbox is empty.
+        fn MailboxReceive(mailbox: MailboxId, msg: *mut u8, msglen: usize) -> isize
+    }
+    sys! {
+        /// Custom - Receive the oldest message in a mailbox, failing if the
+        /// mailbox is empty.
+        fn MailboxTryReceive(mailbox: MailboxId, msg: *mut u8, msglen: usize) -> isize
+    }
+    sys! {
+        /// Custom - Destroy a mailbox owned by the calling task.
+        fn MailboxDestroy(mailbox: MailboxId) -> isize
+    }
 }
 
 /// Errors which may occur when invoking syscalls.
@@ -76,10 +219,18 @@ pub mod error {
     /// Errors returned by the `Send` syscall.
     #[derive(Debug)]
     pub enum Send {
-        /// `tid` is not the task id of an existing task.
+        /// `tid` is not the task id of an existing task (e.g: the task has
+        /// exited).
         TidDoesNotExist,
         /// The send-receive-reply transaction could not be completed.
         CouldNotSSR,
+        /// The receiver exited (or was destroyed) after receiving the message,
+        /// but before replying to it.
+        ReceiverTerminated,
+        /// The receiver didn't receive the message in time (see
+        /// [`try_send`](crate::try_send) and
+        /// [`send_timeout`](crate::send_timeout)).
+        Timeout,
         /// The reply was truncated. `usize` corresponds to the length of the
         /// original reply.
         Truncated(NonZeroUsize),
@@ -88,6 +239,9 @@ pub mod error {
     /// Errors returned by the `Receive` syscall.
     #[derive(Debug)]
     pub enum Receive {
+        /// No message was sent to the task in time (see
+        /// [`receive_timeout`](crate::receive_timeout)).
+        Timeout,
         /// The message was truncated. `usize` corresponds to the length of the
         /// original message.
         Truncated(NonZeroUsize),
@@ -96,7 +250,8 @@ pub mod error {
     /// Errors returned by the `Reply` syscall.
     #[derive(Debug)]
     pub enum Reply {
-        /// `tid` is not the task id of an existing task.
+        /// `tid` is not the task id of an existing task (e.g: the task has
+        /// exited).
         TidDoesNotExist,
         /// `tid` is not the task id of a reply-blocked task.
         TidIsNotReplyBlocked,
@@ -110,8 +265,109 @@ pub mod error {
     pub enum AwaitEvent {
         /// Invalid event id.
         InvalidEventId,
-        /// Corrupted volatile data.
+        /// Volatile data from one or more occurrences of the event was dropped
+        /// (i.e: the event occurred too many times without being waited on).
         CorruptedVolatileData,
+        /// Too many tasks are already waiting on the event.
+        TooManyWaiters,
+    }
+
+    /// Errors returned by the `Destroy` syscall.
+    #[derive(Debug)]
+    pub enum Destroy {
+        /// `tid` is not the task id of an existing task (e.g: the task has
+        /// exited).
+        TidDoesNotExist,
+        /// `tid` is the calling task's task id (use [`exit`](crate::exit)
+        /// instead).
+        TidIsCurrentTask,
+    }
+
+    /// Errors returned by the `StackUsage` syscall.
+    #[derive(Debug)]
+    pub enum StackUsage {
+        /// `tid` is not the task id of an existing task (e.g: the task has
+        /// exited).
+        TidDoesNotExist,
+        /// Stack usage isn't tracked on this platform (e.g: when running on
+        /// the host).
+        Unsupported,
+    }
+
+    /// Errors returned by the `SetLogLevel` syscall.
+    #[derive(Debug)]
+    pub enum SetLogLevel {
+        /// The kernel has run out of space for per-module filters.
+        TooManyFilters,
+        /// The module path is too long.
+        ModuleTooLong,
+    }
+
+    /// Errors returned by the `Delay` and `DelayUntil` syscalls.
+    #[derive(Debug)]
+    pub enum Delay {
+        /// The kernel wasn't built with the `kernel-clock` feature.
+        ClockDisabled,
+        /// `delay` was called with a negative number of ticks.
+        NegativeDelay,
+    }
+
+    /// Errors returned by the `MailboxCreate` syscall.
+    #[derive(Debug)]
+    pub enum MailboxCreate {
+        /// `slot_size` or `count` is zero.
+        InvalidSize,
+        /// The kernel has run out of mailboxes.
+        OutOfMailboxes,
+        /// The kernel's mailbox pool doesn't have enough free space.
+        OutOfMemory,
+    }
+
+    /// Errors returned by the `MailboxPost` syscall.
+    #[derive(Debug)]
+    pub enum MailboxPost {
+        /// `mailbox` does not refer to an existing mailbox (e.g: it was
+        /// destroyed).
+        MailboxDoesNotExist,
+        /// The message is larger than the mailbox's slot size.
+        MessageTooLarge,
+        /// The mailbox is full.
+        Full,
+    }
+
+    /// Errors returned by the `MailboxReceive` and `MailboxTryReceive`
+    /// syscalls.
+    #[derive(Debug)]
+    pub enum MailboxReceive {
+        /// `mailbox` does not refer to an existing mailbox (e.g: it was
+        /// destroyed).
+        MailboxDoesNotExist,
+        /// The mailbox is empty (see
+        /// [`mailbox_try_receive`](crate::mailbox_try_receive)).
+        Empty,
+        /// The mailbox was destroyed while waiting for a message.
+        MailboxDestroyed,
+        /// The message was truncated. `usize` corresponds to the length of the
+        /// original message.
+        Truncated(NonZeroUsize),
+    }
+
+    /// Errors returned by the `MailboxDestroy` syscall.
+    #[derive(Debug)]
+    pub enum MailboxDestroy {
+        /// `mailbox` does not refer to an existing mailbox (e.g: it was
+        /// already destroyed).
+        MailboxDoesNotExist,
+        /// The mailbox is owned by another task.
+        NotOwner,
+    }
+
+    /// Errors returned by the `PerfTask` syscall.
+    #[derive(Debug)]
+    pub enum PerfTask {
+        /// `tid` is not the task id of an existing task (e.g: the task has
+        /// exited).
+        TidDoesNotExist,
     }
 }
 
@@ -123,9 +379,12 @@ pub fn r#yield() {
 }
 
 /// Causes a task to cease execution permanently. It is removed from all
-/// priority queues, send queues, receive queues and event queues. Resources
-/// owned by the task, primarily its memory and task descriptor, are not
-/// reclaimed.
+/// priority queues, send queues, receive queues and event queues. Any tasks
+/// waiting to send to the task are unblocked with
+/// [`error::Send::CouldNotSSR`], and any tasks waiting for the task to reply
+/// are unblocked with [`error::Send::ReceiverTerminated`]. The task's
+/// descriptor and stack are reclaimed, and may be reused by subsequent calls
+/// to [`create`].
 ///
 /// NOTE: Each task must call `exit` when it returns!
 pub fn exit() -> ! {
@@ -154,6 +413,8 @@ pub fn my_parent_tid() -> Option<Tid> {
 /// and the given function pointer as a pointer to the entry point of
 /// executable code.
 ///
+/// `priority` must be less than [`abi::NUM_PRIORITIES`].
+///
 /// If `create` returns successfully, the task descriptor has all the state
 /// needed to run the task, the task’s stack has been suitably initialized, and
 /// the task has been entered into its ready queue so that it will run the next
@@ -188,7 +449,8 @@ pub fn create(priority: usize, function: extern "C" fn() -> !) -> Result<Tid, er
 ///
 /// There is no guarantee that `send()` will return. If, for example, the task
 /// to which the message is directed never calls `receive()`, `send()` never
-/// returns and the sending task remains blocked forever.
+/// returns and the sending task remains blocked forever (see [`try_send`] and
+/// [`send_timeout`] for alternatives which don't).
 pub fn send(
     tid: Tid,
     msg: impl AsRef<[u8]>,
@@ -237,10 +499,17 @@ fn send_impl(
     replylen: usize,
 ) -> Result<usize, error::Send> {
     let ret = unsafe { ffi::Send(tid, msg, msglen, reply, replylen) };
+    send_result(ret, replylen)
+}
+
+/// Interpret the return value of one of the `Send` syscall variants.
+fn send_result(ret: isize, replylen: usize) -> Result<usize, error::Send> {
     match ret {
         e if ret < 0 => match e {
             -1 => Err(error::Send::TidDoesNotExist),
             -2 => Err(error::Send::CouldNotSSR),
+            -3 => Err(error::Send::ReceiverTerminated),
+            -4 => Err(error::Send::Timeout),
             _ => panic!("unexpected Send error: {}", e),
         },
         rplen => {
@@ -256,6 +525,62 @@ fn send_impl(
     }
 }
 
+/// Custom - Like [`send`], except that it fails with
+/// [`error::Send::Timeout`] instead of blocking if the receiver isn't
+/// already waiting in [`receive`].
+///
+/// Once the message has been received, `try_send` blocks until the receiver
+/// replies (just like [`send`]).
+pub fn try_send(
+    tid: Tid,
+    msg: impl AsRef<[u8]>,
+    mut reply: impl AsMut<[u8]>,
+) -> Result<usize, error::Send> {
+    let msg = msg.as_ref();
+    let reply = reply.as_mut();
+    let ret = unsafe {
+        ffi::TrySend(
+            tid,
+            msg.as_ptr(),
+            msg.len(),
+            reply.as_mut_ptr(),
+            reply.len(),
+        )
+    };
+    send_result(ret, reply.len())
+}
+
+/// Custom - Like [`send`], except that it fails with
+/// [`error::Send::Timeout`] if the receiver doesn't receive the message within
+/// `timeout` clock ticks (where a `timeout` of 0 is equivalent to
+/// [`try_send`]).
+///
+/// Once the message has been received, `send_timeout` blocks until the
+/// receiver replies (just like [`send`]).
+///
+/// Timeouts require a kernel built with the `kernel-clock` feature. Otherwise,
+/// every `timeout` is treated as 0.
+pub fn send_timeout(
+    tid: Tid,
+    msg: impl AsRef<[u8]>,
+    mut reply: impl AsMut<[u8]>,
+    timeout: usize,
+) -> Result<usize, error::Send> {
+    let msg = msg.as_ref();
+    let reply = reply.as_mut();
+    let ret = unsafe {
+        ffi::SendTimeout(
+            tid,
+            msg.as_ptr(),
+            msg.len(),
+            reply.as_mut_ptr(),
+            reply.len(),
+            timeout,
+        )
+    };
+    send_result(ret, reply.len())
+}
+
 /// Blocks until a message is sent to the caller, returning the Tid of the task
 /// that sent the message and the number of bytes in the message.
 ///
@@ -272,11 +597,36 @@ pub fn receive(mut msg: impl AsMut<[u8]>) -> Result<(Tid, usize), error::Receive
 fn receive_impl(msg: &mut [u8]) -> Result<(Tid, usize), error::Receive> {
     let mut tid = Tid::from(0);
     let ret = unsafe { ffi::Receive(&mut tid, msg.as_mut_ptr(), msg.len()) };
+    receive_result(ret, tid, msg.len())
+}
+
+/// Custom - Like [`receive`], except that it fails with
+/// [`error::Receive::Timeout`] if no message is sent to the caller within
+/// `timeout` clock ticks (where a `timeout` of 0 only receives messages which
+/// are already queued).
+///
+/// Timeouts require a kernel built with the `kernel-clock` feature. Otherwise,
+/// every `timeout` is treated as 0.
+pub fn receive_timeout(
+    mut msg: impl AsMut<[u8]>,
+    timeout: usize,
+) -> Result<(Tid, usize), error::Receive> {
+    let msg = msg.as_mut();
+    let mut tid = Tid::from(0);
+    let ret = unsafe { ffi::ReceiveTimeout(&mut tid, msg.as_mut_ptr(), msg.len(), timeout) };
+    receive_result(ret, tid, msg.len())
+}
+
+/// Interpret the return value of one of the `Receive` syscall variants.
+fn receive_result(ret: isize, tid: Tid, msgbuflen: usize) -> Result<(Tid, usize), error::Receive> {
     match ret {
-        e if ret < 0 => panic!("unexpected Receive error: {}", e),
+        e if ret < 0 => match e {
+            -1 => Err(error::Receive::Timeout),
+            _ => panic!("unexpected Receive error: {}", e),
+        },
         msglen => {
             let msglen = msglen as usize;
-            if msglen > msg.len() {
+            if msglen > msgbuflen {
                 // SAFETY: if msglen was zero, then `0 > msg.len(): usize` would never trigger
                 let msglen = unsafe { NonZeroUsize::new_unchecked(msglen) };
                 Err(error::Receive::Truncated(msglen))
@@ -325,12 +675,17 @@ fn reply_impl(tid: Tid, reply: &[u8]) -> Result<(), error::Reply> {
 /// volatile data (if applicable).
 ///
 /// Valid `event_id` numbers vary based on target platform.
+///
+/// Multiple tasks may wait on the same event. Depending on the event, either
+/// all waiting tasks are unblocked when it occurs, or only the highest
+/// priority one is (see the kernel platform's `wake_policy`).
 pub fn await_event(event_id: usize) -> Result<usize, error::AwaitEvent> {
     let ret = unsafe { ffi::AwaitEvent(event_id) };
     match ret {
         e if ret < 0 => match e {
             -1 => Err(error::AwaitEvent::InvalidEventId),
             -2 => Err(error::AwaitEvent::CorruptedVolatileData),
+            -3 => Err(error::AwaitEvent::TooManyWaiters),
             _ => panic!("unexpected AwaitEvent error: {}", e),
         },
         volatile => Ok(volatile as usize),
@@ -346,7 +701,257 @@ pub fn perf() -> abi::PerfData {
     }
 }
 
+/// Custom - Obtain per-task [`TaskPerfData`](abi::TaskPerfData).
+pub fn perf_task(tid: Tid) -> Result<abi::TaskPerfData, error::PerfTask> {
+    let mut perf_data = core::mem::MaybeUninit::<abi::TaskPerfData>::uninit();
+    let ret = unsafe { ffi::PerfTask(tid, perf_data.as_mut_ptr()) };
+    match ret {
+        e if ret < 0 => match e {
+            -1 => Err(error::PerfTask::TidDoesNotExist),
+            _ => panic!("unexpected PerfTask error: {}", e),
+        },
+        // SAFETY: the kernel initialized the struct
+        _ => Ok(unsafe { perf_data.assume_init() }),
+    }
+}
+
+/// Custom - Forcefully terminate a task.
+///
+/// The task is removed from all priority queues, send queues, receive queues
+/// and event queues. Any tasks waiting to send to the task are unblocked with
+/// [`error::Send::CouldNotSSR`], and any tasks waiting for the task to reply
+/// are unblocked with [`error::Send::ReceiverTerminated`]. The task's
+/// descriptor and stack are reclaimed, and may be reused by subsequent calls
+/// to [`create`].
+pub fn destroy(tid: Tid) -> Result<(), error::Destroy> {
+    let ret = unsafe { ffi::Destroy(tid) };
+    match ret {
+        0 => Ok(()),
+        -1 => Err(error::Destroy::TidDoesNotExist),
+        -2 => Err(error::Destroy::TidIsCurrentTask),
+        e => panic!("unexpected Destroy error: {}", e),
+    }
+}
+
+/// Custom - Query the maximum number of bytes of stack the task has used
+/// since it was created (i.e: its stack high-water mark).
+///
+/// Only the top `CHOOCHOOS_STACK_PAINT_SIZE` bytes of each stack are tracked,
+/// so the high-water mark saturates at that size. Tasks which overflow their
+/// stack are killed by the kernel.
+pub fn stack_usage(tid: Tid) -> Result<usize, error::StackUsage> {
+    let ret = unsafe { ffi::StackUsage(tid) };
+    match ret {
+        e if ret < 0 => match e {
+            -1 => Err(error::StackUsage::TidDoesNotExist),
+            -2 => Err(error::StackUsage::Unsupported),
+            _ => panic!("unexpected StackUsage error: {}", e),
+        },
+        bytes => Ok(bytes as usize),
+    }
+}
+
+/// Custom - Drain output from the kernel log into `buf`, returning the number
+/// of bytes read (which is 0 if there is no new output).
+///
+/// Log output is text, with each message terminated by a newline. Messages may
+/// be split across reads. If the kernel log fills up before it's drained, the
+/// oldest unread output is lost.
+pub fn read_log(buf: &mut [u8]) -> usize {
+    unsafe { ffi::ReadLog(buf.as_mut_ptr(), buf.len()) as usize }
+}
+
+/// Custom - Drain records from the kernel trace buffer into `buf`, returning
+/// the number of records read (which is 0 if there are no new records, or if
+/// the kernel wasn't built with the `trace` feature).
+///
+/// If the trace buffer fills up before it's drained, the oldest unread records
+/// are lost.
+pub fn read_trace(buf: &mut [abi::trace::Record]) -> usize {
+    unsafe { ffi::ReadTrace(buf.as_mut_ptr(), buf.len()) as usize }
+}
+
+/// Custom - Check for Send-Receive-Reply deadlocks, i.e: cycles of tasks which
+/// are each blocked sending to (or waiting for a reply from) the next task in
+/// the cycle.
+///
+/// Every deadlocked cycle is reported to the kernel log. The Tids of the
+/// deadlocked tasks are written into `tids` (cycle by cycle, with each task
+/// blocked on the next), and the total number of deadlocked tasks is returned
+/// (which may exceed `tids.len()`).
+///
+/// The kernel also checks for (and reports) new deadlocks whenever it runs out
+/// of ready tasks.
+pub fn detect_deadlocks(tids: &mut [Tid]) -> usize {
+    unsafe { ffi::DetectDeadlocks(tids.as_mut_ptr(), tids.len()) as usize }
+}
+
+/// Custom - Returns the number of kernel clock ticks (of 10ms each) since
+/// boot.
+///
+/// The tick counter is derived from the kernel's monotonic uptime, and is
+/// available even if the kernel wasn't built with the `kernel-clock` feature.
+pub fn time() -> usize {
+    unsafe { ffi::Time() as usize }
+}
+
+/// Custom - Blocks for (at least) `ticks` kernel clock ticks, returning the
+/// current tick once the task wakes up. A delay of 0 ticks returns
+/// immediately, while a negative delay is an error.
+///
+/// Requires a kernel built with the `kernel-clock` feature, in which case the
+/// kernel wakes the task directly (i.e: no clock server task is required).
+pub fn delay(ticks: isize) -> Result<usize, error::Delay> {
+    delay_result(unsafe { ffi::Delay(ticks) })
+}
+
+/// Custom - Blocks until the kernel clock reaches `tick` (returning
+/// immediately if it already has), returning the current tick once the task
+/// wakes up.
+///
+/// Requires a kernel built with the `kernel-clock` feature, in which case the
+/// kernel wakes the task directly (i.e: no clock server task is required).
+pub fn delay_until(tick: usize) -> Result<usize, error::Delay> {
+    delay_result(unsafe { ffi::DelayUntil(tick) })
+}
+
+/// Interpret the return value of the `Delay` / `DelayUntil` syscalls.
+fn delay_result(ret: isize) -> Result<usize, error::Delay> {
+    match ret {
+        e if ret < 0 => match e {
+            -1 => Err(error::Delay::ClockDisabled),
+            -2 => Err(error::Delay::NegativeDelay),
+            _ => panic!("unexpected Delay error: {}", e),
+        },
+        tick => Ok(tick as usize),
+    }
+}
+
+/// Custom - Create a bounded mailbox, which holds up to `count` messages of up
+/// to `slot_size` bytes each.
+///
+/// Storage for the mailbox's messages is allocated from a fixed-size kernel
+/// pool (see `CHOOCHOOS_MAILBOX_POOL_SIZE`). The mailbox is owned by the
+/// calling task, and is destroyed when the task exits (or is destroyed).
+pub fn mailbox_create(slot_size: usize, count: usize) -> Result<MailboxId, error::MailboxCreate> {
+    let ret = unsafe { ffi::MailboxCreate(slot_size, count) };
+    match ret {
+        e if ret < 0 => match e {
+            -1 => Err(error::MailboxCreate::InvalidSize),
+            -2 => Err(error::MailboxCreate::OutOfMailboxes),
+            -3 => Err(error::MailboxCreate::OutOfMemory),
+            _ => panic!("unexpected MailboxCreate error: {}", e),
+        },
+        id => Ok(MailboxId::from(id as usize)),
+    }
+}
+
+/// Custom - Post a message to a mailbox, without waiting for it to be
+/// received.
+///
+/// The message is copied into the mailbox (or directly to the task which has
+/// been waiting on the mailbox the longest), so `msg` may be reused as soon as
+/// the call returns. Fails with [`error::MailboxPost::Full`] if the mailbox
+/// has no free slots.
+pub fn mailbox_post(mailbox: MailboxId, msg: &[u8]) -> Result<(), error::MailboxPost> {
+    let ret = unsafe { ffi::MailboxPost(mailbox, msg.as_ptr(), msg.len()) };
+    match ret {
+        0 => Ok(()),
+        -1 => Err(error::MailboxPost::MailboxDoesNotExist),
+        -2 => Err(error::MailboxPost::MessageTooLarge),
+        -3 => Err(error::MailboxPost::Full),
+        e => panic!("unexpected MailboxPost error: {}", e),
+    }
+}
+
+/// Custom - Receive the oldest message in a mailbox, blocking until one is
+/// posted if the mailbox is empty. Returns the number of bytes in the message.
+///
+/// Tasks waiting on the same mailbox receive messages in first-come,
+/// first-served order. If the message doesn't fit in `msg`, it's truncated,
+/// and [`error::MailboxReceive::Truncated`] is returned.
+pub fn mailbox_receive(
+    mailbox: MailboxId,
+    mut msg: impl AsMut<[u8]>,
+) -> Result<usize, error::MailboxReceive> {
+    let msg = msg.as_mut();
+    let ret = unsafe { ffi::MailboxReceive(mailbox, msg.as_mut_ptr(), msg.len()) };
+    mailbox_receive_result(ret, msg.len())
+}
+
+/// Custom - Like [`mailbox_receive`], except that it fails with
+/// [`error::MailboxReceive::Empty`] instead of blocking if the mailbox is
+/// empty.
+pub fn mailbox_try_receive(
+    mailbox: MailboxId,
+    mut msg: impl AsMut<[u8]>,
+) -> Result<usize, error::MailboxReceive> {
+    let msg = msg.as_mut();
+    let ret = unsafe { ffi::MailboxTryReceive(mailbox, msg.as_mut_ptr(), msg.len()) };
+    mailbox_receive_result(ret, msg.len())
+}
+
+/// Interpret the return value of the `MailboxReceive` variants.
+fn mailbox_receive_result(ret: isize, msgbuflen: usize) -> Result<usize, error::MailboxReceive> {
+    match ret {
+        e if ret < 0 => match e {
+            -1 => Err(error::MailboxReceive::MailboxDoesNotExist),
+            -2 => Err(error::MailboxReceive::Empty),
+            -3 => Err(error::MailboxReceive::MailboxDestroyed),
+            _ => panic!("unexpected MailboxReceive error: {}", e),
+        },
+        msglen => {
+            let msglen = msglen as usize;
+            if msglen > msgbuflen {
+                // SAFETY: if msglen was zero, then `0 > msg.len(): usize` would never trigger
+                let msglen = unsafe { NonZeroUsize::new_unchecked(msglen) };
+                Err(error::MailboxReceive::Truncated(msglen))
+            } else {
+                Ok(msglen)
+            }
+        }
+    }
+}
+
+/// Custom - Destroy a mailbox owned by the calling task, freeing its storage.
+///
+/// Any undelivered messages are discarded, and any tasks waiting on the
+/// mailbox are unblocked with [`error::MailboxReceive::MailboxDestroyed`].
+pub fn mailbox_destroy(mailbox: MailboxId) -> Result<(), error::MailboxDestroy> {
+    let ret = unsafe { ffi::MailboxDestroy(mailbox) };
+    match ret {
+        0 => Ok(()),
+        -1 => Err(error::MailboxDestroy::MailboxDoesNotExist),
+        -2 => Err(error::MailboxDestroy::NotOwner),
+        e => panic!("unexpected MailboxDestroy error: {}", e),
+    }
+}
+
+/// Custom - Set the kernel log level of `module` (and its children), e.g:
+/// `"kernel::syscalls::send"`. Module paths are relative to the kernel crate's
+/// root, and an empty `module` sets the level of every module without a more
+/// specific filter.
+///
+/// Initial log levels are set at build time (see `CHOOCHOOS_LOG`).
+pub fn set_log_level(module: &str, level: LogLevel) -> Result<(), error::SetLogLevel> {
+    let ret = unsafe { ffi::SetLogLevel(module.as_ptr(), module.len(), level as usize) };
+    match ret {
+        0 => Ok(()),
+        -2 => Err(error::SetLogLevel::TooManyFilters),
+        -3 => Err(error::SetLogLevel::ModuleTooLong),
+        e => panic!("unexpected SetLogLevel error: {}", e),
+    }
+}
+
 /// Custom - Terminate the kernel.
 pub fn shutdown() -> ! {
     unsafe { ffi::Shutdown() }
 }
+
+/// Custom - Terminate the kernel, printing a crash dump which includes the
+/// calling task's registers and backtrace.
+///
+/// Used by the userspace panic handler.
+pub fn abort() -> ! {
+    unsafe { ffi::Abort() }
+}
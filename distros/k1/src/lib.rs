@@ -8,7 +8,7 @@ pub mod writeup {
 }
 
 use choochoos::sys;
-use ts7200::bwprintln;
+use choochoos::bwprintln;
 
 extern "C" fn other_task() -> ! {
     bwprintln!(
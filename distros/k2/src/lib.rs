@@ -1,7 +1,7 @@
 #![no_std]
 
 use choochoos::sys;
-use ts7200::bwprintln;
+use choochoos::bwprintln;
 
 #[no_mangle]
 pub extern "C" fn FirstUserTask() -> ! {
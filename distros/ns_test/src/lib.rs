@@ -1,7 +1,7 @@
 #![no_std]
 
 use choochoos::{ns, sys};
-use ts7200::bwprintln;
+use choochoos::bwprintln;
 
 extern "C" fn task_1() -> ! {
     ns::register_as("Task1").unwrap();
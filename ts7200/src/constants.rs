@@ -20,6 +20,7 @@ pub mod timer {
 pub mod uart {
     pub const UART1_BASE: u32 = 0x808c_0000;
     pub const UART2_BASE: u32 = 0x808d_0000;
+    pub const UART3_BASE: u32 = 0x808e_0000;
 
     pub const DATA_OFFSET: u32 = 0x0; // low 8 bits
     pub const DATA_MASK: u32 = 0xff;
@@ -33,7 +33,7 @@ impl Uart {
             base: match channel {
                 Channel::COM1 => uart::UART1_BASE,
                 Channel::COM2 => uart::UART2_BASE,
-                Channel::COM3 => uart::UART2_BASE + 0x10000,
+                Channel::COM3 => uart::UART3_BASE,
             },
             _not_sync: core::marker::PhantomData,
         }
@@ -65,6 +65,19 @@ impl Uart {
         }
     }
 
+    /// Reads a byte if the UART has received data, without blocking.
+    pub fn try_read_byte(&self) -> Option<u8> {
+        let flags = (self.base + uart::FLAG_OFFSET) as *const u8;
+        let data = (self.base + uart::DATA_OFFSET) as *mut u8;
+
+        unsafe {
+            if ptr::read_volatile(flags) & uart::RXFF_MASK == 0 {
+                return None;
+            }
+            Some(ptr::read_volatile(data) as u8)
+        }
+    }
+
     /// Writes a byte by busy-waiting until the UART is ready to accept data.
     pub fn write_byte_blocking(&self, b: u8) {
         let flags = (self.base + uart::FLAG_OFFSET) as *const u8;